@@ -0,0 +1,84 @@
+// Minimum number of earlier transactions in a category before new ones are judged
+const MIN_HISTORY: usize = 5;
+// Modified z-score (based on median and MAD) above which an amount is unusual
+const MAD_THRESHOLD: f64 = 3.5;
+// Classic z-score threshold, used when most of the history has the exact same amount
+const Z_THRESHOLD: f64 = 3.0;
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        Some((sorted[mid - 1] + sorted[mid]) / 2.0)
+    } else {
+        Some(sorted[mid])
+    }
+}
+
+// Decide whether `amount` stands out from the earlier amounts of the same category
+pub fn is_unusual(amount: f64, history: &[f64]) -> bool {
+    if history.len() < MIN_HISTORY {
+        return false;
+    }
+    let amount = amount.abs();
+    let amounts: Vec<f64> = history.iter().map(|a| a.abs()).collect();
+    let median = median(&amounts).unwrap_or(0.0);
+    let deviations: Vec<f64> = amounts.iter().map(|a| (a - median).abs()).collect();
+    let mad = self::median(&deviations).unwrap_or(0.0);
+
+    if mad > 0.0 {
+        return 0.6745 * (amount - median).abs() / mad > MAD_THRESHOLD;
+    }
+
+    // More than half of the history is identical, fall back to mean and standard deviation
+    let mean = amounts.iter().sum::<f64>() / amounts.len() as f64;
+    let variance = amounts.iter().map(|a| (a - mean).powi(2)).sum::<f64>() / amounts.len() as f64;
+    let std_dev = variance.sqrt();
+    if std_dev > 0.0 {
+        (amount - mean).abs() / std_dev > Z_THRESHOLD
+    } else {
+        // Every earlier amount was the same, anything else is unexpected
+        (amount - median).abs() > f64::EPSILON
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_of_odd_and_even_lengths() {
+        assert_eq!(median(&[]), None);
+        assert_eq!(median(&[3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), Some(2.5));
+    }
+
+    #[test]
+    fn amounts_far_from_the_median_are_unusual() {
+        let history = [-10.0, -12.0, -11.0, -9.0, -13.0];
+        assert!(!is_unusual(-12.5, &history));
+        // Modified z-score of 0.6745 * 5 / 1 = 3.37 is still below the threshold
+        assert!(!is_unusual(-16.0, &history));
+        // 0.6745 * 6 / 1 = 4.05 is above it
+        assert!(is_unusual(-17.0, &history));
+        assert!(is_unusual(-100.0, &history));
+        // Too little history to judge
+        assert!(!is_unusual(-100.0, &history[..MIN_HISTORY - 1]));
+    }
+
+    #[test]
+    fn mostly_identical_history_falls_back_to_the_standard_deviation() {
+        // The MAD is 0, so the mean (12.0) and standard deviation (4.0) are used
+        let history = [10.0, 10.0, 10.0, 20.0, 10.0, 10.0, 10.0, 10.0, 20.0, 10.0];
+        assert!(!is_unusual(24.0, &history));
+        assert!(is_unusual(25.0, &history));
+
+        let history = [10.0; 6];
+        assert!(!is_unusual(-10.0, &history));
+        assert!(is_unusual(10.01, &history));
+    }
+}
@@ -1,15 +1,41 @@
-use crate::data::{Data, Transaction, Transactions};
+use crate::bills::{self, REMINDER_DAYS};
+use crate::budget;
+use crate::data::{Data, Source, Status, Transaction, Transactions, Transfer};
+use crate::investments::{self, Prices, PRICES_FILE};
+use crate::loans::{self, Strategy};
+use crate::payee;
+use crate::reconcile::Statement;
+use crate::refund;
+use crate::report::{Grouping, Period};
+use crate::shared;
+use crate::splits;
+use crate::storage::{Query, Storage};
+use crate::tags;
+use crate::tax;
 use crate::ui::Ui;
-use chrono::NaiveDate;
-use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use chrono::{Days, Local, NaiveDate};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use ratatui::{
     prelude::*,
     style::{Color, Style},
     widgets::{Block, BorderType, Borders, ListState, Padding, ScrollbarState, TableState},
 };
 use std::error::Error;
+use std::fmt;
 use std::time::{Duration, Instant};
-use tui_textarea::TextArea;
+use tui_textarea::{CursorMove, TextArea};
+
+// Number of input fields in the Add Expense and Add Income forms
+const INPUT_COUNT: usize = 7;
+// Step of the +/- keys adjusting the extra monthly payment in the Debts tab
+const DEBT_EXTRA_STEP: f64 = 50.0;
+// Amount the +/- keys put into or take out of an envelope in the Budget tab
+const BUDGET_STEP: f64 = 10.0;
+// How long the footer shows what Ctrl+Z or Ctrl+Y undid or redid, and why a change failed
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+const ERROR_DURATION: Duration = Duration::from_secs(10);
+// How often the TUI checks whether another program changed the ledger
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Tab {
@@ -18,17 +44,60 @@ pub enum Tab {
     AddExpense,
     AddIncome,
     Report,
+    Calendar,
+    Debts,
+    Budget,
 }
 
-impl ToString for Tab {
-    fn to_string(&self) -> String {
-        match self {
-            Tab::Home => "Home".to_string(),
-            Tab::Transactions => "Transactions".to_string(),
-            Tab::AddExpense => "Add Expense".to_string(),
-            Tab::AddIncome => "Add Income".to_string(),
-            Tab::Report => "Report".to_string(),
-        }
+impl fmt::Display for Tab {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Tab::Home => "Home",
+            Tab::Transactions => "Transactions",
+            Tab::AddExpense => "Add Expense",
+            Tab::AddIncome => "Add Income",
+            Tab::Report => "Report",
+            Tab::Calendar => "Calendar",
+            Tab::Debts => "Debts",
+            Tab::Budget => "Budget",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Sub-views of the Report tab, switched with the left and right arrow keys
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportView {
+    Overview,
+    Comparison,
+    Tax,
+    Reimbursable,
+    NetWorth,
+    Balances,
+}
+
+impl ReportView {
+    pub const ALL: [ReportView; 6] = [
+        ReportView::Overview,
+        ReportView::Comparison,
+        ReportView::Tax,
+        ReportView::Reimbursable,
+        ReportView::NetWorth,
+        ReportView::Balances,
+    ];
+}
+
+impl fmt::Display for ReportView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ReportView::Overview => "Overview",
+            ReportView::Comparison => "Comparison",
+            ReportView::Tax => "Tax Year",
+            ReportView::Reimbursable => "Reimbursable",
+            ReportView::NetWorth => "Net Worth",
+            ReportView::Balances => "Balances",
+        };
+        write!(f, "{}", name)
     }
 }
 
@@ -40,20 +109,50 @@ pub struct App {
     pub tabs: Vec<Tab>,
     pub transactions: Transactions,
     pub amount_input: TextArea<'static>,
+    pub payee_input: TextArea<'static>,
     pub category_input: TextArea<'static>,
     pub date_input: TextArea<'static>,
     pub description_input: TextArea<'static>,
+    pub tags_input: TextArea<'static>,
+    pub splits_input: TextArea<'static>,
+    pub editing: Option<u32>, // Id of the transaction being edited in the form, if any
+    pub refund_of: Option<u32>, // Id of the expense the form is entering a refund for, if any
     pub active_input: usize,
     pub cursor_visible: bool,
     pub last_tick: Instant,
     pub transaction_type: TransactionType,
-    pub input_modified: [bool; 4],
+    pub input_modified: [bool; INPUT_COUNT],
+    pub payee_suggestions: Vec<String>,
+    pub tag_suggestions: Vec<String>,
+    pub tag_filter: Option<String>,
+    pub show_details: bool, // Whether the details and change log of the selected transaction are shown
+    pub statement_input: Option<TextArea<'static>>, // Statement balance and date being entered
+    pub statement: Option<Statement>, // Statement being reconciled against in reconcile mode
     pub expense_categories: &'static [&'static str],
     pub income_categories: &'static [&'static str],
     pub table_state: TableState,
     pub vertical_scroll_state: ScrollbarState,
     pub vertical_scroll: usize,
     pub category_list_state: ListState,
+    pub report_view: ReportView,
+    pub report_period: Period,
+    pub report_grouping: Grouping,
+    pub report_anchor: NaiveDate,
+    pub calendar_date: NaiveDate,
+    pub day_transactions: Vec<Transaction>, // Transactions on the selected calendar day
+    pub calendar_period: Period,
+    pub tax_year: i32,
+    pub debt_selected: usize, // Index of the loan highlighted in the Debts tab
+    pub debt_strategy: Strategy,
+    pub debt_extra: f64, // Extra money per month the payoff planner puts towards debts
+    pub bill_popup: bool, // Whether the reminder popup shown on startup is still open
+    pub bill_selected: usize, // Index of the reminder highlighted on the Home tab and in the popup
+    pub budget_month: NaiveDate, // First day of the month shown in the Budget tab
+    pub budget_selected: usize, // Index of the envelope highlighted in the Budget tab
+    pub budget_error: Option<String>, // Why the last change to the envelopes was refused
+    pub prices: Result<Prices, String>, // Price history for valuing investments, or why it failed to load
+    pub toast: Option<(String, Instant)>, // Message for the footer, e.g. what the latest undo did, and until when
+    pub last_reload_check: Instant, // When the ledger was last checked for changes by other programs
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -63,26 +162,53 @@ pub enum TransactionType {
     Income,
 }
 
+impl TransactionType {
+    // Name of the list the transaction is stored in
+    pub fn key(&self) -> &'static str {
+        match self {
+            TransactionType::Expense => "expenses",
+            TransactionType::Income => "income",
+        }
+    }
+}
+
 impl App {
-    pub fn new() -> Result<Self, Box<dyn Error>> {
-        let data = Data::new("transactions.json")?;
+    // App working on the ledger kept in `storage`
+    pub fn with_storage(storage: Box<dyn Storage>) -> Result<Self, Box<dyn Error>> {
+        let mut data = Data::with_storage(storage)?;
+        data.set_source(Source::Tui);
         let transactions = data.get_transactions().clone();
+        let tax_year = tax::fiscal_year_of(data.get_tax_settings(), Local::now().date_naive());
         let mut app = Self {
             data,
             current_tab: Tab::Home,
-            input_modified: [false; 4],
+            input_modified: [false; INPUT_COUNT],
+            payee_suggestions: Vec::new(),
+            tag_suggestions: Vec::new(),
+            tag_filter: None,
+            show_details: false,
+            statement_input: None,
+            statement: None,
             tabs: vec![
                 Tab::Home,
                 Tab::Transactions,
                 Tab::AddExpense,
                 Tab::AddIncome,
                 Tab::Report,
+                Tab::Calendar,
+                Tab::Debts,
+                Tab::Budget,
             ],
             transaction_type: TransactionType::Expense,
             amount_input: TextArea::default(),
+            payee_input: TextArea::default(),
             category_input: TextArea::default(),
             date_input: TextArea::default(),
             description_input: TextArea::default(),
+            tags_input: TextArea::default(),
+            splits_input: TextArea::default(),
+            editing: None,
+            refund_of: None,
             active_input: 0,
             cursor_visible: false,
             last_tick: Instant::now(),
@@ -102,6 +228,7 @@ impl App {
                     "Salary".to_string(),
                     "Interest".to_string(),
                     "Gifts".to_string(),
+                    "Dividends".to_string(),
                     "Other".to_string(),
                 ],
             },
@@ -117,22 +244,46 @@ impl App {
                 "Bills",
                 "Other",
             ],
-            income_categories: &["Salary", "Interest", "Gifts", "Other"],
+            income_categories: &["Salary", "Interest", "Gifts", "Dividends", "Other"],
             category_list_state: ListState::default(),
+            report_view: ReportView::Overview,
+            report_period: Period::Month,
+            report_grouping: Grouping::Category,
+            report_anchor: Period::Month.start_of(Local::now().date_naive()),
+            calendar_date: Local::now().date_naive(),
+            day_transactions: Vec::new(),
+            calendar_period: Period::Month,
+            tax_year,
+            debt_selected: 0,
+            debt_strategy: Strategy::Avalanche,
+            debt_extra: 0.0,
+            bill_popup: false,
+            bill_selected: 0,
+            budget_month: Period::Month.start_of(Local::now().date_naive()),
+            budget_selected: 0,
+            budget_error: None,
+            prices: investments::load_prices(PRICES_FILE).map_err(|e| e.to_string()),
+            toast: None,
+            last_reload_check: Instant::now(),
         };
         app.reset_inputs();
+        app.refresh_day_transactions();
         Ok(app)
     }
 
     pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
         let mut ui = Ui::new()?;
+        // Surface overdue bills and bills due soon before anything else
+        self.bill_popup = !self.bill_reminders().is_empty();
         loop {
             self.update_cursor();
+            if self.last_reload_check.elapsed() >= RELOAD_INTERVAL {
+                self.reload_if_changed();
+                self.post_due_transactions();
+            }
             ui.draw(self)?;
-            if event::poll(Duration::from_millis(100))? {
-                if self.handle_input()? {
-                    break;
-                }
+            if event::poll(Duration::from_millis(100))? && self.handle_input()? {
+                break;
             }
         }
         Ok(())
@@ -141,31 +292,132 @@ impl App {
     fn handle_input(&mut self) -> Result<bool, Box<dyn Error>> {
         if let Event::Key(key) = event::read()? {
             if key.kind == KeyEventKind::Press {
-                match key.code {
-                    KeyCode::Tab => self.next_tab(),
-                    KeyCode::BackTab => self.previous_tab(),
-                    KeyCode::Esc => return Ok(true), // Quit the app
-                    _ => self.handle_tab_specific_input(key),
-                }
+                return Ok(self.handle_key(key));
             }
         }
         Ok(false)
     }
 
+    // Handle a key press; returns true when the app should quit
+    fn handle_key(&mut self, key: event::KeyEvent) -> bool {
+        if self.bill_popup {
+            match key.code {
+                KeyCode::Esc | KeyCode::Enter => self.bill_popup = false,
+                _ => self.handle_bills_input(key),
+            }
+            return false;
+        }
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('z') if control => self.undo(false),
+            KeyCode::Char('y') if control => self.undo(true),
+            KeyCode::Tab => self.next_tab(),
+            KeyCode::BackTab => self.previous_tab(),
+            // Leave reconcile mode instead of quitting
+            KeyCode::Esc
+                if self.current_tab == Tab::Transactions
+                    && (self.statement_input.is_some() || self.statement.is_some()) =>
+            {
+                self.statement_input = None;
+                self.statement = None;
+            }
+            KeyCode::Esc => return true, // Quit the app
+            _ => self.handle_tab_specific_input(key),
+        }
+        false
+    }
+
+    // Revert the latest change, or repeat the latest undone one, and say which in the footer
+    fn undo(&mut self, redo: bool) {
+        let result = if redo {
+            self.data.redo()
+        } else {
+            self.data.undo()
+        };
+        let message = match result {
+            Ok(Some(description)) if redo => format!("Redid: {}", description),
+            Ok(Some(description)) => format!("Undid: {}", description),
+            Ok(None) if redo => "Nothing to redo".to_string(),
+            Ok(None) => "Nothing to undo".to_string(),
+            Err(err) => return self.report_error(Err(err)),
+        };
+        self.refresh_transactions();
+        self.toast = Some((message, Instant::now() + TOAST_DURATION));
+    }
+
+    // Pick up changes another program made to the ledger. The same transaction stays
+    // selected and the forms keep what was typed into them.
+    fn reload_if_changed(&mut self) {
+        self.last_reload_check = Instant::now();
+        let selected = self.selected_transaction();
+        match self.data.reload_if_changed() {
+            Ok(true) => {
+                self.refresh_transactions();
+                if let Some((kind, transaction)) = selected {
+                    if let Some(index) = self
+                        .table_rows()
+                        .iter()
+                        .position(|(k, t)| *k == kind && t.id == transaction.id)
+                    {
+                        self.table_state.select(Some(index));
+                    }
+                }
+                self.toast = Some((
+                    "Reloaded the ledger, which another program changed".to_string(),
+                    Instant::now() + TOAST_DURATION,
+                ));
+            }
+            Ok(false) => {}
+            Err(err) => self.report_error(Err(err)),
+        }
+    }
+
+    // Post scheduled transactions whose date arrived while the app is open
+    fn post_due_transactions(&mut self) {
+        match self.data.update_pending(Local::now().date_naive()) {
+            Ok(0) => {}
+            Ok(_) => self.refresh_transactions(),
+            Err(err) => self.report_error(Err(err)),
+        }
+    }
+
+    // Show why a change failed in the footer. After a conflict with another program the
+    // ledger was reloaded, so the view is refreshed as well.
+    fn report_error(&mut self, result: Result<(), Box<dyn Error>>) {
+        if let Err(err) = result {
+            self.refresh_transactions();
+            self.toast = Some((format!("ERROR: {}", err), Instant::now() + ERROR_DURATION));
+        }
+    }
+
+    // Message for the footer while it is recent enough to show
+    pub fn toast(&self) -> Option<&str> {
+        self.toast
+            .as_ref()
+            .filter(|(_, until)| Instant::now() < *until)
+            .map(|(message, _)| message.as_str())
+    }
+
     fn next_tab(&mut self) {
         self.current_tab = match self.current_tab {
             Tab::Home => Tab::Transactions,
             Tab::Transactions => Tab::AddExpense,
             Tab::AddExpense => Tab::AddIncome,
-            Tab::AddIncome => Tab::Home,
-            Tab::Report => Tab::Transactions,
+            Tab::AddIncome => Tab::Report,
+            Tab::Report => Tab::Calendar,
+            Tab::Calendar => Tab::Debts,
+            Tab::Debts => Tab::Budget,
+            Tab::Budget => Tab::Home,
         };
         self.reset_inputs();
     }
 
     fn previous_tab(&mut self) {
         self.current_tab = match self.current_tab {
-            Tab::Home => Tab::Report,
+            Tab::Home => Tab::Budget,
+            Tab::Budget => Tab::Debts,
+            Tab::Debts => Tab::Calendar,
+            Tab::Calendar => Tab::Report,
             Tab::Report => Tab::AddIncome,
             Tab::AddIncome => Tab::AddExpense,
             Tab::AddExpense => Tab::Transactions,
@@ -176,6 +428,7 @@ impl App {
 
     fn handle_tab_specific_input(&mut self, key: event::KeyEvent) {
         match self.current_tab {
+            Tab::Transactions if self.statement_input.is_some() => self.handle_statement_input(key),
             Tab::Transactions => match key.code {
                 KeyCode::Down => {
                     // Scroll down the table (adjust table state).
@@ -208,11 +461,68 @@ impl App {
                     self.vertical_scroll_state =
                         self.vertical_scroll_state.position(self.vertical_scroll);
                 }
+                // Dismiss the anomaly flag of the selected transaction after reviewing it
+                KeyCode::Char('x') => {
+                    let result = self.dismiss_flag();
+                    self.report_error(result);
+                }
+                // Edit the selected transaction in the Add form, unless it is reconciled
+                KeyCode::Char('e') => {
+                    if let Some((kind, transaction)) = self.selected_transaction() {
+                        if transaction.status != Status::Reconciled {
+                            self.start_edit(kind, &transaction);
+                        }
+                    }
+                }
+                // Enter a refund for the selected expense
+                KeyCode::Char('u') => {
+                    if let Some((TransactionType::Expense, transaction)) =
+                        self.selected_transaction()
+                    {
+                        if transaction.refund_of.is_none() {
+                            self.start_refund(&transaction);
+                        }
+                    }
+                }
+                // Enter or leave reconcile mode
+                KeyCode::Char('r') => {
+                    if self.statement.is_some() {
+                        self.statement = None;
+                    } else {
+                        self.start_reconcile();
+                    }
+                }
+                // Tick the selected transaction off against the statement
+                KeyCode::Char(' ') if self.statement.is_some() => {
+                    let result = self.toggle_cleared();
+                    self.report_error(result);
+                }
+                // Lock the ticked transactions once the difference is zero
+                KeyCode::Char('f') if self.statement.is_some() => {
+                    let result = self.finish_reconcile();
+                    self.report_error(result);
+                }
+                // Cycle the tag filter through all known tags
+                KeyCode::Char('t') => self.next_tag_filter(),
+                // Show or hide the details and change log of the selected transaction
+                KeyCode::Enter => self.show_details = !self.show_details,
+                // Toggle whether the selected expense is tax-deductible
+                KeyCode::Char('d') => {
+                    let result = self.toggle_deductible();
+                    self.report_error(result);
+                }
                 _ => {
                     self.input_to_active_field(key);
                 }
             },
             Tab::AddExpense | Tab::AddIncome => match key.code {
+                // Accept the payee suggestion when the cursor is at the end of the field
+                KeyCode::Right if self.active_input == 1 && self.payee_cursor_at_end() => {
+                    self.accept_payee_suggestion();
+                }
+                KeyCode::Right if self.active_input == 5 && self.tags_cursor_at_end() => {
+                    self.accept_tag_suggestion();
+                }
                 KeyCode::Down => {
                     self.next_input();
                 }
@@ -220,26 +530,580 @@ impl App {
                     self.previous_input();
                 }
                 KeyCode::Enter => {
-                    if let Ok(true) = self.submit_transaction() {
-                        self.current_tab = Tab::Transactions;
-                        self.reset_inputs();
+                    // A refused change keeps the form filled in, so it can be submitted again
+                    match self.submit_transaction() {
+                        Ok(true) => {
+                            self.current_tab = Tab::Transactions;
+                            self.reset_inputs();
+                        }
+                        Ok(false) => {}
+                        Err(err) => self.report_error(Err(err)),
                     }
                 }
                 _ => {
                     self.input_to_active_field(key);
                 }
             },
-            Tab::Report => {} // Handle Report tab inputs if necessary
-            Tab::Home => {}
+            Tab::Report => self.handle_report_input(key),
+            Tab::Calendar => self.handle_calendar_input(key),
+            Tab::Debts => self.handle_debts_input(key),
+            Tab::Budget => self.handle_budget_input(key),
+            Tab::Home => self.handle_bills_input(key),
+        }
+    }
+
+    // Transactions in the order they are shown in the Transactions table, honoring the tag filter.
+    // Pending transactions are listed after the posted ones.
+    pub fn table_rows(&self) -> Vec<(TransactionType, &Transaction)> {
+        let mut rows: Vec<(TransactionType, &Transaction)> = self
+            .transactions
+            .expenses
+            .iter()
+            .map(|t| (TransactionType::Expense, t))
+            .chain(
+                self.transactions
+                    .income
+                    .iter()
+                    .map(|t| (TransactionType::Income, t)),
+            )
+            .filter(|(_, t)| match &self.tag_filter {
+                Some(tag) => t.tags.contains(tag),
+                None => true,
+            })
+            .filter(|(_, t)| match &self.statement {
+                Some(statement) => statement.covers(t),
+                None => true,
+            })
+            .collect();
+        rows.sort_by_key(|(_, t)| t.pending);
+        rows
+    }
+
+    fn next_tag_filter(&mut self) {
+        let all = tags::all_tags(
+            self.transactions
+                .expenses
+                .iter()
+                .chain(self.transactions.income.iter()),
+        );
+        let next = match &self.tag_filter {
+            None => all.first(),
+            Some(current) => all
+                .iter()
+                .position(|t| t == current)
+                .and_then(|i| all.get(i + 1)),
+        };
+        self.tag_filter = next.cloned();
+        self.table_state = TableState::default();
+        self.vertical_scroll = 0;
+    }
+
+    // Transactions whose amount looked unusual and still need a review
+    pub fn flagged_transactions(&self) -> Vec<&Transaction> {
+        self.table_rows()
+            .into_iter()
+            .map(|(_, t)| t)
+            .filter(|t| t.flagged)
+            .collect()
+    }
+
+    // The transaction highlighted in the Transactions table
+    pub fn selected_transaction(&self) -> Option<(TransactionType, Transaction)> {
+        self.table_state.selected().and_then(|i| {
+            self.table_rows()
+                .get(i)
+                .map(|(kind, t)| (*kind, (*t).clone()))
+        })
+    }
+
+    // Open the form of the transaction's type filled with its values
+    fn start_edit(&mut self, kind: TransactionType, transaction: &Transaction) {
+        self.current_tab = match kind {
+            TransactionType::Expense => Tab::AddExpense,
+            TransactionType::Income => Tab::AddIncome,
+        };
+        self.reset_inputs();
+        self.editing = Some(transaction.id);
+        self.refund_of = transaction.refund_of;
+        let values = [
+            transaction.amount.abs().to_string(),
+            transaction.payee.clone(),
+            transaction.category.clone(),
+            transaction.date.format("%Y-%m-%d").to_string(),
+            transaction.description.clone(),
+            transaction.tags.join(", "),
+            splits::format(&transaction.splits),
+        ];
+        for (index, value) in values.into_iter().enumerate() {
+            if !value.is_empty() {
+                self.set_input(index, value);
+            }
+        }
+        self.active_input = 0;
+    }
+
+    // Open the Add Expense form prefilled with a refund of what is left of `expense`
+    fn start_refund(&mut self, expense: &Transaction) {
+        self.current_tab = Tab::AddExpense;
+        self.reset_inputs();
+        self.refund_of = Some(expense.id);
+        let remaining = refund::remaining(&self.transactions, expense);
+        let values = [
+            format!("{:.2}", remaining),
+            expense.payee.clone(),
+            expense.category.clone(),
+            Local::now().date_naive().format("%Y-%m-%d").to_string(),
+            format!("Refund: {}", expense.description),
+        ];
+        for (index, value) in values.into_iter().enumerate() {
+            if !value.is_empty() {
+                self.set_input(index, value);
+            }
+        }
+        self.active_input = 0;
+    }
+
+    // Replace the text of an input field and validate it
+    fn set_input(&mut self, index: usize, value: String) {
+        let textarea = match index {
+            0 => &mut self.amount_input,
+            1 => &mut self.payee_input,
+            2 => &mut self.category_input,
+            3 => &mut self.date_input,
+            4 => &mut self.description_input,
+            5 => &mut self.tags_input,
+            6 => &mut self.splits_input,
+            _ => unreachable!(),
+        };
+        *textarea = TextArea::new(vec![value.clone()]);
+        textarea.move_cursor(CursorMove::End);
+        self.input_modified[index] = true;
+        match index {
+            0 => self.validate_amount(&value),
+            1 => self.validate_payee(&value),
+            2 => self.validate_category(&value),
+            3 => self.validate_date(&value),
+            4 => self.validate_description(&value),
+            5 => self.validate_tags(&value),
+            _ => self.validate_splits(&value),
+        };
+    }
+
+    // Ask for the statement balance and date before entering reconcile mode
+    fn start_reconcile(&mut self) {
+        let mut input = TextArea::default();
+        input.set_block(
+            Block::default()
+                .title(" Statement ending balance and date, e.g. 1520.40 2026-09-30 (Enter to start, Esc to cancel)")
+                .title_style(Style::default().fg(Color::Yellow).bold())
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow))
+                .border_type(BorderType::Rounded)
+                .padding(Padding::new(1, 1, 0, 0)),
+        );
+        self.statement_input = Some(input);
+    }
+
+    fn handle_statement_input(&mut self, key: event::KeyEvent) {
+        let Some(input) = self.statement_input.as_mut() else {
+            return;
+        };
+        if key.code != KeyCode::Enter {
+            input.input(key);
+            return;
+        }
+        match Statement::parse(&input.lines()[0], Local::now().date_naive()) {
+            Ok(statement) => {
+                self.statement = Some(statement);
+                self.statement_input = None;
+                self.table_state = TableState::default();
+                self.vertical_scroll = 0;
+            }
+            Err(err) => {
+                input.set_block(
+                    Block::default()
+                        .border_style(Color::LightRed)
+                        .borders(Borders::ALL)
+                        .title(format!("ERROR: {}", err)),
+                );
+            }
+        }
+    }
+
+    // Switch the selected transaction between uncleared and cleared
+    fn toggle_cleared(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some((kind, mut transaction)) = self.selected_transaction() {
+            if transaction.pending {
+                return Ok(());
+            }
+            transaction.status = match transaction.status {
+                Status::Uncleared => Status::Cleared,
+                Status::Cleared => Status::Uncleared,
+                Status::Reconciled => return Ok(()),
+            };
+            self.data.update_transaction(transaction, kind.key())?;
+            self.refresh_transactions();
+        }
+        Ok(())
+    }
+
+    fn finish_reconcile(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(statement) = self.statement {
+            if statement.is_balanced(&self.transactions) {
+                self.data.reconcile(statement.date)?;
+                self.refresh_transactions();
+                self.statement = None;
+            }
+        }
+        Ok(())
+    }
+
+    fn toggle_deductible(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some((TransactionType::Expense, mut transaction)) = self.selected_transaction() {
+            if transaction.status == Status::Reconciled {
+                return Ok(());
+            }
+            transaction.deductible = !transaction.deductible;
+            if !transaction.deductible {
+                transaction.tax_code = None;
+            }
+            self.data
+                .update_transaction(transaction, TransactionType::Expense.key())?;
+            self.refresh_transactions();
+        }
+        Ok(())
+    }
+
+    fn dismiss_flag(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some((kind, mut transaction)) = self.selected_transaction() {
+            if transaction.flagged {
+                transaction.flagged = false;
+                self.data.update_transaction(transaction, kind.key())?;
+                self.refresh_transactions();
+            }
+        }
+        Ok(())
+    }
+
+    // Copy the stored transactions into the view after a change
+    fn refresh_transactions(&mut self) {
+        let stored = self.data.get_transactions();
+        self.transactions.expenses = stored.expenses.clone();
+        self.transactions.income = stored.income.clone();
+        // Keep a row selected when the last one went away
+        let count = self.table_rows().len();
+        if let Some(selected) = self.table_state.selected() {
+            self.table_state
+                .select(count.checked_sub(1).map(|last| selected.min(last)));
+        }
+        self.refresh_day_transactions();
+    }
+
+    // Query the transactions of the selected calendar day once instead of on every frame
+    fn refresh_day_transactions(&mut self) {
+        let query = Query {
+            from: Some(self.calendar_date),
+            until: Some(self.calendar_date),
+            category: None,
+        };
+        self.day_transactions = [TransactionType::Expense, TransactionType::Income]
+            .iter()
+            .flat_map(|kind| self.data.query(kind.key(), &query).unwrap_or_default())
+            .collect();
+    }
+
+    fn handle_report_input(&mut self, key: event::KeyEvent) {
+        let view_index = ReportView::ALL
+            .iter()
+            .position(|v| *v == self.report_view)
+            .unwrap_or(0);
+        match key.code {
+            KeyCode::Right => {
+                self.report_view = ReportView::ALL[(view_index + 1) % ReportView::ALL.len()];
+            }
+            KeyCode::Left => {
+                self.report_view = ReportView::ALL
+                    [(view_index + ReportView::ALL.len() - 1) % ReportView::ALL.len()];
+            }
+            // Step through fiscal years
+            KeyCode::Char('[') if self.report_view == ReportView::Tax => self.tax_year -= 1,
+            KeyCode::Char(']') if self.report_view == ReportView::Tax => self.tax_year += 1,
+            // Step through periods
+            KeyCode::Char('[') => {
+                self.report_anchor = self.report_period.shift(self.report_anchor, -1);
+            }
+            KeyCode::Char(']') => {
+                self.report_anchor = self.report_period.shift(self.report_anchor, 1);
+            }
+            // Group the comparison by category or payee
+            KeyCode::Char('g') => self.report_grouping = self.report_grouping.next(),
+            // Record the suggested settle-up payments as transfers
+            KeyCode::Char('s') if self.report_view == ReportView::Balances => {
+                let result = self.settle_up();
+                self.report_error(result);
+            }
+            // Switch between monthly and yearly comparison
+            KeyCode::Char('p') => {
+                self.report_period = self.report_period.toggle();
+                self.report_anchor = self.report_period.start_of(self.report_anchor);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_calendar_input(&mut self, key: event::KeyEvent) {
+        let date = self.calendar_date;
+        self.calendar_date = match key.code {
+            // Move the selected day
+            KeyCode::Left => date - Days::new(1),
+            KeyCode::Right => date + Days::new(1),
+            KeyCode::Up => date - Days::new(7),
+            KeyCode::Down => date + Days::new(7),
+            // Jump a whole month or year
+            KeyCode::Char('[') => self.calendar_period.shift(date, -1),
+            KeyCode::Char(']') => self.calendar_period.shift(date, 1),
+            KeyCode::Char('t') => Local::now().date_naive(),
+            KeyCode::Char('p') => {
+                self.calendar_period = self.calendar_period.toggle();
+                date
+            }
+            _ => date,
+        };
+        if self.calendar_date != date {
+            self.refresh_day_transactions();
+        }
+    }
+
+    fn handle_debts_input(&mut self, key: event::KeyEvent) {
+        let count = self.data.get_loans().len();
+        match key.code {
+            KeyCode::Down if count > 0 => self.debt_selected = (self.debt_selected + 1) % count,
+            KeyCode::Up if count > 0 => {
+                self.debt_selected = (self.debt_selected + count - 1) % count
+            }
+            // Record the next payment of the selected loan
+            KeyCode::Char('p') => {
+                let result = self.pay_selected_loan();
+                self.report_error(result);
+            }
+            KeyCode::Char('s') => self.debt_strategy = self.debt_strategy.toggle(),
+            // Adjust the extra monthly payment of the planner
+            KeyCode::Char('+') => self.debt_extra += DEBT_EXTRA_STEP,
+            KeyCode::Char('-') => self.debt_extra = (self.debt_extra - DEBT_EXTRA_STEP).max(0.0),
+            _ => {}
+        }
+    }
+
+    fn settle_up(&mut self) -> Result<(), Box<dyn Error>> {
+        let balances = shared::balances(&self.transactions, self.data.get_transfers());
+        let transfers: Vec<Transfer> = shared::settle_up(&balances)
+            .into_iter()
+            .map(|(from, to, amount)| Transfer {
+                id: 0,
+                date: Local::now().date_naive(),
+                from,
+                to,
+                amount,
+            })
+            .collect();
+        if !transfers.is_empty() {
+            self.data.add_transfers(transfers)?;
+        }
+        Ok(())
+    }
+
+    fn handle_budget_input(&mut self, key: event::KeyEvent) {
+        let mut changed = self.data.get_budget().clone();
+        let envelopes = budget::envelopes(&changed, &self.transactions, self.budget_month);
+        let count = envelopes.len();
+        let selected = envelopes
+            .get(self.budget_selected)
+            .map(|e| e.category.clone());
+        let result = match (key.code, selected) {
+            (KeyCode::Down, _) if count > 0 => {
+                self.budget_selected = (self.budget_selected + 1) % count;
+                return;
+            }
+            (KeyCode::Up, _) if count > 0 => {
+                self.budget_selected = (self.budget_selected + count - 1) % count;
+                return;
+            }
+            // Step through months
+            (KeyCode::Char('['), _) => {
+                self.budget_month = Period::Month.shift(self.budget_month, -1);
+                return;
+            }
+            (KeyCode::Char(']'), _) => {
+                self.budget_month = Period::Month.shift(self.budget_month, 1);
+                return;
+            }
+            // Assign money to the selected envelope or take it out again
+            (KeyCode::Char('+'), Some(category)) => budget::assign(
+                &mut changed,
+                &self.transactions,
+                self.budget_month,
+                &category,
+                BUDGET_STEP,
+            ),
+            (KeyCode::Char('-'), Some(category)) => budget::assign(
+                &mut changed,
+                &self.transactions,
+                self.budget_month,
+                &category,
+                -BUDGET_STEP,
+            ),
+            // Cover overspending with money from the fullest envelope
+            (KeyCode::Char('c'), Some(category)) => budget::cover(
+                &mut changed,
+                &self.transactions,
+                self.budget_month,
+                &category,
+            ),
+            _ => return,
+        };
+        self.budget_error = match result {
+            Ok(()) => self
+                .data
+                .update_budget(changed)
+                .err()
+                .map(|e| e.to_string()),
+            Err(err) => Some(err),
+        };
+        if self.budget_error.is_some() {
+            self.refresh_transactions();
         }
     }
 
+    // Unpaid bills that are overdue or due within the next days
+    pub fn bill_reminders(&self) -> Vec<bills::Due> {
+        bills::reminders(
+            self.data.get_bills(),
+            Local::now().date_naive(),
+            REMINDER_DAYS,
+        )
+    }
+
+    fn handle_bills_input(&mut self, key: event::KeyEvent) {
+        let count = self.bill_reminders().len();
+        match key.code {
+            KeyCode::Down if count > 0 => self.bill_selected = (self.bill_selected + 1) % count,
+            KeyCode::Up if count > 0 => {
+                self.bill_selected = (self.bill_selected + count - 1) % count
+            }
+            // Mark the selected bill paid and record the expense
+            KeyCode::Char('p') => {
+                let result = self.pay_selected_bill();
+                self.report_error(result);
+            }
+            _ => {}
+        }
+    }
+
+    fn pay_selected_bill(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(due) = self.bill_reminders().get(self.bill_selected).cloned() else {
+            return Ok(());
+        };
+        let Some(bill) = self
+            .data
+            .get_bills()
+            .iter()
+            .find(|b| b.id == due.bill_id)
+            .cloned()
+        else {
+            return Ok(());
+        };
+        let id = self
+            .data
+            .next_transaction_id(TransactionType::Expense.key());
+        let payment = bills::payment_transaction(&bill, due.date, Local::now().date_naive(), id);
+        self.data.pay_bill(bill.id, due.date, payment)?;
+        self.refresh_transactions();
+        // Keep the selection on a remaining reminder and close the popup once all are paid
+        let count = self.bill_reminders().len();
+        self.bill_selected = self.bill_selected.min(count.saturating_sub(1));
+        if count == 0 {
+            self.bill_popup = false;
+        }
+        Ok(())
+    }
+
+    fn pay_selected_loan(&mut self) -> Result<(), Box<dyn Error>> {
+        let Some(loan) = self.data.get_loans().get(self.debt_selected).cloned() else {
+            return Ok(());
+        };
+        if let Some(next) = loans::remaining_schedule(&loan, &self.transactions).first() {
+            let id = self
+                .data
+                .next_transaction_id(TransactionType::Expense.key());
+            self.data.add_transaction(
+                loans::payment_transaction(&loan, next, id),
+                TransactionType::Expense.key(),
+            )?;
+            self.refresh_transactions();
+        }
+        Ok(())
+    }
+
     fn next_input(&mut self) {
-        self.active_input = (self.active_input + 1) % 4;
+        self.leave_payee_input();
+        self.active_input = (self.active_input + 1) % INPUT_COUNT;
     }
 
     fn previous_input(&mut self) {
-        self.active_input = (self.active_input + 3) % 4;
+        self.leave_payee_input();
+        self.active_input = (self.active_input + INPUT_COUNT - 1) % INPUT_COUNT;
+    }
+
+    // Transactions of the list the current form adds to
+    fn form_history(&self) -> &[Transaction] {
+        match self.current_tab {
+            Tab::AddIncome => &self.transactions.income,
+            _ => &self.transactions.expenses,
+        }
+    }
+
+    fn payee_cursor_at_end(&self) -> bool {
+        let (_, column) = self.payee_input.cursor();
+        column >= self.payee_input.lines()[0].chars().count()
+    }
+
+    fn accept_payee_suggestion(&mut self) {
+        if let Some(suggestion) = self.payee_suggestions.first().cloned() {
+            self.set_input(1, suggestion.clone());
+            self.prefill_category(&suggestion);
+        }
+    }
+
+    fn tags_cursor_at_end(&self) -> bool {
+        let (_, column) = self.tags_input.cursor();
+        column >= self.tags_input.lines()[0].chars().count()
+    }
+
+    // Replace the tag being typed with the best suggestion
+    fn accept_tag_suggestion(&mut self) {
+        if let Some(suggestion) = self.tag_suggestions.first().cloned() {
+            let input = self.tags_input.lines()[0].clone();
+            let prefix = &input[..input.len() - tags::current_token(&input).len()];
+            let text = format!("{}{}, ", prefix, suggestion);
+            self.set_input(5, text);
+        }
+    }
+
+    // Moving on from a known payee fills in its most common category
+    fn leave_payee_input(&mut self) {
+        if self.active_input == 1 {
+            let payee = self.payee_input.lines()[0].trim().to_string();
+            self.prefill_category(&payee);
+        }
+    }
+
+    fn prefill_category(&mut self, payee: &str) {
+        if payee.is_empty() || !self.category_input.lines()[0].is_empty() {
+            return;
+        }
+        if let Some(category) = payee::most_common_category(self.form_history(), payee) {
+            self.set_input(2, category);
+        }
     }
 
     fn input_to_active_field(&mut self, key: event::KeyEvent) {
@@ -252,13 +1116,27 @@ impl App {
                     if !input.is_empty() {
                         self.validate_amount(&input);
                     }
+                    // Keep the remaining split amount in sync with the total
+                    if self.input_modified[6] {
+                        let splits = self.splits_input.lines()[0].clone();
+                        self.validate_splits(&splits);
+                    }
                 }
                 input_received
             }
             1 => {
-                let input_received = self.category_input.input(key);
+                let input_received = self.payee_input.input(key);
                 if input_received {
                     self.input_modified[1] = true;
+                    let input = self.payee_input.lines()[0].clone();
+                    self.validate_payee(&input);
+                }
+                input_received
+            }
+            2 => {
+                let input_received = self.category_input.input(key);
+                if input_received {
+                    self.input_modified[2] = true;
                     let input = self.category_input.lines()[0].clone();
                     if !input.is_empty() {
                         self.validate_category(&input);
@@ -266,10 +1144,10 @@ impl App {
                 }
                 input_received
             }
-            2 => {
+            3 => {
                 let input_received = self.date_input.input(key);
                 if input_received {
-                    self.input_modified[2] = true;
+                    self.input_modified[3] = true;
                     let input = self.date_input.lines()[0].clone();
                     if !input.is_empty() {
                         self.validate_date(&input);
@@ -277,10 +1155,10 @@ impl App {
                 }
                 input_received
             }
-            3 => {
+            4 => {
                 let input_received = self.description_input.input(key);
                 if input_received {
-                    self.input_modified[3] = true;
+                    self.input_modified[4] = true;
                     let input = self.description_input.lines()[0].clone();
                     if !input.is_empty() {
                         self.validate_description(&input);
@@ -288,6 +1166,24 @@ impl App {
                 }
                 input_received
             }
+            5 => {
+                let input_received = self.tags_input.input(key);
+                if input_received {
+                    self.input_modified[5] = true;
+                    let input = self.tags_input.lines()[0].clone();
+                    self.validate_tags(&input);
+                }
+                input_received
+            }
+            6 => {
+                let input_received = self.splits_input.input(key);
+                if input_received {
+                    self.input_modified[6] = true;
+                    let input = self.splits_input.lines()[0].clone();
+                    self.validate_splits(&input);
+                }
+                input_received
+            }
             _ => false,
         };
 
@@ -325,6 +1221,28 @@ impl App {
         }
     }
 
+    // Validation function for Payee, which is optional and offers completions from history
+    fn validate_payee(&mut self, input: &str) -> bool {
+        self.payee_suggestions = payee::suggestions(self.form_history(), input)
+            .into_iter()
+            .filter(|p| p != input)
+            .collect();
+        let title = match self.payee_suggestions.first() {
+            Some(suggestion) => format!("Suggestion: {} (→ to accept)", suggestion),
+            None if input.is_empty() => "Payee (optional)".to_string(),
+            None => "OK".to_string(),
+        };
+        self.payee_input
+            .set_style(Style::default().fg(Color::LightGreen));
+        self.payee_input.set_block(
+            Block::default()
+                .border_style(Color::LightGreen)
+                .borders(Borders::ALL)
+                .title(title),
+        );
+        true
+    }
+
     // Validation function for Category
     fn validate_category(&mut self, input: &str) -> bool {
         // Check for empty string or invalid characters (You might want to expand this check)
@@ -359,7 +1277,7 @@ impl App {
 
     // Validation function for Date
     fn validate_date(&mut self, input: &str) -> bool {
-        if let Err(_) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        if NaiveDate::parse_from_str(input, "%Y-%m-%d").is_err() {
             // Set ERROR styles for Date
             self.date_input
                 .set_style(Style::default().fg(Color::LightRed));
@@ -371,14 +1289,20 @@ impl App {
             );
             false
         } else {
-            // Set OK styles for Date
+            // Set OK styles for Date, noting when the transaction will be held back as pending
+            let future = NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .is_ok_and(|date| date > Local::now().date_naive());
             self.date_input
                 .set_style(Style::default().fg(Color::LightGreen));
             self.date_input.set_block(
                 Block::default()
                     .border_style(Color::LightGreen)
                     .borders(Borders::ALL)
-                    .title("OK"),
+                    .title(if future {
+                        "OK (future date: saved as pending until then)"
+                    } else {
+                        "OK"
+                    }),
             );
             true
         }
@@ -410,6 +1334,81 @@ impl App {
         }
     }
 
+    // Validation function for Tags, which are optional and complete from earlier tags
+    fn validate_tags(&mut self, input: &str) -> bool {
+        let token = tags::current_token(input);
+        let history = self
+            .transactions
+            .expenses
+            .iter()
+            .chain(self.transactions.income.iter());
+        self.tag_suggestions = tags::suggestions(history, token)
+            .into_iter()
+            .filter(|t| t != token)
+            .collect();
+
+        if let Some(invalid) = tags::parse(input).into_iter().find(|t| !tags::is_valid(t)) {
+            // Set ERROR styles for Tags
+            self.tags_input
+                .set_style(Style::default().fg(Color::LightRed));
+            self.tags_input.set_block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Color::LightRed)
+                    .title(format!("ERROR: Invalid Tag '{}'", invalid)),
+            );
+            return false;
+        }
+
+        let title = match self.tag_suggestions.first() {
+            Some(suggestion) => format!("Suggestion: {} (→ to accept)", suggestion),
+            None if input.is_empty() => "Tags, separated by commas (optional)".to_string(),
+            None => "OK".to_string(),
+        };
+        self.tags_input
+            .set_style(Style::default().fg(Color::LightGreen));
+        self.tags_input.set_block(
+            Block::default()
+                .border_style(Color::LightGreen)
+                .borders(Borders::ALL)
+                .title(title),
+        );
+        true
+    }
+
+    // Validation function for Splits, which must add up to the amount when given
+    fn validate_splits(&mut self, input: &str) -> bool {
+        let total = self.amount_input.lines()[0].parse::<f64>().unwrap_or(0.0);
+        let (valid, title) = match splits::parse(input, false) {
+            Ok(lines) if lines.is_empty() => (
+                true,
+                "Splits: Category:Amount[:Note]; ... (optional)".to_string(),
+            ),
+            Ok(lines) if splits::is_balanced(&lines, total) => (true, "OK".to_string()),
+            Ok(lines) => (
+                false,
+                format!(
+                    "Splits must add up to the amount, remaining: {:.2}",
+                    splits::remaining(&lines, total)
+                ),
+            ),
+            Err(err) => (false, format!("ERROR: {}", err)),
+        };
+        let color = if valid {
+            Color::LightGreen
+        } else {
+            Color::LightRed
+        };
+        self.splits_input.set_style(Style::default().fg(color));
+        self.splits_input.set_block(
+            Block::default()
+                .border_style(color)
+                .borders(Borders::ALL)
+                .title(title),
+        );
+        valid
+    }
+
     fn reset_inputs(&mut self) {
         let amount_title = match self.current_tab {
             Tab::AddExpense => " Enter Expense Amount",
@@ -428,6 +1427,16 @@ impl App {
                 .border_type(BorderType::Rounded)
                 .padding(Padding::new(1, 1, 0, 0)),
         );
+        self.payee_input = TextArea::default();
+        self.payee_input.set_block(
+            Block::default()
+                .title(" Enter Payee")
+                .title_style(Style::default().fg(Color::Yellow).bold())
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow))
+                .border_type(BorderType::Rounded)
+                .padding(Padding::new(1, 1, 0, 0)),
+        );
         self.category_input = TextArea::default();
         self.category_input.set_block(
             Block::default()
@@ -458,12 +1467,19 @@ impl App {
                 .border_type(BorderType::Rounded)
                 .padding(Padding::new(1, 1, 0, 0)),
         );
+        self.tags_input = TextArea::default();
+        self.splits_input = TextArea::default();
+        self.editing = None;
+        self.refund_of = None;
         // Reset styling for all inputs
         for textarea in [
             &mut self.amount_input,
+            &mut self.payee_input,
             &mut self.category_input,
             &mut self.date_input,
             &mut self.description_input,
+            &mut self.tags_input,
+            &mut self.splits_input,
         ] {
             textarea.set_style(Style::default());
             textarea.set_block(
@@ -475,7 +1491,7 @@ impl App {
             );
         }
 
-        self.input_modified = [false; 4];
+        self.input_modified = [false; INPUT_COUNT];
         self.active_input = 0;
         self.cursor_visible = false;
         self.last_tick = Instant::now();
@@ -483,12 +1499,18 @@ impl App {
         // Initial validation
         let amount = self.amount_input.lines()[0].clone();
         self.validate_amount(&amount);
+        let payee = self.payee_input.lines()[0].clone();
+        self.validate_payee(&payee);
         let category = self.category_input.lines()[0].clone();
         self.validate_category(&category);
         let date = self.date_input.lines()[0].clone();
         self.validate_date(&date);
         let description = self.description_input.lines()[0].clone();
         self.validate_description(&description);
+        let tags = self.tags_input.lines()[0].clone();
+        self.validate_tags(&tags);
+        let splits = self.splits_input.lines()[0].clone();
+        self.validate_splits(&splits);
     }
 
     pub fn update_cursor(&mut self) {
@@ -502,82 +1524,344 @@ impl App {
     pub fn get_active_textarea(&self) -> &TextArea<'static> {
         match self.active_input {
             0 => &self.amount_input,
-            1 => &self.category_input,
-            2 => &self.date_input,
-            3 => &self.description_input,
+            1 => &self.payee_input,
+            2 => &self.category_input,
+            3 => &self.date_input,
+            4 => &self.description_input,
+            5 => &self.tags_input,
+            6 => &self.splits_input,
             _ => unreachable!(),
         }
     }
 
+    // Map user input to one of the known categories of the form's type
+    fn known_category(&self, input: &str) -> &'static str {
+        let categories = match self.current_tab {
+            Tab::AddIncome => self.income_categories,
+            _ => self.expense_categories,
+        };
+        categories
+            .iter()
+            .find(|c| c.trim() == input.trim())
+            .copied()
+            .unwrap_or("Other")
+    }
+
     fn submit_transaction(&mut self) -> Result<bool, Box<dyn Error>> {
         // Get input values
         let amount_input_str = self.amount_input.lines()[0].clone();
+        let payee_input_str = self.payee_input.lines()[0].trim().to_string();
         let category_input_str = self.category_input.lines()[0].clone();
         let date_input_str = self.date_input.lines()[0].clone();
         let description_input_str = self.description_input.lines()[0].clone();
+        let tags_input_str = self.tags_input.lines()[0].clone();
+        let splits_input_str = self.splits_input.lines()[0].clone();
 
         let valid_amount = !self.input_modified[0] || self.validate_amount(&amount_input_str);
-        let valid_category = !self.input_modified[1] || self.validate_category(&category_input_str);
-        let valid_date = !self.input_modified[2] || self.validate_date(&date_input_str);
+        let valid_category = !self.input_modified[2] || self.validate_category(&category_input_str);
+        let valid_date = !self.input_modified[3] || self.validate_date(&date_input_str);
         let valid_description =
-            !self.input_modified[3] || self.validate_description(&description_input_str);
+            !self.input_modified[4] || self.validate_description(&description_input_str);
+        let valid_tags = self.validate_tags(&tags_input_str);
+        let valid_splits = self.validate_splits(&splits_input_str);
 
-        if !valid_amount || !valid_category || !valid_date || !valid_description {
+        if !valid_amount
+            || !valid_category
+            || !valid_date
+            || !valid_description
+            || !valid_tags
+            || !valid_splits
+        {
             return Ok(false);
         }
 
-        let amount = match self.current_tab {
-            Tab::AddExpense => -amount_input_str.parse::<f64>()?,
-            Tab::AddIncome => amount_input_str.parse::<f64>()?,
+        let kind = match self.current_tab {
+            Tab::AddExpense => TransactionType::Expense,
+            Tab::AddIncome => TransactionType::Income,
             _ => unreachable!(),
         };
+        let amount = match kind {
+            // Refunds are stored as positive expenses so they net against their category
+            TransactionType::Expense if self.refund_of.is_some() => {
+                amount_input_str.parse::<f64>()?
+            }
+            TransactionType::Expense => -amount_input_str.parse::<f64>()?,
+            TransactionType::Income => amount_input_str.parse::<f64>()?,
+        };
         let date = NaiveDate::parse_from_str(&date_input_str, "%Y-%m-%d")?;
+        let category = self.known_category(&category_input_str);
+        let mut split_lines = splits::parse(&splits_input_str, amount < 0.0)?;
+        for split in split_lines.iter_mut() {
+            split.category = self.known_category(&split.category).to_string();
+        }
 
-        let category = match self.current_tab {
-            Tab::AddExpense => self
-                .expense_categories
-                .iter()
-                .find(|c| c.trim() == category_input_str.trim())
-                .map(|s| *s)
-                .unwrap_or_else(|| "Other"),
-            Tab::AddIncome => self
-                .income_categories
-                .iter()
-                .find(|c| c.trim() == category_input_str.trim())
-                .map(|s| *s)
-                .unwrap_or_else(|| "Other"),
-            _ => unreachable!(),
+        let list = match kind {
+            TransactionType::Expense => &self.transactions.expenses,
+            TransactionType::Income => &self.transactions.income,
         };
+        // Edits keep everything the form doesn't show, like flags and tax marks
+        let original = self
+            .editing
+            .and_then(|id| list.iter().find(|t| t.id == id))
+            .cloned();
+        // New refunds are tax-deductible whenever the expense they pay back was
+        let base = original.clone().unwrap_or_else(|| {
+            self.refund_of
+                .and_then(|id| list.iter().find(|t| t.id == id))
+                .map(|expense| Transaction {
+                    deductible: expense.deductible,
+                    tax_code: expense.tax_code.clone(),
+                    ..Default::default()
+                })
+                .unwrap_or_default()
+        });
 
         // Create the Transaction
         let transaction = Transaction {
-            id: match self.current_tab {
-                Tab::AddExpense => self.transactions.expenses.len() as u32 + 1,
-                Tab::AddIncome => self.transactions.income.len() as u32 + 1,
-                _ => unreachable!(),
-            },
+            id: original
+                .as_ref()
+                .map(|t| t.id)
+                .unwrap_or_else(|| self.data.next_transaction_id(kind.key())),
             amount,
-            category: description_input_str.clone(),
+            category: category.to_string(),
             date,
             description: description_input_str.clone(),
+            payee: payee_input_str,
+            tags: tags::parse(&tags_input_str),
+            splits: split_lines,
+            refund_of: self.refund_of,
+            ..base
         };
 
-        self.data.add_transaction(
-            transaction.clone(),
-            match self.current_tab {
-                Tab::AddExpense => "expenses",
-                Tab::AddIncome => "income",
-                _ => unreachable!(),
-            },
-        )?;
+        if original.is_some() {
+            self.data.update_transaction(transaction, kind.key())?;
+        } else {
+            self.data.add_transaction(transaction, kind.key())?;
+        }
 
-        match self.current_tab {
-            Tab::AddExpense => self.transactions.expenses.push(transaction),
-            Tab::AddIncome => self.transactions.income.push(transaction),
-            _ => unreachable!(),
-        };
+        self.refresh_transactions();
 
         // If successful, you might want to signal this (perhaps by changing a flag in App for UI updates)
         Ok(true)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{JsonFile, Memory};
+    use crossterm::event::{KeyEvent, KeyModifiers};
+
+    fn app() -> App {
+        App::with_storage(Box::new(Memory::default())).unwrap()
+    }
+
+    fn press(app: &mut App, code: KeyCode) {
+        app.handle_tab_specific_input(KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    fn add_expense(app: &mut App, amount: &str, category: &str, date: &str) {
+        app.current_tab = Tab::AddExpense;
+        app.reset_inputs();
+        app.set_input(0, amount.to_string());
+        app.set_input(2, category.to_string());
+        app.set_input(3, date.to_string());
+        app.set_input(4, "Test".to_string());
+        assert!(app.submit_transaction().unwrap());
+    }
+
+    #[test]
+    fn submitting_the_form_stores_a_negative_expense() {
+        let mut app = app();
+        add_expense(&mut app, "12.50", "Food", "2026-01-05");
+        let expenses = &app.data.get_transactions().expenses;
+        assert_eq!(expenses.len(), 1);
+        assert_eq!(expenses[0].id, 1);
+        assert_eq!(expenses[0].amount, -12.5);
+        assert_eq!(app.transactions.expenses.len(), 1);
+    }
+
+    #[test]
+    fn day_transactions_follow_the_calendar_selection() {
+        let mut app = app();
+        add_expense(&mut app, "10", "Food", "2026-01-05");
+        add_expense(&mut app, "20", "Bills", "2026-01-06");
+        app.current_tab = Tab::Calendar;
+        app.calendar_date = NaiveDate::from_ymd_opt(2026, 1, 4).unwrap();
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.day_transactions.len(), 1);
+        assert_eq!(app.day_transactions[0].category, "Food");
+        add_expense(&mut app, "5", "Health", "2026-01-05");
+        assert_eq!(app.day_transactions.len(), 2);
+    }
+
+    #[test]
+    fn control_z_and_control_y_undo_and_redo_with_a_message() {
+        let mut app = app();
+        add_expense(&mut app, "12.50", "Food", "2026-01-05");
+        app.current_tab = Tab::Transactions;
+        let control = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+
+        assert!(!app.handle_key(control('z')));
+        assert!(app.transactions.expenses.is_empty());
+        assert_eq!(app.toast(), Some("Undid: Added expense 'Test' (-12.50)"));
+
+        app.handle_key(control('y'));
+        assert_eq!(app.transactions.expenses.len(), 1);
+        assert_eq!(app.toast(), Some("Redid: Added expense 'Test' (-12.50)"));
+        app.handle_key(control('y'));
+        assert_eq!(app.toast(), Some("Nothing to redo"));
+    }
+
+    #[test]
+    fn change_refused_after_another_program_wrote_shows_the_conflict() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-app.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut app = App::with_storage(Box::new(JsonFile::new(path))).unwrap();
+        add_expense(&mut app, "12.50", "Food", "2026-01-05");
+
+        let mut other = Data::new(path).unwrap();
+        let id = other.next_transaction_id("income");
+        other
+            .add_transaction(
+                Transaction {
+                    id,
+                    amount: 100.0,
+                    category: "Salary".to_string(),
+                    date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+                    ..Default::default()
+                },
+                "income",
+            )
+            .unwrap();
+
+        app.current_tab = Tab::Transactions;
+        app.table_state.select(Some(0));
+        press(&mut app, KeyCode::Char('d'));
+        std::fs::remove_file(path).unwrap();
+        assert!(app.toast().unwrap().starts_with("ERROR: Another FinTrackR"));
+        assert_eq!(app.transactions.expenses.len(), 1);
+        assert_eq!(app.transactions.income.len(), 1);
+    }
+
+    #[test]
+    fn reload_keeps_the_selection_and_form_input() {
+        let path =
+            std::env::temp_dir().join(format!("fintrackr-{}-reload.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+        let mut app = App::with_storage(Box::new(JsonFile::new(path))).unwrap();
+        add_expense(&mut app, "10", "Food", "2026-01-05");
+        add_expense(&mut app, "20", "Bills", "2026-01-06");
+        app.reload_if_changed();
+        assert!(app.toast().is_none());
+
+        app.current_tab = Tab::AddExpense;
+        app.reset_inputs();
+        app.set_input(0, "42".to_string());
+        app.table_state.select(Some(1));
+        // Another instance undoes the second expense
+        Data::new(path).unwrap().undo().unwrap();
+
+        app.reload_if_changed();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(app.transactions.expenses.len(), 1);
+        assert_eq!(app.table_state.selected(), Some(0));
+        assert_eq!(app.selected_transaction().unwrap().1.category, "Food");
+        assert_eq!(app.amount_input.lines()[0], "42");
+        assert!(app.toast().unwrap().starts_with("Reloaded"));
+    }
+
+    #[test]
+    fn changes_are_logged_per_field_with_their_source() {
+        let mut app = app();
+        add_expense(&mut app, "12.50", "Food", "2026-01-05");
+        let mut expense = app.data.get_transactions().expenses[0].clone();
+        assert!(expense.created_at.is_some());
+        assert_eq!(expense.changes.len(), 1);
+        assert_eq!(expense.changes[0].source, Source::Tui);
+
+        expense.amount = -15.0;
+        expense.category = "Bills".to_string();
+        app.data
+            .update_transaction(expense.clone(), "expenses")
+            .unwrap();
+        // Saving without changes adds nothing to the log
+        app.data.update_transaction(expense, "expenses").unwrap();
+        let expense = &app.data.get_transactions().expenses[0];
+        assert_eq!(expense.changes.len(), 2);
+        let fields: Vec<(&str, &str, &str)> = expense.changes[1]
+            .fields
+            .iter()
+            .map(|c| (c.field.as_str(), c.before.as_str(), c.after.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![("amount", "-12.5", "-15.0"), ("category", "Food", "Bills")]
+        );
+        assert_eq!(expense.updated_at, Some(expense.changes[1].at));
+
+        // Undoing and redoing are logged as further changes, the save without changes again
+        // adding nothing
+        for _ in 0..2 {
+            app.data.undo().unwrap();
+        }
+        for _ in 0..2 {
+            app.data.redo().unwrap();
+        }
+        let expense = &app.data.get_transactions().expenses[0];
+        assert_eq!(expense.amount, -15.0);
+        let fields: Vec<Vec<(&str, &str, &str)>> = expense
+            .changes
+            .iter()
+            .map(|change| {
+                change
+                    .fields
+                    .iter()
+                    .map(|c| (c.field.as_str(), c.before.as_str(), c.after.as_str()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                vec![],
+                vec![("amount", "-12.5", "-15.0"), ("category", "Food", "Bills")],
+                vec![("amount", "-15.0", "-12.5"), ("category", "Bills", "Food")],
+                vec![("amount", "-12.5", "-15.0"), ("category", "Food", "Bills")],
+            ]
+        );
+    }
+
+    #[test]
+    fn budget_keys_assign_only_money_that_is_left() {
+        let mut app = app();
+        let mut budget = app.data.get_budget().clone();
+        budget.start = Some(app.budget_month);
+        app.data.update_budget(budget).unwrap();
+        app.current_tab = Tab::Budget;
+
+        press(&mut app, KeyCode::Char('+'));
+        assert!(app.budget_error.is_some());
+        assert!(app.data.get_budget().assignments.is_empty());
+
+        app.data
+            .add_transaction(
+                Transaction {
+                    id: 1,
+                    amount: 15.0,
+                    category: "Salary".to_string(),
+                    date: app.budget_month,
+                    ..Default::default()
+                },
+                "income",
+            )
+            .unwrap();
+        app.refresh_transactions();
+        press(&mut app, KeyCode::Char('+'));
+        press(&mut app, KeyCode::Char('+'));
+        assert_eq!(app.data.get_budget().assignments.len(), 1);
+        assert!(app.budget_error.is_some());
+    }
+}
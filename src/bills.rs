@@ -0,0 +1,180 @@
+use crate::data::{Bill, Transaction};
+use chrono::{Days, Months, NaiveDate};
+
+// Bills due within this many days are shown as reminders
+pub const REMINDER_DAYS: u64 = 7;
+
+// One due date of a recurring bill
+#[derive(Debug, Clone, PartialEq)]
+pub struct Due {
+    pub bill_id: u32,
+    pub payee: String,
+    pub amount: f64,
+    pub date: NaiveDate,
+    pub paid: bool,
+}
+
+impl Due {
+    pub fn is_overdue(&self, today: NaiveDate) -> bool {
+        !self.paid && self.date < today
+    }
+
+    // "overdue 3 days", "due today" or "due in 5 days"
+    pub fn status(&self, today: NaiveDate) -> String {
+        let days = (self.date - today).num_days();
+        if self.paid {
+            "paid".to_string()
+        } else if days < 0 {
+            format!("overdue {} days", -days)
+        } else if days == 0 {
+            "due today".to_string()
+        } else {
+            format!("due in {} days", days)
+        }
+    }
+}
+
+// Due dates of `bill` from the first one on, ending where the calendar runs out. Each date is
+// counted from the first one, so a bill due on the 31st stays at the end of shorter months.
+fn occurrences(bill: &Bill) -> impl Iterator<Item = NaiveDate> + '_ {
+    let every = bill.every_months.max(1);
+    (0u32..)
+        .map_while(move |n| n.checked_mul(every))
+        .map_while(move |months| bill.first_due.checked_add_months(Months::new(months)))
+}
+
+fn due(bill: &Bill, date: NaiveDate) -> Due {
+    Due {
+        bill_id: bill.id,
+        payee: bill.payee.clone(),
+        amount: bill.amount,
+        date,
+        paid: bill.paid.contains(&date),
+    }
+}
+
+// Due dates of `bill` up to and including `until`
+pub fn due_dates(bill: &Bill, until: NaiveDate) -> Vec<Due> {
+    occurrences(bill)
+        .take_while(|date| *date <= until)
+        .map(|date| due(bill, date))
+        .collect()
+}
+
+// Due date of `bill` to pay next: the latest one on or before `today` if it is still unpaid,
+// otherwise the first unpaid one after `today`. Older unpaid dates are left behind, so a bill
+// started long ago doesn't have to be paid off from its first due date. None once the calendar
+// runs out.
+pub fn next_due(bill: &Bill, today: NaiveDate) -> Option<Due> {
+    let current = occurrences(bill).take_while(|date| *date <= today).last();
+    match current {
+        Some(date) if !bill.paid.contains(&date) => Some(due(bill, date)),
+        _ => occurrences(bill)
+            .skip_while(|date| *date <= today)
+            .find(|date| !bill.paid.contains(date))
+            .map(|date| due(bill, date)),
+    }
+}
+
+// Unpaid bills that are overdue or due within `days` days, oldest first. Only the current due
+// date of each bill counts as overdue.
+pub fn reminders(bills: &[Bill], today: NaiveDate, days: u64) -> Vec<Due> {
+    let until = today
+        .checked_add_days(Days::new(days))
+        .unwrap_or(NaiveDate::MAX);
+    let mut due: Vec<Due> = bills
+        .iter()
+        .flat_map(|bill| {
+            let overdue = next_due(bill, today).filter(|d| d.date <= today);
+            let upcoming = due_dates(bill, until)
+                .into_iter()
+                .filter(|d| d.date > today && !d.paid);
+            overdue.into_iter().chain(upcoming)
+        })
+        .collect();
+    due.sort_by(|a, b| (a.date, &a.payee).cmp(&(b.date, &b.payee)));
+    due
+}
+
+// Expense paying the bill due on `due`, dated `date`
+pub fn payment_transaction(bill: &Bill, due: NaiveDate, date: NaiveDate, id: u32) -> Transaction {
+    Transaction {
+        id,
+        amount: -bill.amount,
+        category: bill.category.clone(),
+        date,
+        description: format!("{} bill due {}", bill.payee, due),
+        payee: bill.payee.clone(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn rent(first_due: &str, paid: &[&str]) -> Bill {
+        Bill {
+            id: 1,
+            payee: "Landlord".to_string(),
+            category: "Housing".to_string(),
+            amount: 900.0,
+            first_due: date(first_due),
+            every_months: 1,
+            paid: paid.iter().map(|d| date(d)).collect(),
+        }
+    }
+
+    #[test]
+    fn due_dates_stay_at_the_end_of_the_month() {
+        let dates: Vec<NaiveDate> = due_dates(&rent("2026-01-31", &[]), date("2026-04-30"))
+            .into_iter()
+            .map(|d| d.date)
+            .collect();
+        assert_eq!(
+            dates,
+            vec![
+                date("2026-01-31"),
+                date("2026-02-28"),
+                date("2026-03-31"),
+                date("2026-04-30")
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_current_due_date_is_overdue() {
+        let bill = rent("2024-01-01", &[]);
+        let today = date("2026-10-18");
+        assert_eq!(next_due(&bill, today).unwrap().date, date("2026-10-01"));
+        let reminders = reminders(&[bill], today, 14);
+        let dates: Vec<NaiveDate> = reminders.iter().map(|d| d.date).collect();
+        assert_eq!(dates, vec![date("2026-10-01"), date("2026-11-01")]);
+    }
+
+    #[test]
+    fn paid_bills_move_on_to_the_next_due_date() {
+        let bill = rent("2026-09-01", &["2026-10-01", "2026-11-01"]);
+        let today = date("2026-10-18");
+        assert_eq!(next_due(&bill, today).unwrap().date, date("2026-12-01"));
+        assert!(reminders(&[bill], today, 7).is_empty());
+    }
+
+    #[test]
+    fn extreme_dates_end_the_schedule_instead_of_panicking() {
+        let mut bill = rent("2026-01-01", &[]);
+        bill.first_due = NaiveDate::MAX;
+        assert_eq!(
+            next_due(&bill, date("2026-10-18")).unwrap().date,
+            NaiveDate::MAX
+        );
+        bill.paid.push(NaiveDate::MAX);
+        assert_eq!(next_due(&bill, date("2026-10-18")), None);
+        assert_eq!(due_dates(&bill, NaiveDate::MAX).len(), 1);
+        assert!(reminders(&[bill], NaiveDate::MAX, 7).is_empty());
+    }
+}
@@ -0,0 +1,441 @@
+use crate::data::{Assignment, Budget, Transactions};
+use crate::report::{self, Period};
+use chrono::NaiveDate;
+
+// Balances within half a cent count as zero
+const TOLERANCE: f64 = 0.005;
+
+// One category envelope in a month
+#[derive(Debug, Clone, PartialEq)]
+pub struct Envelope {
+    pub category: String,
+    pub carried: f64, // Balance rolled over from the previous month, negative when overspent
+    pub assigned: f64, // Money assigned this month
+    pub spent: f64,   // Spending this month, net of refunds
+}
+
+impl Envelope {
+    pub fn available(&self) -> f64 {
+        self.carried + self.assigned - self.spent
+    }
+
+    pub fn is_overspent(&self) -> bool {
+        self.available() < -TOLERANCE
+    }
+}
+
+// "YYYY-MM" to the first day of that month
+pub fn parse_month(input: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", input), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid month '{}', use YYYY-MM", input))
+}
+
+// Income received from the start of budgeting up to and including `month`
+fn income_through(budget: &Budget, transactions: &Transactions, month: NaiveDate) -> f64 {
+    let Some(start) = budget.start else {
+        return 0.0;
+    };
+    let end = Period::Month.shift(month, 1);
+    report::sum(
+        transactions
+            .income
+            .iter()
+            .filter(|t| !t.pending && t.date >= start && t.date < end)
+            .map(|t| t.amount),
+    )
+}
+
+// Money received but not yet assigned to an envelope as of `month`
+pub fn to_be_budgeted(budget: &Budget, transactions: &Transactions, month: NaiveDate) -> f64 {
+    income_through(budget, transactions, month)
+        - report::sum(
+            budget
+                .assignments
+                .iter()
+                .filter(|a| a.month <= month)
+                .map(|a| a.amount),
+        )
+}
+
+// Assignments and spending of `category` in the months [from, to)
+fn activity(
+    budget: &Budget,
+    transactions: &Transactions,
+    category: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> (f64, f64) {
+    let assigned = report::sum(
+        budget
+            .assignments
+            .iter()
+            .filter(|a| a.category == category && a.month >= from && a.month < to)
+            .map(|a| a.amount),
+    );
+    let spent = report::sum(
+        transactions
+            .expenses
+            .iter()
+            .filter(|t| !t.pending && t.date >= from && t.date < to)
+            .flat_map(|t| t.category_amounts())
+            .filter(|(c, _)| *c == category)
+            .map(|(_, amount)| -amount),
+    );
+    (assigned, spent)
+}
+
+// Every envelope in `month`: the expense categories followed by any other category that
+// was budgeted or spent on since budgeting started
+pub fn envelopes(budget: &Budget, transactions: &Transactions, month: NaiveDate) -> Vec<Envelope> {
+    let Some(start) = budget.start else {
+        return Vec::new();
+    };
+    let start = Period::Month.start_of(start);
+    let end = Period::Month.shift(month, 1);
+    let mut categories = transactions.expense_categories.clone();
+    let used = budget
+        .assignments
+        .iter()
+        .filter(|a| a.month < end)
+        .map(|a| a.category.as_str())
+        .chain(
+            transactions
+                .expenses
+                .iter()
+                .filter(|t| !t.pending && t.date >= start && t.date < end)
+                .flat_map(|t| t.category_amounts().into_iter().map(|(c, _)| c)),
+        );
+    for category in used {
+        if !categories.iter().any(|c| c == category) {
+            categories.push(category.to_string());
+        }
+    }
+
+    categories
+        .into_iter()
+        .map(|category| {
+            let (assigned_before, spent_before) =
+                activity(budget, transactions, &category, start, month);
+            let (assigned, spent) = activity(budget, transactions, &category, month, end);
+            Envelope {
+                carried: assigned_before - spent_before,
+                assigned,
+                spent,
+                category,
+            }
+        })
+        .collect()
+}
+
+// Balance of one envelope at the end of `month`
+pub fn available(
+    budget: &Budget,
+    transactions: &Transactions,
+    month: NaiveDate,
+    category: &str,
+) -> f64 {
+    envelopes(budget, transactions, month)
+        .iter()
+        .find(|e| e.category == category)
+        .map(Envelope::available)
+        .unwrap_or(0.0)
+}
+
+// Months from `month` up to the last one with an assignment, whose "to be budgeted" an
+// assignment in `month` also reduces
+fn affected_months(budget: &Budget, month: NaiveDate) -> Vec<NaiveDate> {
+    let last = budget
+        .assignments
+        .iter()
+        .map(|a| a.month)
+        .max()
+        .unwrap_or(month)
+        .max(month);
+    let mut months = vec![month];
+    while let Some(next) = months.last().map(|m| Period::Month.shift(*m, 1)) {
+        if next > last {
+            break;
+        }
+        months.push(next);
+    }
+    months
+}
+
+// Put money into an envelope (or take it out with a negative amount)
+pub fn assign(
+    budget: &mut Budget,
+    transactions: &Transactions,
+    month: NaiveDate,
+    category: &str,
+    amount: f64,
+) -> Result<(), String> {
+    let Some(start) = budget.start else {
+        return Err("Budgeting is off, start it with `fintrackr budget start`".to_string());
+    };
+    if !amount.is_finite() {
+        return Err(format!("Invalid amount {}", amount));
+    }
+    let month = Period::Month.start_of(month);
+    if month < Period::Month.start_of(start) {
+        return Err(format!("Budgeting started in {}", start.format("%Y-%m")));
+    }
+    if !transactions
+        .expense_categories
+        .iter()
+        .any(|c| c == category)
+    {
+        return Err(format!("Unknown expense category '{}'", category));
+    }
+    if amount > 0.0 {
+        let left = affected_months(budget, month)
+            .into_iter()
+            .map(|m| to_be_budgeted(budget, transactions, m))
+            .fold(f64::INFINITY, f64::min);
+        if amount > left + TOLERANCE {
+            return Err(format!("Only {:.2} left to budget", left.max(0.0)));
+        }
+    } else {
+        let balance = available(budget, transactions, month, category);
+        if -amount > balance + TOLERANCE {
+            return Err(format!(
+                "{} only has {:.2} available",
+                category,
+                balance.max(0.0)
+            ));
+        }
+    }
+    budget.assignments.push(Assignment {
+        month,
+        category: category.to_string(),
+        amount,
+    });
+    Ok(())
+}
+
+// Move money from one envelope to another, e.g. to cover overspending
+pub fn move_money(
+    budget: &mut Budget,
+    transactions: &Transactions,
+    month: NaiveDate,
+    from: &str,
+    to: &str,
+    amount: f64,
+) -> Result<(), String> {
+    if amount <= 0.0 {
+        return Err("The amount must be positive".to_string());
+    }
+    if !transactions.expense_categories.iter().any(|c| c == to) {
+        return Err(format!("Unknown expense category '{}'", to));
+    }
+    assign(budget, transactions, month, from, -amount)?;
+    budget.assignments.push(Assignment {
+        month: Period::Month.start_of(month),
+        category: to.to_string(),
+        amount,
+    });
+    Ok(())
+}
+
+// Cover the overspending of `category` from the envelope with the most money available
+pub fn cover(
+    budget: &mut Budget,
+    transactions: &Transactions,
+    month: NaiveDate,
+    category: &str,
+) -> Result<(), String> {
+    let envelopes = envelopes(budget, transactions, month);
+    let missing = -envelopes
+        .iter()
+        .find(|e| e.category == category)
+        .map(Envelope::available)
+        .unwrap_or(0.0);
+    if missing <= TOLERANCE {
+        return Err(format!("{} is not overspent", category));
+    }
+    let source = envelopes
+        .iter()
+        .filter(|e| e.category != category)
+        .max_by(|a, b| a.available().total_cmp(&b.available()))
+        .filter(|e| e.available() >= missing - TOLERANCE)
+        .ok_or_else(|| format!("No envelope has {:.2} to spare", missing))?;
+    let from = source.category.clone();
+    move_money(budget, transactions, month, &from, category, missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DataStructure, Transaction};
+
+    fn month(text: &str) -> NaiveDate {
+        parse_month(text).unwrap()
+    }
+
+    fn transaction(id: u32, day: &str, category: &str, amount: f64) -> Transaction {
+        Transaction {
+            id,
+            amount,
+            category: category.to_string(),
+            date: NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn envelope(
+        budget: &Budget,
+        transactions: &Transactions,
+        at: &str,
+        category: &str,
+    ) -> Envelope {
+        envelopes(budget, transactions, month(at))
+            .into_iter()
+            .find(|e| e.category == category)
+            .unwrap()
+    }
+
+    // Budgeting since January with 1000 of income in January
+    fn setup() -> (Budget, Transactions) {
+        let mut transactions = DataStructure::default().transactions;
+        transactions
+            .income
+            .push(transaction(1, "2026-01-02", "Salary", 1000.0));
+        let budget = Budget {
+            start: Some(month("2026-01")),
+            assignments: Vec::new(),
+        };
+        (budget, transactions)
+    }
+
+    #[test]
+    fn only_received_money_can_be_assigned() {
+        let (mut budget, transactions) = setup();
+        assign(&mut budget, &transactions, month("2026-01"), "Food", 300.0).unwrap();
+        assign(&mut budget, &transactions, month("2026-01"), "Bills", 500.0).unwrap();
+        assert_eq!(
+            to_be_budgeted(&budget, &transactions, month("2026-01")),
+            200.0
+        );
+        assert_eq!(
+            assign(&mut budget, &transactions, month("2026-01"), "Food", 300.0),
+            Err("Only 200.00 left to budget".to_string())
+        );
+        // Money assigned in a later month is no longer available earlier
+        assign(
+            &mut budget,
+            &transactions,
+            month("2026-03"),
+            "Health",
+            150.0,
+        )
+        .unwrap();
+        assert_eq!(
+            assign(&mut budget, &transactions, month("2026-01"), "Food", 100.0),
+            Err("Only 50.00 left to budget".to_string())
+        );
+        assert!(assign(&mut budget, &transactions, month("2025-12"), "Food", 10.0).is_err());
+        assert!(assign(&mut budget, &transactions, month("2026-01"), "Boats", 10.0).is_err());
+        assert!(assign(
+            &mut budget,
+            &transactions,
+            month("2026-01"),
+            "Food",
+            f64::NAN
+        )
+        .is_err());
+        // Taking out more than the envelope holds fails
+        assert!(assign(&mut budget, &transactions, month("2026-01"), "Food", -301.0).is_err());
+        assign(&mut budget, &transactions, month("2026-01"), "Food", -300.0).unwrap();
+        assert_eq!(
+            to_be_budgeted(&budget, &transactions, month("2026-01")),
+            500.0
+        );
+        assert_eq!(
+            to_be_budgeted(&budget, &transactions, month("2026-03")),
+            350.0
+        );
+    }
+
+    #[test]
+    fn balances_roll_over_to_the_next_month() {
+        let (mut budget, mut transactions) = setup();
+        assign(&mut budget, &transactions, month("2026-01"), "Food", 300.0).unwrap();
+        assign(&mut budget, &transactions, month("2026-01"), "Bills", 500.0).unwrap();
+        transactions.expenses = vec![
+            transaction(1, "2026-01-10", "Food", -350.0),
+            transaction(2, "2026-01-15", "Bills", -100.0),
+            transaction(3, "2026-02-03", "Bills", -20.0),
+        ];
+
+        let food = envelope(&budget, &transactions, "2026-01", "Food");
+        assert_eq!(
+            (food.carried, food.assigned, food.spent),
+            (0.0, 300.0, 350.0)
+        );
+        assert!(food.is_overspent());
+
+        // Overspending and unspent money are both carried into February
+        let food = envelope(&budget, &transactions, "2026-02", "Food");
+        assert_eq!(food.carried, -50.0);
+        assert_eq!(food.spent, 0.0);
+        let bills = envelope(&budget, &transactions, "2026-02", "Bills");
+        assert_eq!(bills.carried, 400.0);
+        assert_eq!(bills.available(), 380.0);
+
+        // Covering takes the missing money from the fullest envelope
+        cover(&mut budget, &transactions, month("2026-02"), "Food").unwrap();
+        assert_eq!(
+            available(&budget, &transactions, month("2026-02"), "Food"),
+            0.0
+        );
+        assert_eq!(
+            available(&budget, &transactions, month("2026-02"), "Bills"),
+            330.0
+        );
+        assert!(cover(&mut budget, &transactions, month("2026-02"), "Food").is_err());
+        assert_eq!(
+            to_be_budgeted(&budget, &transactions, month("2026-02")),
+            200.0
+        );
+    }
+
+    #[test]
+    fn moving_money_needs_enough_in_the_source_envelope() {
+        let (mut budget, transactions) = setup();
+        assign(&mut budget, &transactions, month("2026-01"), "Food", 100.0).unwrap();
+        move_money(
+            &mut budget,
+            &transactions,
+            month("2026-01"),
+            "Food",
+            "Bills",
+            60.0,
+        )
+        .unwrap();
+        assert!(move_money(
+            &mut budget,
+            &transactions,
+            month("2026-01"),
+            "Food",
+            "Bills",
+            60.0
+        )
+        .is_err());
+        assert!(move_money(
+            &mut budget,
+            &transactions,
+            month("2026-01"),
+            "Food",
+            "Bills",
+            -5.0
+        )
+        .is_err());
+        assert_eq!(
+            available(&budget, &transactions, month("2026-01"), "Food"),
+            40.0
+        );
+        assert_eq!(
+            available(&budget, &transactions, month("2026-01"), "Bills"),
+            60.0
+        );
+    }
+}
@@ -0,0 +1,1130 @@
+use crate::bills::{self, REMINDER_DAYS};
+use crate::budget;
+use crate::crypto::{self, Key};
+use crate::data::{
+    Bill, Data, Goal, Loan, Shared, Trade, TradeKind, Transaction, Transfer, DATA_FILE,
+};
+use crate::export::{self, Format};
+use crate::goals;
+use crate::html;
+use crate::import;
+use crate::investments::{self, PRICES_FILE};
+use crate::loans::{self, Strategy};
+use crate::report::{self, Comparison, Grouping, Period};
+use crate::shared;
+use crate::storage::{self, Storage};
+use crate::tax;
+use chrono::{Datelike, Local, Months, NaiveDate};
+use crossterm::style::Stylize;
+use std::error::Error;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+const USAGE: &str = "Usage: fintrackr [COMMAND]
+
+Without a command the interactive TUI is started.
+
+Commands:
+  report [--period month|year] [--date YYYY-MM-DD] [--by category|payee|tag] [--html FILE]
+      Compare spending per category, payee or tag against the previous period and the same period last year,
+      or write a self-contained HTML report with tables and charts
+  tax report [--year YYYY] [--csv FILE]
+      Total deductible expenses and taxable income per tax code for a fiscal year
+  tax fiscal-year MM-DD
+      Set the first day of the fiscal year
+  tax deductible|taxable CATEGORY [CODE]
+      Mark an expense category as deductible or an income category as taxable
+  tax clear CATEGORY
+      Remove the tax configuration of a category
+  tax mark|unmark EXPENSE_ID [CODE]
+      Mark a single expense as deductible, optionally with its own tax code
+  loan add NAME --kind mortgage|car|personal --principal AMOUNT --rate PERCENT --term MONTHS
+           --start YYYY-MM-DD [--day DAY]
+      Record a loan paid back in fixed monthly payments
+  loan list
+      Show the remaining balance and payoff date of every loan
+  loan schedule ID
+      Print the amortization schedule of a loan
+  loan pay ID
+      Record the next payment of a loan as an expense split into interest and principal
+  loan remove ID
+      Delete a loan, keeping its recorded payments
+  loan plan [--extra AMOUNT]
+      Compare paying off all loans with the snowball and avalanche strategies
+  invest buy|sell SYMBOL QUANTITY PRICE [--date YYYY-MM-DD]
+      Record a brokerage trade; sales are matched against the oldest lots first (FIFO)
+  invest dividend SYMBOL AMOUNT [--date YYYY-MM-DD]
+      Record a dividend as income
+  invest list [--prices FILE]
+      Show open positions at the latest prices with unrealized gains
+  invest gains [--year YYYY]
+      Show realized gains of all sales, optionally for one calendar year
+  networth [--prices FILE]
+      Add up cash, investments and debts
+  goal add NAME TARGET --by YYYY-MM[-DD] [--tag TAG] [--symbol SYMBOL] [--saved AMOUNT]
+      Add a savings goal; transactions tagged with TAG (default goal:NAME) count as contributions
+  goal list
+      Show the progress of every goal
+  goal remove ID
+      Delete a goal
+  bill add PAYEE AMOUNT --category CATEGORY --due YYYY-MM-DD [--every MONTHS]
+      Add a recurring bill, due every month unless --every says otherwise
+  bill list [--days N]
+      Show every bill with its next due date, then the overdue bills and those due within N days
+  bill pay ID [--date YYYY-MM-DD]
+      Mark the current due date of a bill as paid and record the expense
+  bill remove ID
+      Delete a bill, keeping its recorded payments
+  budget start [YYYY-MM]
+      Turn on zero-based budgeting from the given month (default this month)
+  budget off
+      Turn zero-based budgeting off, keeping the envelopes
+  budget show [--month YYYY-MM]
+      Show money left to budget and every envelope with its rolled over balance
+  budget assign CATEGORY AMOUNT [--month YYYY-MM]
+      Put money that is left to budget into an envelope (negative to take it out again)
+  budget move FROM TO AMOUNT [--month YYYY-MM]
+      Move money between envelopes, e.g. to cover overspending
+  share set EXPENSE_ID --paid-by NAME --equal A,B | --shares A:2,B:1 | --exact A:30,B:20
+      Record who paid an expense and split it equally, by shares or by exact amounts
+  share clear EXPENSE_ID
+      Stop sharing an expense
+  share balances
+      Show who owes whom and the fewest payments that settle everything
+  share settle FROM TO AMOUNT [--date YYYY-MM-DD]
+      Record money paid from one person to another as a transfer
+  export ledger|hledger|beancount [--output FILE]
+      Write all transactions as a plain-text accounting journal, to standard output by default
+  export accounts
+      Show the account every category is exported to
+  export map expense|income CATEGORY [ACCOUNT]
+      Export a category to another account than Expenses:<Category> or Income:<Category>;
+      without ACCOUNT the category gets its default account again
+  export asset ACCOUNT | export commodity CODE
+      Set the account the money comes from and goes to (default Assets:Checking) and the currency
+  delete expense|income ID
+      Delete a transaction; reconciled transactions and expenses with refunds are kept
+  recategorize expense|income FROM TO
+      Move all transactions and split lines of category FROM to category TO, except reconciled ones
+  import expense|income FILE
+      Add the transactions of a CSV file with lines date,amount,category,description[,payee]
+  undo | redo
+      Revert the latest change to the ledger, or make the latest undone change again
+  history
+      List the changes that can be undone and redone
+  encrypt on|off|rotate
+      Encrypt the ledger file with a passphrase, store it in plain text again, or change the passphrase;
+      FINTRACKR_PASSPHRASE and FINTRACKR_NEW_PASSPHRASE can hold the current and new passphrase
+  migrate
+      Move the ledger into a SQLite database (transactions.db); needs the sqlite feature
+  help
+      Show this message";
+
+// Entry point for the non-interactive commands
+pub fn run(args: &[String]) -> Result<(), Box<dyn Error>> {
+    match args[0].as_str() {
+        "report" => report(&args[1..]),
+        "tax" => tax(&args[1..]),
+        "loan" => loan(&args[1..]),
+        "invest" => invest(&args[1..]),
+        "networth" => net_worth(&args[1..]),
+        "goal" => goal(&args[1..]),
+        "share" => share(&args[1..]),
+        "budget" => budget(&args[1..]),
+        "bill" => bill(&args[1..]),
+        "export" => export(&args[1..]),
+        "delete" => delete(&args[1..]),
+        "recategorize" => recategorize(&args[1..]),
+        "import" => import(&args[1..]),
+        "undo" => undo(false),
+        "redo" => undo(true),
+        "history" => history(),
+        "encrypt" => encrypt(&args[1..]),
+        "migrate" => migrate(),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        other => Err(format!("Unknown command '{}'\n\n{}", other, USAGE).into()),
+    }
+}
+
+// Look up the value following a `--name` option
+fn option_value<'a>(args: &'a [String], name: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == name)
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.as_str())
+}
+
+fn parse_period(args: &[String]) -> Result<Period, Box<dyn Error>> {
+    match option_value(args, "--period") {
+        None | Some("month") => Ok(Period::Month),
+        Some("year") => Ok(Period::Year),
+        Some(other) => Err(format!("Invalid period '{}', expected month or year", other).into()),
+    }
+}
+
+fn parse_date(args: &[String]) -> Result<NaiveDate, Box<dyn Error>> {
+    match option_value(args, "--date") {
+        Some(date) => Ok(NaiveDate::parse_from_str(date, "%Y-%m-%d")?),
+        None => Ok(Local::now().date_naive()),
+    }
+}
+
+fn report(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let period = parse_period(args)?;
+    let anchor = parse_date(args)?;
+    let data = Data::new(DATA_FILE)?;
+
+    if let Some(path) = option_value(args, "--html") {
+        fs::write(path, html::render(data.get_transactions(), period, anchor))?;
+        println!(
+            "Wrote {} report to {}",
+            period.label(period.start_of(anchor)),
+            path
+        );
+        return Ok(());
+    }
+
+    let grouping = match option_value(args, "--by") {
+        None | Some("category") => Grouping::Category,
+        Some("payee") => Grouping::Payee,
+        Some("tag") => Grouping::Tag,
+        Some(other) => {
+            return Err(format!(
+                "Invalid grouping '{}', expected category, payee or tag",
+                other
+            )
+            .into())
+        }
+    };
+    let start = period.start_of(anchor);
+    let rows = report::compare(data.get_transactions(), grouping, period, anchor);
+    println!(
+        "Spending by {} for {} compared to {} (previous {}) and {} (last year)\n",
+        grouping,
+        period.label(start),
+        period.label(period.shift(start, -1)),
+        period,
+        period.label(start - Months::new(12)),
+    );
+    print_comparison(grouping, &rows);
+    Ok(())
+}
+
+fn print_comparison(grouping: Grouping, rows: &[Comparison]) {
+    let colored = io::stdout().is_terminal();
+    let heading = match grouping {
+        Grouping::Category => "Category",
+        Grouping::Payee => "Payee",
+        Grouping::Tag => "Tag",
+    };
+    println!(
+        "{:<16} {:>10} {:>10} {:>22} {:>10} {:>22}",
+        heading, "Current", "Previous", "Change", "Last year", "Change"
+    );
+    let total = Comparison::total(grouping, rows);
+    for row in rows.iter().chain(total.iter()) {
+        let previous_change = report::format_delta(row.previous_delta(), row.previous_pct());
+        let last_year_change = report::format_delta(row.last_year_delta(), row.last_year_pct());
+        println!(
+            "{:<16} {:>10.2} {:>10.2} {} {:>10.2} {}",
+            row.name,
+            row.current,
+            row.previous,
+            highlight(
+                &format!("{:>22}", previous_change),
+                row.previous_delta(),
+                colored
+            ),
+            row.last_year,
+            highlight(
+                &format!("{:>22}", last_year_change),
+                row.last_year_delta(),
+                colored
+            ),
+        );
+    }
+}
+
+// Spending increases are shown in red, decreases in green
+fn highlight(text: &str, delta: f64, colored: bool) -> String {
+    if !colored || delta == 0.0 {
+        text.to_string()
+    } else if delta > 0.0 {
+        text.red().bold().to_string()
+    } else {
+        text.green().to_string()
+    }
+}
+
+// Positional argument `index`, or a usage error naming the missing value
+fn positional<'a>(args: &'a [String], index: usize, name: &str) -> Result<&'a str, Box<dyn Error>> {
+    args.get(index)
+        .map(|s| s.as_str())
+        .ok_or_else(|| format!("Missing {}\n\n{}", name, USAGE).into())
+}
+
+fn tax(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    let mut settings = data.get_tax_settings().clone();
+    match positional(args, 0, "tax command")? {
+        "report" => {
+            let year = match option_value(args, "--year") {
+                Some(year) => year.parse()?,
+                None => tax::fiscal_year_of(&settings, Local::now().date_naive()),
+            };
+            let lines = tax::tax_lines(data.get_transactions(), &settings, year);
+            let (start, end) = tax::fiscal_year_range(&settings, year);
+            println!(
+                "Tax year {} ({} to {})\n",
+                tax::fiscal_year_label(&settings, year),
+                start,
+                end.pred_opt().unwrap_or(end)
+            );
+            println!(
+                "{:<12} {:<12} {:>6} {:>12}",
+                "Kind", "Tax code", "Items", "Total"
+            );
+            for total in tax::summarize(&lines) {
+                println!(
+                    "{:<12} {:<12} {:>6} {:>12.2}",
+                    total.kind.to_string(),
+                    if total.code.is_empty() {
+                        "-"
+                    } else {
+                        &total.code
+                    },
+                    total.count,
+                    total.total
+                );
+            }
+            if let Some(path) = option_value(args, "--csv") {
+                fs::write(path, tax::to_csv(&lines))?;
+                println!("\nWrote {} line(s) to {}", lines.len(), path);
+            }
+            return Ok(());
+        }
+        "fiscal-year" => {
+            let start = positional(args, 1, "fiscal year start (MM-DD)")?;
+            let date = NaiveDate::parse_from_str(&format!("2000-{}", start), "%Y-%m-%d")?;
+            settings.fiscal_year_start_month = date.month();
+            settings.fiscal_year_start_day = date.day();
+        }
+        "deductible" => {
+            let category = positional(args, 1, "category")?;
+            let code = args.get(2).cloned().unwrap_or_default();
+            settings
+                .deductible_categories
+                .insert(category.to_string(), code);
+        }
+        "taxable" => {
+            let category = positional(args, 1, "category")?;
+            let code = args.get(2).cloned().unwrap_or_default();
+            settings
+                .taxable_categories
+                .insert(category.to_string(), code);
+        }
+        "clear" => {
+            let category = positional(args, 1, "category")?;
+            settings.deductible_categories.remove(category);
+            settings.taxable_categories.remove(category);
+        }
+        command @ ("mark" | "unmark") => {
+            let mut expense = find_expense(&data, args)?;
+            expense.deductible = command == "mark";
+            expense.tax_code = if expense.deductible {
+                args.get(2).cloned()
+            } else {
+                None
+            };
+            data.update_transaction(expense, "expenses")?;
+            return Ok(());
+        }
+        other => return Err(format!("Unknown tax command '{}'\n\n{}", other, USAGE).into()),
+    }
+    data.update_tax_settings(settings)?;
+    Ok(())
+}
+
+// Value of a required `--name` option
+fn required_option<'a>(args: &'a [String], name: &str) -> Result<&'a str, Box<dyn Error>> {
+    option_value(args, name).ok_or_else(|| format!("Missing {}\n\n{}", name, USAGE).into())
+}
+
+fn find_loan(data: &Data, args: &[String]) -> Result<Loan, Box<dyn Error>> {
+    let id: u32 = positional(args, 1, "loan id")?.parse()?;
+    data.get_loans()
+        .iter()
+        .find(|l| l.id == id)
+        .cloned()
+        .ok_or_else(|| format!("No loan with id {}", id).into())
+}
+
+fn loan(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    match positional(args, 0, "loan command")? {
+        "add" => {
+            let start = NaiveDate::parse_from_str(required_option(args, "--start")?, "%Y-%m-%d")?;
+            let loan = Loan {
+                id: 0,
+                name: positional(args, 1, "loan name")?.to_string(),
+                kind: required_option(args, "--kind")?.to_string(),
+                principal: required_option(args, "--principal")?.parse()?,
+                rate: required_option(args, "--rate")?.parse()?,
+                term_months: required_option(args, "--term")?.parse()?,
+                start,
+                payment_day: match option_value(args, "--day") {
+                    Some(day) => day.parse()?,
+                    None => start.day(),
+                },
+            };
+            loans::validate(&loan)?;
+            let payment = loans::monthly_payment(&loan);
+            let id = data.add_loan(loan)?;
+            println!("Added loan {} with a monthly payment of {:.2}", id, payment);
+        }
+        "list" => {
+            println!(
+                "{:>4} {:<20} {:<10} {:>12} {:>7} {:>10} {:>12} {:>6}",
+                "Id", "Name", "Kind", "Balance", "Rate", "Payment", "Payoff", "Left"
+            );
+            for loan in data.get_loans() {
+                let remaining = loans::remaining_schedule(loan, data.get_transactions());
+                println!(
+                    "{:>4} {:<20} {:<10} {:>12.2} {:>6.2}% {:>10.2} {:>12} {:>6}",
+                    loan.id,
+                    loan.name,
+                    loan.kind,
+                    loans::balance(loan, data.get_transactions()),
+                    loan.rate,
+                    loans::monthly_payment(loan),
+                    remaining
+                        .last()
+                        .map(|i| i.date.to_string())
+                        .unwrap_or_else(|| "paid off".to_string()),
+                    remaining.len()
+                );
+            }
+        }
+        "schedule" => {
+            let loan = find_loan(&data, args)?;
+            println!(
+                "{:>4} {:<12} {:>10} {:>10} {:>10} {:>12}",
+                "#", "Date", "Payment", "Interest", "Principal", "Balance"
+            );
+            for i in loans::schedule(&loan) {
+                println!(
+                    "{:>4} {:<12} {:>10.2} {:>10.2} {:>10.2} {:>12.2}",
+                    i.number,
+                    i.date.to_string(),
+                    i.payment,
+                    i.interest,
+                    i.principal,
+                    i.balance
+                );
+            }
+        }
+        "pay" => {
+            let loan = find_loan(&data, args)?;
+            let next = loans::remaining_schedule(&loan, data.get_transactions())
+                .first()
+                .copied()
+                .ok_or_else(|| format!("Loan {} is paid off", loan.id))?;
+            let id = data.next_transaction_id("expenses");
+            data.add_transaction(loans::payment_transaction(&loan, &next, id), "expenses")?;
+            println!(
+                "Recorded payment {} of {:.2} on {} ({:.2} interest, {:.2} principal)",
+                next.number, next.payment, next.date, next.interest, next.principal
+            );
+        }
+        "remove" => {
+            let loan = find_loan(&data, args)?;
+            data.remove_loan(loan.id)?;
+        }
+        "plan" => {
+            let extra: f64 = match option_value(args, "--extra") {
+                Some(extra) => extra.parse()?,
+                None => 0.0,
+            };
+            if !extra.is_finite() || extra < 0.0 {
+                return Err("--extra must be zero or more".into());
+            }
+            for strategy in [Strategy::Snowball, Strategy::Avalanche] {
+                let plan = loans::plan(data.get_loans(), data.get_transactions(), strategy, extra);
+                println!(
+                    "{}: debt-free {}, total interest {:.2}",
+                    strategy,
+                    plan.debt_free()
+                        .map(|d| format!("by {}", d.format("%Y-%m")))
+                        .unwrap_or_else(|| "never".to_string()),
+                    plan.total_interest
+                );
+                for (name, date) in &plan.payoff {
+                    println!(
+                        "  {:<20} {}",
+                        name,
+                        date.map(|d| d.format("%Y-%m").to_string())
+                            .unwrap_or_else(|| "never".to_string())
+                    );
+                }
+            }
+        }
+        other => return Err(format!("Unknown loan command '{}'\n\n{}", other, USAGE).into()),
+    }
+    Ok(())
+}
+
+fn invest(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    let date = parse_date(args)?;
+    let prices = investments::load_prices(option_value(args, "--prices").unwrap_or(PRICES_FILE))?;
+    match positional(args, 0, "invest command")? {
+        command @ ("buy" | "sell") => {
+            let trade = Trade {
+                id: 0,
+                kind: if command == "buy" {
+                    TradeKind::Buy
+                } else {
+                    TradeKind::Sell
+                },
+                symbol: positional(args, 1, "symbol")?.to_uppercase(),
+                date,
+                quantity: positional(args, 2, "quantity")?.parse()?,
+                price: positional(args, 3, "price")?.parse()?,
+            };
+            let valid = trade.quantity > 0.0
+                && trade.quantity.is_finite()
+                && trade.price >= 0.0
+                && trade.price.is_finite();
+            if !valid {
+                return Err("Quantity must be positive and the price not negative".into());
+            }
+            // Refuse sales of shares that are not held
+            let mut trades = data.get_trades().to_vec();
+            trades.push(trade.clone());
+            investments::portfolio(&trades, &prices, NaiveDate::MAX)?;
+            let id = data.add_trade(trade)?;
+            println!("Recorded trade {}", id);
+        }
+        "dividend" => {
+            let symbol = positional(args, 1, "symbol")?.to_uppercase();
+            let amount: f64 = positional(args, 2, "amount")?.parse()?;
+            if !(amount > 0.0 && amount.is_finite()) {
+                return Err("The dividend must be greater than zero".into());
+            }
+            let id = data.next_transaction_id("income");
+            data.add_transaction(
+                Transaction {
+                    id,
+                    amount,
+                    category: "Dividends".to_string(),
+                    date,
+                    description: format!("Dividend {}", symbol),
+                    payee: symbol,
+                    ..Default::default()
+                },
+                "income",
+            )?;
+        }
+        "list" => {
+            let portfolio = investments::portfolio(data.get_trades(), &prices, date)?;
+            println!(
+                "{:<8} {:>10} {:>12} {:>12} {:>12} {:>12}  Priced on",
+                "Symbol", "Quantity", "Cost", "Price", "Value", "Unrealized"
+            );
+            for position in &portfolio.positions {
+                println!(
+                    "{:<8} {:>10.4} {:>12.2} {:>12} {:>12.2} {:>12.2}  {}",
+                    position.symbol,
+                    position.quantity(),
+                    position.cost(),
+                    position
+                        .price
+                        .map(|(_, price)| format!("{:.2}", price))
+                        .unwrap_or_else(|| "-".to_string()),
+                    position.value(),
+                    position.unrealized(),
+                    position
+                        .price
+                        .map(|(date, _)| date.to_string())
+                        .unwrap_or_else(|| "no price, valued at cost".to_string())
+                );
+            }
+            println!(
+                "\nValue {:.2}, unrealized {:.2}, realized {:.2}",
+                portfolio.value(),
+                portfolio.unrealized(),
+                portfolio.realized()
+            );
+        }
+        "gains" => {
+            let year: Option<i32> = option_value(args, "--year").map(str::parse).transpose()?;
+            let portfolio = investments::portfolio(data.get_trades(), &prices, NaiveDate::MAX)?;
+            println!(
+                "{:<12} {:<8} {:>10} {:>12} {:>12} {:>12}",
+                "Date", "Symbol", "Quantity", "Proceeds", "Cost", "Gain"
+            );
+            let sales: Vec<_> = portfolio
+                .realized
+                .iter()
+                .filter(|r| year.is_none_or(|year| r.date.year() == year))
+                .collect();
+            for sale in &sales {
+                println!(
+                    "{:<12} {:<8} {:>10.4} {:>12.2} {:>12.2} {:>12.2}",
+                    sale.date.to_string(),
+                    sale.symbol,
+                    sale.quantity,
+                    sale.proceeds,
+                    sale.cost,
+                    sale.gain()
+                );
+            }
+            println!(
+                "\nRealized gain {:.2}",
+                report::sum(sales.iter().map(|s| s.gain()))
+            );
+        }
+        other => return Err(format!("Unknown invest command '{}'\n\n{}", other, USAGE).into()),
+    }
+    Ok(())
+}
+
+fn net_worth(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let data = Data::new(DATA_FILE)?;
+    let prices = investments::load_prices(option_value(args, "--prices").unwrap_or(PRICES_FILE))?;
+    let today = Local::now().date_naive();
+    let portfolio = investments::portfolio(data.get_trades(), &prices, today)?;
+    let worth = investments::net_worth(
+        data.get_transactions(),
+        data.get_trades(),
+        data.get_loans(),
+        &portfolio,
+        today,
+    );
+    println!("Cash         {:>12.2}", worth.cash);
+    println!("Investments  {:>12.2}", worth.investments);
+    println!("Debts        {:>12.2}", 0.0 - worth.debts);
+    println!("Net worth    {:>12.2}", worth.total());
+    Ok(())
+}
+
+fn goal(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    let today = Local::now().date_naive();
+    match positional(args, 0, "goal command")? {
+        "add" => {
+            let name = positional(args, 1, "goal name")?.to_string();
+            let tag = match option_value(args, "--tag") {
+                Some(tag) => tag.trim_start_matches('#').to_string(),
+                None => goals::default_tag(&name),
+            };
+            let goal = Goal {
+                id: 0,
+                target: positional(args, 2, "target amount")?.parse()?,
+                start: today,
+                due: goals::parse_due(required_option(args, "--by")?)?,
+                symbol: option_value(args, "--symbol").map(str::to_uppercase),
+                starting_amount: match option_value(args, "--saved") {
+                    Some(saved) => saved.parse()?,
+                    None => 0.0,
+                },
+                name,
+                tag,
+            };
+            if !(goal.target > 0.0 && goal.target.is_finite()) {
+                return Err("The target must be greater than zero".into());
+            }
+            if !(goal.starting_amount >= 0.0 && goal.starting_amount.is_finite()) {
+                return Err("--saved must be zero or more".into());
+            }
+            if goal.due <= today {
+                return Err("The target date must be in the future".into());
+            }
+            let id = data.add_goal(goal.clone())?;
+            println!(
+                "Added goal {}; tag transactions with #{} to contribute",
+                id, goal.tag
+            );
+        }
+        "list" => {
+            let prices = investments::load_prices(PRICES_FILE)?;
+            let portfolio = investments::portfolio(data.get_trades(), &prices, today)?;
+            println!(
+                "{:>4} {:<20} {:>10} {:>10} {:>6} {:>12} {:>10}  Status",
+                "Id", "Name", "Saved", "Target", "Done", "Due", "Monthly"
+            );
+            for goal in data.get_goals() {
+                let progress = goals::progress(goal, data.get_transactions(), &portfolio, today);
+                println!(
+                    "{:>4} {:<20} {:>10.2} {:>10.2} {:>5.0}% {:>12} {:>10.2}  {}",
+                    goal.id,
+                    goal.name,
+                    progress.saved,
+                    goal.target,
+                    progress.ratio(goal) * 100.0,
+                    goal.due.to_string(),
+                    progress.required_monthly,
+                    progress.status()
+                );
+            }
+        }
+        "remove" => {
+            let id: u32 = positional(args, 1, "goal id")?.parse()?;
+            data.remove_goal(id)?;
+        }
+        other => return Err(format!("Unknown goal command '{}'\n\n{}", other, USAGE).into()),
+    }
+    Ok(())
+}
+
+fn find_expense(data: &Data, args: &[String]) -> Result<Transaction, Box<dyn Error>> {
+    let id: u32 = positional(args, 1, "expense id")?.parse()?;
+    data.get_transactions()
+        .expenses
+        .iter()
+        .find(|t| t.id == id)
+        .cloned()
+        .ok_or_else(|| format!("No expense with id {}", id).into())
+}
+
+fn share(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    match positional(args, 0, "share command")? {
+        "set" => {
+            let mut expense = find_expense(&data, args)?;
+            let total = expense.amount.abs();
+            let shares = if let Some(people) = option_value(args, "--equal") {
+                shared::equal(total, people)?
+            } else if let Some(input) = option_value(args, "--shares") {
+                shared::shares(total, input)?
+            } else if let Some(input) = option_value(args, "--exact") {
+                shared::exact(total, input)?
+            } else {
+                return Err(format!("Missing --equal, --shares or --exact\n\n{}", USAGE).into());
+            };
+            expense.shared = Some(Shared {
+                paid_by: required_option(args, "--paid-by")?.to_string(),
+                shares: shares.clone(),
+            });
+            // Refused for reconciled expenses
+            data.update_transaction(expense, "expenses")?;
+            for share in &shares {
+                println!("{:<20} {:>10.2}", share.person, share.amount);
+            }
+        }
+        "clear" => {
+            let mut expense = find_expense(&data, args)?;
+            expense.shared = None;
+            data.update_transaction(expense, "expenses")?;
+        }
+        "balances" => {
+            let balances = shared::balances(data.get_transactions(), data.get_transfers());
+            for (person, balance) in &balances {
+                let state = if *balance > 0.0 {
+                    "is owed"
+                } else if *balance < 0.0 {
+                    "owes"
+                } else {
+                    "is settled"
+                };
+                println!("{:<20} {:<10} {:>10.2}", person, state, balance.abs());
+            }
+            let payments = shared::settle_up(&balances);
+            if !payments.is_empty() {
+                println!("\nTo settle up:");
+            }
+            for (from, to, amount) in payments {
+                println!("  {} pays {} {:.2}", from, to, amount);
+            }
+        }
+        "settle" => {
+            let transfer = Transfer {
+                id: 0,
+                date: parse_date(args)?,
+                from: positional(args, 1, "payer")?.to_string(),
+                to: positional(args, 2, "recipient")?.to_string(),
+                amount: positional(args, 3, "amount")?.parse()?,
+            };
+            if !(transfer.amount > 0.0 && transfer.amount.is_finite()) {
+                return Err("The amount must be positive".into());
+            }
+            let id = data.add_transfer(transfer)?;
+            println!("Recorded transfer {}", id);
+        }
+        other => return Err(format!("Unknown share command '{}'\n\n{}", other, USAGE).into()),
+    }
+    Ok(())
+}
+
+fn budget(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    let mut envelopes = data.get_budget().clone();
+    let this_month = Period::Month.start_of(Local::now().date_naive());
+    let month = match option_value(args, "--month") {
+        Some(month) => budget::parse_month(month)?,
+        None => this_month,
+    };
+    match positional(args, 0, "budget command")? {
+        "start" => {
+            envelopes.start = Some(match args.get(1) {
+                Some(month) => budget::parse_month(month)?,
+                None => this_month,
+            });
+        }
+        "off" => envelopes.start = None,
+        "show" => {
+            let transactions = data.get_transactions();
+            println!(
+                "To be budgeted in {}: {:.2}\n",
+                month.format("%Y-%m"),
+                budget::to_be_budgeted(&envelopes, transactions, month)
+            );
+            println!(
+                "{:<20} {:>10} {:>10} {:>10} {:>10}",
+                "Envelope", "Carried", "Assigned", "Spent", "Available"
+            );
+            for envelope in budget::envelopes(&envelopes, transactions, month) {
+                let available = format!("{:>10.2}", envelope.available());
+                println!(
+                    "{:<20} {:>10.2} {:>10.2} {:>10.2} {}",
+                    envelope.category,
+                    envelope.carried,
+                    envelope.assigned,
+                    envelope.spent,
+                    highlight(
+                        &available,
+                        -envelope.available(),
+                        io::stdout().is_terminal()
+                    )
+                );
+            }
+            return Ok(());
+        }
+        "assign" => {
+            let category = positional(args, 1, "category")?;
+            let amount: f64 = positional(args, 2, "amount")?.parse()?;
+            budget::assign(
+                &mut envelopes,
+                data.get_transactions(),
+                month,
+                category,
+                amount,
+            )?;
+        }
+        "move" => {
+            let from = positional(args, 1, "source envelope")?;
+            let to = positional(args, 2, "target envelope")?;
+            let amount: f64 = positional(args, 3, "amount")?.parse()?;
+            budget::move_money(
+                &mut envelopes,
+                data.get_transactions(),
+                month,
+                from,
+                to,
+                amount,
+            )?;
+        }
+        other => return Err(format!("Unknown budget command '{}'\n\n{}", other, USAGE).into()),
+    }
+    data.update_budget(envelopes)?;
+    Ok(())
+}
+
+fn bill(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    let today = Local::now().date_naive();
+    match positional(args, 0, "bill command")? {
+        "add" => {
+            let bill = Bill {
+                id: 0,
+                payee: positional(args, 1, "payee")?.to_string(),
+                amount: positional(args, 2, "amount")?.parse()?,
+                category: required_option(args, "--category")?.to_string(),
+                first_due: NaiveDate::parse_from_str(required_option(args, "--due")?, "%Y-%m-%d")?,
+                every_months: match option_value(args, "--every") {
+                    Some(months) => months.parse()?,
+                    None => 1,
+                },
+                paid: Vec::new(),
+            };
+            if !(bill.amount > 0.0 && bill.amount.is_finite()) {
+                return Err("The amount must be greater than zero".into());
+            }
+            if bill.every_months == 0 {
+                return Err("--every must be at least 1 month".into());
+            }
+            let id = data.add_bill(bill)?;
+            println!("Added bill {}", id);
+        }
+        "list" => {
+            let days = match option_value(args, "--days") {
+                Some(days) => days.parse()?,
+                None => REMINDER_DAYS,
+            };
+            println!(
+                "{:>4} {:<20} {:<16} {:>10} {:>7} {:>12}",
+                "Id", "Payee", "Category", "Amount", "Every", "Next due"
+            );
+            for bill in data.get_bills() {
+                println!(
+                    "{:>4} {:<20} {:<16} {:>10.2} {:>6}m {:>12}",
+                    bill.id,
+                    bill.payee,
+                    bill.category,
+                    bill.amount,
+                    bill.every_months,
+                    bills::next_due(bill, today)
+                        .map_or("-".to_string(), |due| due.date.to_string())
+                );
+            }
+            let reminders = bills::reminders(data.get_bills(), today, days);
+            if !reminders.is_empty() {
+                println!("\nOverdue or due within {} days:", days);
+            }
+            let colored = io::stdout().is_terminal();
+            for due in reminders {
+                let line = format!(
+                    "  {} {} {:.2} ({})",
+                    due.date,
+                    due.payee,
+                    due.amount,
+                    due.status(today)
+                );
+                let overdue = if due.is_overdue(today) { 1.0 } else { 0.0 };
+                println!("{}", highlight(&line, overdue, colored));
+            }
+        }
+        "pay" => {
+            let id: u32 = positional(args, 1, "bill id")?.parse()?;
+            let bill = data
+                .get_bills()
+                .iter()
+                .find(|b| b.id == id)
+                .cloned()
+                .ok_or_else(|| format!("No bill with id {}", id))?;
+            let date = parse_date(args)?;
+            let due = bills::next_due(&bill, date).ok_or("The bill has no further due date")?;
+            let expense_id = data.next_transaction_id("expenses");
+            let payment = bills::payment_transaction(&bill, due.date, date, expense_id);
+            data.pay_bill(id, due.date, payment)?;
+            println!("Paid {} bill due {}", bill.payee, due.date);
+        }
+        "remove" => {
+            let id: u32 = positional(args, 1, "bill id")?.parse()?;
+            data.remove_bill(id)?;
+        }
+        other => return Err(format!("Unknown bill command '{}'\n\n{}", other, USAGE).into()),
+    }
+    Ok(())
+}
+
+// Write a ledger, hledger or beancount journal, or configure the accounts it uses
+fn export(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    let mut settings = data.get_export_settings().clone();
+    match positional(args, 0, "format or export command")? {
+        "accounts" => {
+            println!("Asset account: {}", settings.asset_account);
+            println!("Commodity:     {}\n", settings.commodity);
+            let transactions = data.get_transactions();
+            for (kind, categories) in [
+                ("expenses", &transactions.expense_categories),
+                ("income", &transactions.income_categories),
+            ] {
+                for category in categories {
+                    println!(
+                        "{:<20} {}",
+                        category,
+                        export::account(&settings, kind, category)
+                    );
+                }
+            }
+            return Ok(());
+        }
+        "map" => {
+            let accounts = match positional(args, 1, "expense or income")? {
+                "expense" => &mut settings.expense_accounts,
+                "income" => &mut settings.income_accounts,
+                other => return Err(format!("Expected expense or income, not '{}'", other).into()),
+            };
+            let category = positional(args, 2, "category")?.to_string();
+            match args.get(3) {
+                Some(account) => {
+                    export::check_account(account)?;
+                    accounts.insert(category, account.clone());
+                }
+                None => {
+                    accounts.remove(&category);
+                }
+            }
+        }
+        "asset" => {
+            let account = positional(args, 1, "account")?;
+            export::check_account(account)?;
+            settings.asset_account = account.to_string();
+        }
+        "commodity" => {
+            let code = positional(args, 1, "commodity")?;
+            export::check_commodity(code)?;
+            settings.commodity = code.to_string();
+        }
+        name => {
+            let format = Format::parse(name)?;
+            let journal = export::export(data.get_transactions(), &settings, format);
+            match option_value(args, "--output") {
+                Some(path) => {
+                    fs::write(path, journal)?;
+                    eprintln!("Wrote {} journal to {}", format, path);
+                }
+                None => print!("{}", journal),
+            }
+            return Ok(());
+        }
+    }
+    data.update_export_settings(settings)?;
+    Ok(())
+}
+
+// List key of the transaction kind named on the command line
+fn transaction_kind(args: &[String]) -> Result<&'static str, Box<dyn Error>> {
+    match positional(args, 0, "expense or income")? {
+        "expense" => Ok("expenses"),
+        "income" => Ok("income"),
+        other => Err(format!("Expected expense or income, not '{}'", other).into()),
+    }
+}
+
+// Delete a single expense or income
+fn delete(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let kind = transaction_kind(args)?;
+    let id: u32 = positional(args, 1, "transaction id")?.parse()?;
+    let mut data = Data::new(DATA_FILE)?;
+    data.delete_transaction(id, kind)?;
+    println!("Deleted transaction {} (undo with `fintrackr undo`)", id);
+    Ok(())
+}
+
+// Move the transactions of one category to another
+fn recategorize(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let kind = transaction_kind(args)?;
+    let from = positional(args, 1, "category to move from")?;
+    let to = positional(args, 2, "category to move to")?;
+    let mut data = Data::new(DATA_FILE)?;
+    let count = data.recategorize(kind, from, to)?;
+    println!("Moved {} transactions from {} to {}", count, from, to);
+    Ok(())
+}
+
+// Add the transactions of a CSV file as one change
+fn import(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let kind = transaction_kind(args)?;
+    let path = positional(args, 1, "file")?;
+    let mut data = Data::new(DATA_FILE)?;
+    let transactions = data.get_transactions();
+    let categories = if kind == "income" {
+        &transactions.income_categories
+    } else {
+        &transactions.expense_categories
+    };
+    let transactions = import::parse(&fs::read_to_string(path)?, kind == "expenses", categories)
+        .map_err(|err| format!("{}: {}", path, err))?;
+    let count = data.import_transactions(transactions, kind)?;
+    println!("Imported {} transactions from {}", count, path);
+    Ok(())
+}
+
+// Revert the latest change, or make the latest undone change again
+fn undo(redo: bool) -> Result<(), Box<dyn Error>> {
+    let mut data = Data::new(DATA_FILE)?;
+    let result = if redo { data.redo()? } else { data.undo()? };
+    match result {
+        Some(description) if redo => println!("Redid: {}", description),
+        Some(description) => println!("Undid: {}", description),
+        None if redo => println!("Nothing to redo"),
+        None => println!("Nothing to undo"),
+    }
+    Ok(())
+}
+
+// List the undoable changes, latest first, and the undone ones
+fn history() -> Result<(), Box<dyn Error>> {
+    let data = Data::new(DATA_FILE)?;
+    let history = data.get_history();
+    if history.undo.is_empty() && history.redo.is_empty() {
+        println!("No changes recorded");
+        return Ok(());
+    }
+    println!("Latest changes (undo with `fintrackr undo`):");
+    for command in history.undo.iter().rev() {
+        println!("  {}", command.description);
+    }
+    if !history.redo.is_empty() {
+        println!("Undone (redo with `fintrackr redo`):");
+        for command in history.redo.iter().rev() {
+            println!("  {}", command.description);
+        }
+    }
+    Ok(())
+}
+
+// Turn encryption of the ledger file on or off, or change its passphrase
+fn encrypt(args: &[String]) -> Result<(), Box<dyn Error>> {
+    let action = positional(args, 0, "on, off or rotate")?;
+    if !matches!(action, "on" | "off" | "rotate") {
+        return Err(format!("Unknown encrypt command '{}'\n\n{}", action, USAGE).into());
+    }
+    if !Path::new(DATA_FILE).exists() {
+        return Err(format!("{} not found", DATA_FILE).into());
+    }
+    match (action, storage::is_encrypted(DATA_FILE)) {
+        ("on", true) => {
+            return Err(
+                "The ledger is encrypted already; use `fintrackr encrypt rotate` to change the passphrase"
+                    .into(),
+            )
+        }
+        ("off" | "rotate", false) => return Err("The ledger is not encrypted".into()),
+        _ => {}
+    }
+    // Asks for the current passphrase of an encrypted ledger
+    let mut file = storage::open_json(DATA_FILE)?;
+    let data = file
+        .load()?
+        .ok_or_else(|| format!("{} not found", DATA_FILE))?;
+    let key = match action {
+        "off" => None,
+        _ => Some(Key::new(&crypto::new_passphrase()?)?),
+    };
+    file.set_key(key);
+    file.save(&data)?;
+    if file.is_encrypted() {
+        println!(
+            "{} is encrypted; keep the passphrase safe, it can't be recovered",
+            DATA_FILE
+        );
+    } else {
+        println!("{} is stored in plain text again", DATA_FILE);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn migrate() -> Result<(), Box<dyn Error>> {
+    let path = storage::migrate_to_sqlite(DATA_FILE)?;
+    println!(
+        "Moved the ledger into {}; {} was renamed to {}.migrated",
+        path.display(),
+        DATA_FILE,
+        DATA_FILE
+    );
+    Ok(())
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn migrate() -> Result<(), Box<dyn Error>> {
+    Err("This build has no SQLite support, rebuild with `cargo build --features sqlite`".into())
+}
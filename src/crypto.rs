@@ -0,0 +1,226 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use dialoguer::Password;
+use std::env;
+use std::error::Error;
+use std::io::{self, IsTerminal};
+
+// An encrypted ledger file starts with MAGIC, the Argon2id parameters (memory in KiB,
+// iterations, parallelism as little-endian u32) and the salt. The header is authenticated
+// along with the ciphertext, followed by the XChaCha20-Poly1305 nonce and the ciphertext.
+const MAGIC: &[u8] = b"FINTRACKR-ENCRYPTED-1\n";
+const SALT_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + 12 + SALT_LEN;
+const NONCE_LEN: usize = 24;
+// Largest Argon2id parameters accepted from a file header. The header is only authenticated
+// after the key was derived, so a tampered file could otherwise ask for any amount of memory
+// and time.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+// Wrong passphrases accepted at the prompt before giving up
+const ATTEMPTS: usize = 3;
+
+// Environment variables holding the passphrase for scripts, instead of prompting
+pub const PASSPHRASE_VAR: &str = "FINTRACKR_PASSPHRASE";
+pub const NEW_PASSPHRASE_VAR: &str = "FINTRACKR_NEW_PASSPHRASE";
+
+// Key derived from a passphrase. It is derived once when the ledger is opened and every save
+// encrypts with a fresh nonce.
+pub struct Key {
+    cipher: XChaCha20Poly1305,
+    header: Vec<u8>, // MAGIC, parameters and salt the key was derived with
+}
+
+impl Key {
+    // New key with a random salt and the recommended Argon2id parameters
+    pub fn new(passphrase: &str) -> Result<Self, Box<dyn Error>> {
+        Self::with_params(passphrase, Params::default())
+    }
+
+    // New key with a random salt and the given Argon2id parameters
+    pub fn with_params(passphrase: &str, params: Params) -> Result<Self, Box<dyn Error>> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let mut header = MAGIC.to_vec();
+        for value in [params.m_cost(), params.t_cost(), params.p_cost()] {
+            header.extend(value.to_le_bytes());
+        }
+        header.extend(salt);
+        Self::derive(passphrase, header)
+    }
+
+    // Key of an encrypted file, checked by decrypting it
+    pub fn unlock(passphrase: &str, contents: &[u8]) -> Result<Self, Box<dyn Error>> {
+        if !is_encrypted(contents) || contents.len() < HEADER_LEN + NONCE_LEN {
+            return Err("Not an encrypted ledger".into());
+        }
+        let key = Self::derive(passphrase, contents[..HEADER_LEN].to_vec())?;
+        key.decrypt(contents)?;
+        Ok(key)
+    }
+
+    fn derive(passphrase: &str, header: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        let number = |index: usize| {
+            let start = MAGIC.len() + 4 * index;
+            u32::from_le_bytes([
+                header[start],
+                header[start + 1],
+                header[start + 2],
+                header[start + 3],
+            ])
+        };
+        let (memory, iterations, parallelism) = (number(0), number(1), number(2));
+        if memory > MAX_MEMORY_KIB || iterations > MAX_ITERATIONS || parallelism > MAX_PARALLELISM {
+            return Err(format!(
+                "Key derivation parameters out of range: {} KiB, {} iterations, {} lanes",
+                memory, iterations, parallelism
+            )
+            .into());
+        }
+        let params = Params::new(memory, iterations, parallelism, Some(32))
+            .map_err(|err| format!("Invalid key derivation parameters: {}", err))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(
+                passphrase.as_bytes(),
+                &header[HEADER_LEN - SALT_LEN..],
+                &mut key,
+            )
+            .map_err(|err| format!("Key derivation failed: {}", err))?;
+        Ok(Self {
+            cipher: XChaCha20Poly1305::new(&key.into()),
+            header,
+        })
+    }
+
+    // Encrypted file contents for `plaintext`
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: plaintext,
+            aad: &self.header,
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| "Encrypting the ledger failed")?;
+        let mut contents = self.header.clone();
+        contents.extend(nonce);
+        contents.extend(ciphertext);
+        Ok(contents)
+    }
+
+    // Plaintext of encrypted file contents; fails if anything was changed
+    pub fn decrypt(&self, contents: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        if contents.len() < HEADER_LEN + NONCE_LEN || contents[..HEADER_LEN] != self.header[..] {
+            return Err("The ledger was encrypted again with another passphrase".into());
+        }
+        let (nonce, ciphertext) = contents[HEADER_LEN..].split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: &self.header,
+        };
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| "Wrong passphrase, or the ledger file was damaged")?;
+        Ok(plaintext)
+    }
+}
+
+// Whether file contents are an encrypted ledger
+pub fn is_encrypted(contents: &[u8]) -> bool {
+    contents.starts_with(MAGIC)
+}
+
+// Key of an encrypted ledger, with the passphrase from FINTRACKR_PASSPHRASE or asked for
+pub fn unlock(contents: &[u8]) -> Result<Key, Box<dyn Error>> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_VAR) {
+        return Key::unlock(&passphrase, contents);
+    }
+    if !io::stdin().is_terminal() {
+        return Err(format!(
+            "The ledger is encrypted; set {} to its passphrase",
+            PASSPHRASE_VAR
+        )
+        .into());
+    }
+    let mut attempt = 1;
+    loop {
+        let passphrase = Password::new()
+            .with_prompt("Passphrase of the ledger")
+            .interact()?;
+        match Key::unlock(&passphrase, contents) {
+            Ok(key) => return Ok(key),
+            Err(err) if attempt < ATTEMPTS => {
+                eprintln!("{}", err);
+                attempt += 1;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+// New passphrase from FINTRACKR_NEW_PASSPHRASE, or asked for twice
+pub fn new_passphrase() -> Result<String, Box<dyn Error>> {
+    let passphrase = match env::var(NEW_PASSPHRASE_VAR) {
+        Ok(passphrase) => passphrase,
+        Err(_) => Password::new()
+            .with_prompt("New passphrase")
+            .with_confirmation("Repeat the passphrase", "The passphrases don't match")
+            .interact()?,
+    };
+    if passphrase.is_empty() {
+        return Err("The passphrase must not be empty".into());
+    }
+    Ok(passphrase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cheap parameters so the tests run quickly
+    fn key(passphrase: &str) -> Key {
+        Key::with_params(passphrase, Params::new(8, 1, 1, Some(32)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn encrypted_contents_open_only_with_the_passphrase() {
+        let contents = key("correct horse").encrypt(b"{\"ledger\":1}").unwrap();
+        assert!(is_encrypted(&contents));
+        assert!(!contents.windows(6).any(|w| w == b"ledger"));
+
+        let unlocked = Key::unlock("correct horse", &contents).unwrap();
+        assert_eq!(unlocked.decrypt(&contents).unwrap(), b"{\"ledger\":1}");
+        assert!(Key::unlock("wrong", &contents).is_err());
+
+        // Changing the header, e.g. the parameters, is detected as well
+        let mut tampered = contents.clone();
+        tampered[MAGIC.len()] ^= 1;
+        assert!(Key::unlock("correct horse", &tampered).is_err());
+        let mut tampered = contents;
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(unlocked.decrypt(&tampered).is_err());
+    }
+
+    #[test]
+    fn oversized_parameters_in_the_header_are_refused() {
+        let contents = key("correct horse").encrypt(b"{}").unwrap();
+        for (index, value) in [
+            (0, MAX_MEMORY_KIB + 1),
+            (1, MAX_ITERATIONS + 1),
+            (2, MAX_PARALLELISM + 1),
+        ] {
+            let mut tampered = contents.clone();
+            let start = MAGIC.len() + 4 * index;
+            tampered[start..start + 4].copy_from_slice(&value.to_le_bytes());
+            let err = Key::unlock("correct horse", &tampered).err().unwrap();
+            assert!(err.to_string().contains("out of range"));
+        }
+    }
+}
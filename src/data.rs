@@ -1,16 +1,146 @@
-use chrono::NaiveDate;
+use crate::anomaly;
+use crate::history::{self, Command, Edit, History};
+use crate::storage::{self, Conflict, Query, Storage};
+use chrono::{DateTime, Local, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::Path;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Default location of the ledger file, relative to the working directory
+pub const DATA_FILE: &str = "transactions.json";
+// Transaction fields left out of the change log: the log itself and values derived from the date
+const UNLOGGED_FIELDS: [&str; 4] = ["created_at", "updated_at", "changes", "pending"];
 
 // Struct representing a single transaction
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Transaction {
     pub id: u32,             // Unique identifier for the transaction
     pub amount: f64,         // Amount of money for the transaction
     pub category: String,    // Category of the transaction (e.g., "Food", "Salary")
     pub date: NaiveDate,     // Date of the transaction
     pub description: String, // Description or notes about the transaction
+    #[serde(default)]
+    pub payee: String, // Merchant or person the money went to or came from
+    #[serde(default)]
+    pub tags: Vec<String>, // Free-form labels such as "vacation-2026" or "reimbursable"
+    #[serde(default)]
+    pub flagged: bool, // Amount looked unusual for its category and awaits review
+    #[serde(default)]
+    pub deductible: bool, // Expense is tax-deductible regardless of its category
+    #[serde(default)]
+    pub tax_code: Option<String>, // Tax code overriding the one of the category
+    #[serde(default)]
+    pub splits: Vec<Split>, // Parts of the amount booked to other categories, summing to `amount`
+    #[serde(default)]
+    pub status: Status, // Whether the transaction has shown up on a bank statement
+    #[serde(default)]
+    pub pending: bool, // Scheduled for a future date and not part of the actual balance yet
+    #[serde(default)]
+    pub refund_of: Option<u32>, // Id of the expense this refund pays back, stored as a positive expense
+    #[serde(default)]
+    pub loan_id: Option<u32>, // Id of the loan this payment goes to
+    #[serde(default)]
+    pub shared: Option<Shared>, // Who paid and how the expense is split between people
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>, // When the transaction was entered, None if before this was recorded
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>, // When the transaction was last changed
+    #[serde(default)]
+    pub changes: Vec<Change>, // Log of how and where the transaction was entered and changed
+}
+
+// Struct representing one entry of a transaction's change log
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Change {
+    pub at: DateTime<Utc>,        // When the change was made
+    pub source: Source,           // Where the change was made
+    pub fields: Vec<FieldChange>, // Changed fields, empty for the entry of the new transaction
+}
+
+// Struct representing the old and new value of a changed field, as shown to the user
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,  // Name of the field, e.g. "amount"
+    pub before: String, // Value before the change
+    pub after: String,  // Value after the change
+}
+
+// Where a change to the ledger was made
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Tui,    // Interactive app
+    Cli,    // One-off command
+    Import, // Transactions read from a file
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Source::Tui => "TUI",
+            Source::Cli => "CLI",
+            Source::Import => "Import",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Struct describing how an expense is shared within the household
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Shared {
+    pub paid_by: String,    // Person who paid the whole amount
+    pub shares: Vec<Share>, // Part of the amount each person is responsible for
+}
+
+// Struct representing one person's part of a shared expense
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Share {
+    pub person: String, // Name of the person
+    pub amount: f64,    // Positive part of the expense amount
+}
+
+// Struct representing money paid from one person to another to settle shared expenses
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Transfer {
+    pub id: u32,         // Unique identifier for the transfer
+    pub date: NaiveDate, // Date the money was handed over
+    pub from: String,    // Person paying
+    pub to: String,      // Person receiving
+    pub amount: f64,     // Amount paid, positive
+}
+
+// Reconciliation state of a transaction
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    #[default]
+    Uncleared, // Not seen on a statement yet
+    Cleared,    // Ticked off while reconciling
+    Reconciled, // Part of a finished reconciliation, locked against edits
+}
+
+// Struct representing one line of a split transaction
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Split {
+    pub amount: f64,      // Part of the transaction amount, with the same sign
+    pub category: String, // Category this part is booked to
+    #[serde(default)]
+    pub note: String, // Optional note about this part
+}
+
+impl Transaction {
+    // Amount per category, using the split lines when there are any
+    pub fn category_amounts(&self) -> Vec<(&str, f64)> {
+        if self.splits.is_empty() {
+            vec![(self.category.as_str(), self.amount)]
+        } else {
+            self.splits
+                .iter()
+                .map(|s| (s.category.as_str(), s.amount))
+                .collect()
+        }
+    }
 }
 
 // Struct for holding a collection of transactions and categories
@@ -22,61 +152,259 @@ pub struct Transactions {
     pub income_categories: Vec<String>,  // List of categories for income
 }
 
+// Struct holding the tax configuration
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TaxSettings {
+    pub fiscal_year_start_month: u32, // Month the fiscal year starts in (1-12)
+    pub fiscal_year_start_day: u32,   // Day of the month the fiscal year starts on
+    pub deductible_categories: BTreeMap<String, String>, // Deductible expense categories and their tax code
+    pub taxable_categories: BTreeMap<String, String>, // Taxable income categories and their tax code
+}
+
+impl Default for TaxSettings {
+    fn default() -> Self {
+        Self {
+            fiscal_year_start_month: 1,
+            fiscal_year_start_day: 1,
+            deductible_categories: BTreeMap::new(),
+            taxable_categories: BTreeMap::from([
+                ("Salary".to_string(), String::new()),
+                ("Interest".to_string(), String::new()),
+            ]),
+        }
+    }
+}
+
+// Struct holding the account names used when exporting to plain-text accounting formats
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportSettings {
+    pub asset_account: String, // Account the money comes from and goes to, e.g. "Assets:Checking"
+    pub commodity: String,     // Currency of the amounts, e.g. "USD"
+    pub expense_accounts: BTreeMap<String, String>, // Expense categories booked to another account than Expenses:<Category>
+    pub income_accounts: BTreeMap<String, String>, // Income categories booked to another account than Income:<Category>
+}
+
+impl Default for ExportSettings {
+    fn default() -> Self {
+        Self {
+            asset_account: "Assets:Checking".to_string(),
+            commodity: "USD".to_string(),
+            expense_accounts: BTreeMap::new(),
+            income_accounts: BTreeMap::new(),
+        }
+    }
+}
+
+// Struct holding the envelope budget used in zero-based budgeting mode
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Budget {
+    pub start: Option<NaiveDate>, // First day of the first budgeted month, None while the mode is off
+    pub assignments: Vec<Assignment>, // Money put into (or taken out of) envelopes
+}
+
+// Struct representing money assigned to a category envelope in a month
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Assignment {
+    pub month: NaiveDate, // First day of the month the money is assigned in
+    pub category: String, // Expense category of the envelope
+    pub amount: f64,      // Amount assigned, negative when money is taken out
+}
+
+// Struct representing a recurring bill, e.g. rent or an insurance premium
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Bill {
+    pub id: u32,              // Unique identifier for the bill
+    pub payee: String,        // Who the bill is paid to
+    pub category: String,     // Expense category of the payments
+    pub amount: f64,          // Amount due each time, positive
+    pub first_due: NaiveDate, // Due date of the first bill
+    pub every_months: u32,    // Months between due dates, e.g. 1 for monthly or 12 for yearly
+    #[serde(default)]
+    pub paid: Vec<NaiveDate>, // Due dates that have been paid
+}
+
+// Struct representing a loan that is paid back in fixed monthly payments
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Loan {
+    pub id: u32,          // Unique identifier for the loan
+    pub name: String,     // Name of the loan, also used as the payee of its payments
+    pub kind: String,     // "mortgage", "car" or "personal"
+    pub principal: f64,   // Amount borrowed
+    pub rate: f64,        // Yearly interest rate in percent
+    pub term_months: u32, // Number of monthly payments
+    pub start: NaiveDate, // Date the money was borrowed; payments start the month after
+    pub payment_day: u32, // Day of the month payments are due
+}
+
+// Whether a trade adds shares to or removes them from a holding
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeKind {
+    Buy,
+    Sell,
+}
+
+// Struct representing a purchase or sale of shares in a brokerage account
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Trade {
+    pub id: u32,         // Unique identifier for the trade
+    pub kind: TradeKind, // Buy or sell
+    pub symbol: String,  // Ticker symbol, upper case
+    pub date: NaiveDate, // Date the trade was executed
+    pub quantity: f64,   // Number of shares
+    pub price: f64,      // Price per share
+}
+
+// Struct representing a savings goal
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Goal {
+    pub id: u32,          // Unique identifier for the goal
+    pub name: String,     // Name of the goal, e.g. "Emergency fund"
+    pub target: f64,      // Amount to save
+    pub start: NaiveDate, // Date saving started
+    pub due: NaiveDate,   // Date the target should be reached by
+    pub tag: String,      // Transactions with this tag count as contributions
+    #[serde(default)]
+    pub symbol: Option<String>, // Investment holding whose market value counts towards the goal
+    #[serde(default)]
+    pub starting_amount: f64, // Amount already saved when the goal was created
+}
+
 // Struct representing the overall data structure
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DataStructure {
     pub transactions: Transactions, // Transactions and categories data
+    #[serde(default)]
+    pub tax: TaxSettings, // Fiscal year and tax code configuration
+    #[serde(default)]
+    pub loans: Vec<Loan>, // Loans and debts being paid off
+    #[serde(default)]
+    pub trades: Vec<Trade>, // Brokerage buys and sells
+    #[serde(default)]
+    pub goals: Vec<Goal>, // Savings goals shown on the Home tab
+    #[serde(default)]
+    pub transfers: Vec<Transfer>, // Settlements of shared expenses between people
+    #[serde(default)]
+    pub bills: Vec<Bill>, // Recurring bills with reminders
+    #[serde(default)]
+    pub budget: Budget, // Envelopes of the zero-based budgeting mode
+    #[serde(default)]
+    pub export: ExportSettings, // Account names for ledger, hledger and beancount exports
+    #[serde(default)]
+    pub history: History, // Latest changes, for undo and redo
+}
+
+impl Default for DataStructure {
+    // Empty ledger with the initial categories
+    fn default() -> Self {
+        Self {
+            transactions: Transactions {
+                expenses: Vec::new(), // Initialize empty list of expenses
+                income: Vec::new(),   // Initialize empty list of income
+                // Set initial expense categories
+                expense_categories: vec![
+                    "Food".to_string(),
+                    "Housing".to_string(),
+                    "Transportation".to_string(),
+                    "Entertainment".to_string(),
+                    "Health".to_string(),
+                    "Bills".to_string(),
+                    "Other".to_string(),
+                ],
+                // Set initial income categories
+                income_categories: vec![
+                    "Salary".to_string(),
+                    "Interest".to_string(),
+                    "Gifts".to_string(),
+                    "Dividends".to_string(),
+                    "Other".to_string(),
+                ],
+            },
+            tax: TaxSettings::default(),
+            loans: Vec::new(),
+            trades: Vec::new(),
+            goals: Vec::new(),
+            transfers: Vec::new(),
+            bills: Vec::new(),
+            budget: Budget::default(),
+            export: ExportSettings::default(),
+            history: History::default(),
+        }
+    }
 }
 
 // Struct for managing data with file persistence
 pub struct Data {
-    data: DataStructure, // Data structure holding transactions and categories
-    file_path: String,   // Path to the file where data is stored
+    data: DataStructure,       // Data structure holding transactions and categories
+    storage: Box<dyn Storage>, // Where the data is loaded from and saved to
+    source: Source,            // Where changes are made, recorded in the change logs
 }
 
 impl Data {
     // Constructor to create a new Data instance, loading or creating the file if necessary
     pub fn new(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        // Check if the file exists
-        if !Path::new(file_path).exists() {
-            // Create a new default data structure
-            let data = DataStructure {
-                transactions: Transactions {
-                    expenses: Vec::new(), // Initialize empty list of expenses
-                    income: Vec::new(),   // Initialize empty list of income
-                    // Set initial expense categories
-                    expense_categories: vec![
-                        "Food".to_string(),
-                        "Housing".to_string(),
-                        "Transportation".to_string(),
-                        "Entertainment".to_string(),
-                        "Health".to_string(),
-                        "Bills".to_string(),
-                        "Other".to_string(),
-                    ],
-                    // Set initial income categories
-                    income_categories: vec![
-                        "Salary".to_string(),
-                        "Interest".to_string(),
-                        "Gifts".to_string(),
-                        "Other".to_string(),
-                    ],
-                },
-            };
+        Self::with_storage(storage::open(file_path)?)
+    }
 
-            // Save the new data structure to the file
-            let json = serde_json::to_string(&data)?;
-            fs::write(file_path, json)?;
-        }
+    // Constructor loading the ledger from `storage`, which starts out with the default
+    // categories when nothing has been stored yet
+    pub fn with_storage(mut storage: Box<dyn Storage>) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = match storage.load()? {
+            Some(data) => data,
+            None => {
+                let data = DataStructure::default();
+                storage.save(&data)?;
+                data
+            }
+        };
+        let mut data = Self {
+            data,
+            storage,
+            source: Source::Cli,
+        };
+        // Post scheduled transactions whose date has arrived. This is only stored along with
+        // the next change, so commands that just read the ledger never write it.
+        data.mark_pending(Local::now().date_naive());
+        Ok(data)
+    }
 
-        // Load existing or newly created data
-        let contents = fs::read_to_string(file_path)?;
-        let data = serde_json::from_str(&contents)?;
+    // Method to post pending transactions dated on or before `today` and to hold back
+    // future-dated ones, e.g. while the TUI is kept open past midnight. The ledger is only
+    // written when a transaction changed.
+    pub fn update_pending(
+        &mut self,
+        today: NaiveDate,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let edits = self.mark_pending(today);
+        if !edits.is_empty() {
+            self.persist(&edits)?;
+        }
+        Ok(edits.len())
+    }
 
-        Ok(Self {
-            data,
-            file_path: file_path.to_string(),
-        })
+    // Helper method to set the pending flag of every transaction from its date, in the working
+    // copy only. The flag is derived from the date, so the edits are not recorded in the history.
+    fn mark_pending(&mut self, today: NaiveDate) -> Vec<Edit> {
+        let mut edits = Vec::new();
+        for kind in ["expenses", "income"] {
+            let list = match kind {
+                "income" => &mut self.data.transactions.income,
+                _ => &mut self.data.transactions.expenses,
+            };
+            for (position, transaction) in list.iter_mut().enumerate() {
+                if transaction.pending != (transaction.date > today) {
+                    let before = transaction.clone();
+                    transaction.pending = !transaction.pending;
+                    edits.push(Edit::Transaction {
+                        kind: kind.to_string(),
+                        position,
+                        before: Some(Box::new(before)),
+                        after: Some(Box::new(transaction.clone())),
+                    });
+                }
+            }
+        }
+        edits
     }
 
     // Method to add a transaction to either expenses or income
@@ -85,24 +413,806 @@ impl Data {
         transaction: Transaction,
         transaction_type: &str,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        match transaction_type.to_lowercase().as_str() {
-            "expenses" => self.data.transactions.expenses.push(transaction),
-            "income" => self.data.transactions.income.push(transaction),
-            _ => return Err("Invalid transaction type".into()), // Return an error if the type is invalid
+        let kind = list_key(transaction_type)?;
+        let mut transaction = self.flag_unusual(transaction, kind);
+        self.stamp_new(&mut transaction);
+        let edit = Edit::Transaction {
+            kind: kind.to_string(),
+            position: self.list(kind).len(),
+            before: None,
+            after: Some(Box::new(transaction.clone())),
+        };
+        self.apply(&edit)?;
+        self.commit(format!("Added {}", label(kind, &transaction)), vec![edit])
+    }
+
+    // Method to replace a stored transaction with the same id, unless it is reconciled
+    pub fn update_transaction(
+        &mut self,
+        mut transaction: Transaction,
+        transaction_type: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let kind = list_key(transaction_type)?;
+        let position = self
+            .list(kind)
+            .iter()
+            .position(|t| t.id == transaction.id)
+            .ok_or("Transaction not found")?;
+        let before = self.list(kind)[position].clone();
+        check_unlocked(&before)?;
+        self.stamp_change(&before, &mut transaction);
+        let edit = Edit::Transaction {
+            kind: kind.to_string(),
+            position,
+            before: Some(Box::new(before)),
+            after: Some(Box::new(transaction.clone())),
+        };
+        self.apply(&edit)?;
+        self.commit(format!("Edited {}", label(kind, &transaction)), vec![edit])
+    }
+
+    // Method to delete a transaction. Reconciled expenses and expenses that were partly
+    // refunded are kept, the latter so their refunds still point somewhere.
+    pub fn delete_transaction(
+        &mut self,
+        id: u32,
+        transaction_type: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let kind = list_key(transaction_type)?;
+        if kind == "expenses"
+            && self
+                .data
+                .transactions
+                .expenses
+                .iter()
+                .any(|t| t.refund_of == Some(id))
+        {
+            return Err("Delete the refunds of this expense first".into());
+        }
+        let position = self
+            .list(kind)
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or("Transaction not found")?;
+        let transaction = self.list(kind)[position].clone();
+        check_unlocked(&transaction)?;
+        let description = format!("Deleted {}", label(kind, &transaction));
+        let edit = Edit::Transaction {
+            kind: kind.to_string(),
+            position,
+            before: Some(Box::new(transaction)),
+            after: None,
+        };
+        self.apply(&edit)?;
+        self.commit(description, vec![edit])
+    }
+
+    // Method to move every transaction of a category, and every split line booked to it, to
+    // another known category as one undoable step. Reconciled transactions are left as they
+    // are; returns how many transactions changed.
+    pub fn recategorize(
+        &mut self,
+        transaction_type: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let kind = list_key(transaction_type)?;
+        let categories = match kind {
+            "income" => &self.data.transactions.income_categories,
+            _ => &self.data.transactions.expense_categories,
+        };
+        if !categories.iter().any(|category| category == to) {
+            return Err(format!("Unknown category '{}'", to).into());
+        }
+        let mut edits = Vec::new();
+        for (position, transaction) in self.list(kind).iter().enumerate() {
+            let booked = transaction.category == from
+                || transaction
+                    .splits
+                    .iter()
+                    .any(|split| split.category == from);
+            if !booked || transaction.status == Status::Reconciled {
+                continue;
+            }
+            let mut moved = transaction.clone();
+            if moved.category == from {
+                moved.category = to.to_string();
+            }
+            for split in moved
+                .splits
+                .iter_mut()
+                .filter(|split| split.category == from)
+            {
+                split.category = to.to_string();
+            }
+            self.stamp_change(transaction, &mut moved);
+            edits.push(Edit::Transaction {
+                kind: kind.to_string(),
+                position,
+                before: Some(Box::new(transaction.clone())),
+                after: Some(Box::new(moved)),
+            });
+        }
+        let count = edits.len();
+        if count == 0 {
+            return Ok(0);
+        }
+        for edit in &edits {
+            self.apply(edit)?;
+        }
+        self.commit(
+            format!("Moved {} transactions from {} to {}", count, from, to),
+            edits,
+        )?;
+        Ok(count)
+    }
+
+    // Method to add transactions read from a file as one undoable step, logged as imported;
+    // returns how many were added
+    pub fn import_transactions(
+        &mut self,
+        transactions: Vec<Transaction>,
+        transaction_type: &str,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let kind = list_key(transaction_type)?;
+        let first_id = self.next_transaction_id(kind);
+        let source = std::mem::replace(&mut self.source, Source::Import);
+        let mut edits = Vec::new();
+        for (offset, mut transaction) in transactions.into_iter().enumerate() {
+            transaction.id = first_id + offset as u32;
+            let mut transaction = self.flag_unusual(transaction, kind);
+            self.stamp_new(&mut transaction);
+            edits.push(Edit::Transaction {
+                kind: kind.to_string(),
+                position: self.list(kind).len() + offset,
+                before: None,
+                after: Some(Box::new(transaction)),
+            });
+        }
+        self.source = source;
+        let count = edits.len();
+        if count == 0 {
+            return Ok(0);
+        }
+        for edit in &edits {
+            self.apply(edit)?;
+        }
+        let noun = if kind == "income" {
+            "income"
+        } else {
+            "expenses"
+        };
+        self.commit(format!("Imported {} {}", count, noun), edits)?;
+        Ok(count)
+    }
+
+    // Method to get the id for a new transaction of the given type
+    pub fn next_transaction_id(&self, transaction_type: &str) -> u32 {
+        let kind = list_key(transaction_type).unwrap_or("expenses");
+        self.list(kind).iter().map(|t| t.id).max().unwrap_or(0) + 1
+    }
+
+    // Method to look up the transactions of the given type matching `query`
+    pub fn query(
+        &self,
+        transaction_type: &str,
+        query: &Query,
+    ) -> Result<Vec<Transaction>, Box<dyn std::error::Error>> {
+        self.storage
+            .query(&self.data, list_key(transaction_type)?, query)
+    }
+
+    // Method to lock all cleared transactions up to `date` as reconciled
+    pub fn reconcile(&mut self, date: NaiveDate) -> Result<usize, Box<dyn std::error::Error>> {
+        let mut edits = Vec::new();
+        for kind in ["expenses", "income"] {
+            for (position, transaction) in self.list(kind).iter().enumerate() {
+                if transaction.status == Status::Cleared && transaction.date <= date {
+                    let mut reconciled = transaction.clone();
+                    reconciled.status = Status::Reconciled;
+                    self.stamp_change(transaction, &mut reconciled);
+                    edits.push(Edit::Transaction {
+                        kind: kind.to_string(),
+                        position,
+                        before: Some(Box::new(transaction.clone())),
+                        after: Some(Box::new(reconciled)),
+                    });
+                }
+            }
+        }
+        let count = edits.len();
+        if count == 0 {
+            return Ok(0);
+        }
+        for edit in &edits {
+            self.apply(edit)?;
+        }
+        self.commit(format!("Reconciled {} transactions", count), edits)?;
+        Ok(count)
+    }
+
+    // Method to revert the latest change, returning its description; None when there is
+    // nothing left to undo
+    pub fn undo(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(command) = self.data.history.undo.pop() else {
+            return Ok(None);
+        };
+        let mut edits = Vec::new();
+        for edit in command.inverse() {
+            let edit = self.restamp(edit);
+            self.apply(&edit)?;
+            edits.push(edit);
+        }
+        let description = command.description.clone();
+        self.data.history.redo.push(command);
+        self.persist(&edits)?;
+        Ok(Some(description))
+    }
+
+    // Method to make the latest undone change again, returning its description; None when
+    // there is nothing to redo
+    pub fn redo(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(command) = self.data.history.redo.pop() else {
+            return Ok(None);
+        };
+        let mut edits = Vec::new();
+        for edit in command.edits.clone() {
+            let edit = self.restamp(edit);
+            self.apply(&edit)?;
+            edits.push(edit);
+        }
+        let description = command.description.clone();
+        self.data.history.undo.push(command);
+        self.persist(&edits)?;
+        Ok(Some(description))
+    }
+
+    // Method to get a reference to the changes that can be undone and redone
+    pub fn get_history(&self) -> &History {
+        &self.data.history
+    }
+
+    // Method to get a reference to the loans
+    pub fn get_loans(&self) -> &[Loan] {
+        &self.data.loans
+    }
+
+    // Method to add a loan, assigning it the next free id
+    pub fn add_loan(&mut self, mut loan: Loan) -> Result<u32, Box<dyn std::error::Error>> {
+        loan.id = self.data.loans.iter().map(|l| l.id).max().unwrap_or(0) + 1;
+        let id = loan.id;
+        self.change_settings(format!("Added loan '{}'", loan.name), |data| {
+            data.loans.push(loan);
+            Ok(())
+        })?;
+        Ok(id)
+    }
+
+    // Method to remove a loan; its recorded payments are kept
+    pub fn remove_loan(&mut self, id: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let index = self
+            .data
+            .loans
+            .iter()
+            .position(|l| l.id == id)
+            .ok_or("Loan not found")?;
+        let description = format!("Removed loan '{}'", self.data.loans[index].name);
+        self.change_settings(description, |data| {
+            data.loans.remove(index);
+            Ok(())
+        })
+    }
+
+    // Method to get a reference to the trades
+    pub fn get_trades(&self) -> &[Trade] {
+        &self.data.trades
+    }
+
+    // Method to add a trade, assigning it the next free id
+    pub fn add_trade(&mut self, mut trade: Trade) -> Result<u32, Box<dyn std::error::Error>> {
+        trade.id = self.data.trades.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+        let id = trade.id;
+        let description = format!(
+            "Recorded {} of {} {}",
+            match trade.kind {
+                TradeKind::Buy => "purchase",
+                TradeKind::Sell => "sale",
+            },
+            trade.quantity,
+            trade.symbol
+        );
+        self.change_settings(description, |data| {
+            data.trades.push(trade);
+            Ok(())
+        })?;
+        Ok(id)
+    }
+
+    // Method to get a reference to the savings goals
+    pub fn get_goals(&self) -> &[Goal] {
+        &self.data.goals
+    }
+
+    // Method to add a savings goal, assigning it the next free id
+    pub fn add_goal(&mut self, mut goal: Goal) -> Result<u32, Box<dyn std::error::Error>> {
+        goal.id = self.data.goals.iter().map(|g| g.id).max().unwrap_or(0) + 1;
+        let id = goal.id;
+        self.change_settings(format!("Added goal '{}'", goal.name), |data| {
+            data.goals.push(goal);
+            Ok(())
+        })?;
+        Ok(id)
+    }
+
+    // Method to remove a savings goal
+    pub fn remove_goal(&mut self, id: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let index = self
+            .data
+            .goals
+            .iter()
+            .position(|g| g.id == id)
+            .ok_or("Goal not found")?;
+        let description = format!("Removed goal '{}'", self.data.goals[index].name);
+        self.change_settings(description, |data| {
+            data.goals.remove(index);
+            Ok(())
+        })
+    }
+
+    // Method to get a reference to the settlement transfers
+    pub fn get_transfers(&self) -> &[Transfer] {
+        &self.data.transfers
+    }
+
+    // Method to record a settlement transfer, assigning it the next free id
+    pub fn add_transfer(&mut self, transfer: Transfer) -> Result<u32, Box<dyn std::error::Error>> {
+        Ok(self.add_transfers(vec![transfer])?[0])
+    }
+
+    // Method to add several transfers as one change, e.g. all payments of a settle-up,
+    // returning their ids
+    pub fn add_transfers(
+        &mut self,
+        mut transfers: Vec<Transfer>,
+    ) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        let first = self.data.transfers.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+        for (transfer, id) in transfers.iter_mut().zip(first..) {
+            transfer.id = id;
+        }
+        let ids = transfers.iter().map(|t| t.id).collect();
+        let description = match transfers.as_slice() {
+            [transfer] => format!(
+                "Recorded transfer of {:.2} from {} to {}",
+                transfer.amount, transfer.from, transfer.to
+            ),
+            _ => format!("Recorded {} transfers to settle up", transfers.len()),
+        };
+        self.change_settings(description, |data| {
+            data.transfers.extend(transfers);
+            Ok(())
+        })?;
+        Ok(ids)
+    }
+
+    // Method to get a reference to the recurring bills
+    pub fn get_bills(&self) -> &[Bill] {
+        &self.data.bills
+    }
+
+    // Method to add a recurring bill, assigning it the next free id
+    pub fn add_bill(&mut self, mut bill: Bill) -> Result<u32, Box<dyn std::error::Error>> {
+        bill.id = self.data.bills.iter().map(|b| b.id).max().unwrap_or(0) + 1;
+        let id = bill.id;
+        self.change_settings(format!("Added bill '{}'", bill.payee), |data| {
+            data.bills.push(bill);
+            Ok(())
+        })?;
+        Ok(id)
+    }
+
+    // Method to remove a recurring bill; its recorded payments are kept
+    pub fn remove_bill(&mut self, id: u32) -> Result<(), Box<dyn std::error::Error>> {
+        let index = self
+            .data
+            .bills
+            .iter()
+            .position(|b| b.id == id)
+            .ok_or("Bill not found")?;
+        let description = format!("Removed bill '{}'", self.data.bills[index].payee);
+        self.change_settings(description, |data| {
+            data.bills.remove(index);
+            Ok(())
+        })
+    }
+
+    // Method to mark the bill due on `due` as paid and record its payment as an expense.
+    // Both are undone together.
+    pub fn pay_bill(
+        &mut self,
+        id: u32,
+        due: NaiveDate,
+        payment: Transaction,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let bill = self
+            .data
+            .bills
+            .iter()
+            .find(|b| b.id == id)
+            .ok_or("Bill not found")?;
+        if bill.paid.contains(&due) {
+            return Err(format!("The bill due {} is already paid", due).into());
+        }
+        let description = format!("Paid bill '{}' due {}", bill.payee, due);
+        let before = history::settings(&self.data)?;
+        if let Some(bill) = self.data.bills.iter_mut().find(|b| b.id == id) {
+            bill.paid.push(due);
+        }
+        let (before, after) = changed_settings(before, history::settings(&self.data)?);
+        let mut payment = self.flag_unusual(payment, "expenses");
+        self.stamp_new(&mut payment);
+        let payment = Edit::Transaction {
+            kind: "expenses".to_string(),
+            position: self.data.transactions.expenses.len(),
+            before: None,
+            after: Some(Box::new(payment)),
+        };
+        self.apply(&payment)?;
+        self.commit(description, vec![Edit::Settings { before, after }, payment])
+    }
+
+    // Method to get a reference to the envelope budget
+    pub fn get_budget(&self) -> &Budget {
+        &self.data.budget
+    }
+
+    // Method to replace the envelope budget
+    pub fn update_budget(&mut self, budget: Budget) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_settings("Changed the budget".to_string(), |data| {
+            data.budget = budget;
+            Ok(())
+        })
+    }
+
+    // Method to get a reference to the export account names
+    pub fn get_export_settings(&self) -> &ExportSettings {
+        &self.data.export
+    }
+
+    // Method to replace the export account names
+    pub fn update_export_settings(
+        &mut self,
+        settings: ExportSettings,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_settings("Changed the export accounts".to_string(), |data| {
+            data.export = settings;
+            Ok(())
+        })
+    }
+
+    // Method to get a reference to the tax configuration
+    pub fn get_tax_settings(&self) -> &TaxSettings {
+        &self.data.tax
+    }
+
+    // Method to replace the tax configuration
+    pub fn update_tax_settings(
+        &mut self,
+        settings: TaxSettings,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.change_settings("Changed the tax settings".to_string(), |data| {
+            data.tax = settings;
+            Ok(())
+        })
+    }
+
+    // Helper method to pick the expenses or income list
+    fn list(&self, kind: &str) -> &Vec<Transaction> {
+        match kind {
+            "income" => &self.data.transactions.income,
+            _ => &self.data.transactions.expenses,
+        }
+    }
+
+    // Helper method to pick the expenses or income list for changing it
+    fn list_mut(
+        &mut self,
+        transaction_type: &str,
+    ) -> Result<&mut Vec<Transaction>, Box<dyn std::error::Error>> {
+        match list_key(transaction_type)? {
+            "expenses" => Ok(&mut self.data.transactions.expenses),
+            _ => Ok(&mut self.data.transactions.income),
+        }
+    }
+
+    // Helper method to flag a new transaction whose amount is unusual for its category
+    fn flag_unusual(&self, mut transaction: Transaction, kind: &str) -> Transaction {
+        transaction.pending = transaction.date > Local::now().date_naive();
+        let history: Vec<f64> = self
+            .list(kind)
+            .iter()
+            .filter(|t| t.category == transaction.category && !t.pending && t.refund_of.is_none())
+            .map(|t| t.amount)
+            .collect();
+        transaction.flagged =
+            transaction.refund_of.is_none() && anomaly::is_unusual(transaction.amount, &history);
+        transaction
+    }
+
+    // Method to set where the following changes are made, for the change logs
+    pub fn set_source(&mut self, source: Source) {
+        self.source = source;
+    }
+
+    // Helper method to start the change log of a new transaction
+    fn stamp_new(&self, transaction: &mut Transaction) {
+        let now = Utc::now();
+        transaction.created_at = Some(now);
+        transaction.updated_at = Some(now);
+        transaction.changes = vec![Change {
+            at: now,
+            source: self.source,
+            fields: Vec::new(),
+        }];
+    }
+
+    // Helper method to log the fields a change alters, continuing the change log of `before`
+    fn stamp_change(&self, before: &Transaction, after: &mut Transaction) {
+        let fields = field_changes(before, after);
+        after.created_at = before.created_at;
+        after.updated_at = before.updated_at;
+        after.changes = before.changes.clone();
+        if !fields.is_empty() {
+            let now = Utc::now();
+            after.updated_at = Some(now);
+            after.changes.push(Change {
+                at: now,
+                source: self.source,
+                fields,
+            });
+        }
+    }
+
+    // Helper method to log an undone or redone edit of a stored transaction as a new change,
+    // instead of bringing back the change log it had back then, so the log only ever grows
+    fn restamp(&self, edit: Edit) -> Edit {
+        match edit {
+            Edit::Transaction {
+                kind,
+                position,
+                before,
+                after: Some(mut after),
+            } => {
+                if let Some(current) = self.list(&kind).iter().find(|t| t.id == after.id) {
+                    self.stamp_change(current, &mut after);
+                }
+                Edit::Transaction {
+                    kind,
+                    position,
+                    before,
+                    after: Some(after),
+                }
+            }
+            edit => edit,
+        }
+    }
+
+    // Helper method to change the settings or smaller collections as one undoable step
+    fn change_settings(
+        &mut self,
+        description: String,
+        change: impl FnOnce(&mut DataStructure) -> Result<(), Box<dyn std::error::Error>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let before = history::settings(&self.data)?;
+        change(&mut self.data)?;
+        let (before, after) = changed_settings(before, history::settings(&self.data)?);
+        self.commit(description, vec![Edit::Settings { before, after }])
+    }
+
+    // Helper method to make an edit to the working copy, without storing it
+    fn apply(&mut self, edit: &Edit) -> Result<(), Box<dyn std::error::Error>> {
+        match edit {
+            Edit::Transaction {
+                kind,
+                position,
+                before,
+                after,
+            } => {
+                let Some(id) = before.as_ref().or(after.as_ref()).map(|t| t.id) else {
+                    return Ok(());
+                };
+                let list = self.list_mut(kind)?;
+                let existing = list.iter().position(|t| t.id == id);
+                match (existing, after) {
+                    (Some(index), Some(transaction)) => list[index] = posted(transaction),
+                    (None, Some(transaction)) => {
+                        list.insert((*position).min(list.len()), posted(transaction))
+                    }
+                    (Some(index), None) => {
+                        list.remove(index);
+                    }
+                    (None, None) => {}
+                }
+            }
+            Edit::Settings { after, .. } => {
+                self.data = history::restore_settings(&self.data, after)?;
+            }
         }
-        self.save()?;
         Ok(())
     }
 
+    // Helper method to remember applied edits in the history and store them
+    fn commit(
+        &mut self,
+        description: String,
+        edits: Vec<Edit>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.data.history.record(Command {
+            description,
+            edits: edits.clone(),
+        });
+        self.persist(&edits)
+    }
+
+    // Method to replace the working copy with the stored ledger, e.g. after another program
+    // changed it
+    pub fn reload(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(data) = self.storage.load()? {
+            self.data = data;
+            self.mark_pending(Local::now().date_naive());
+        }
+        Ok(())
+    }
+
+    // Method to load the stored ledger again if another program changed it; returns whether
+    // it did
+    pub fn reload_if_changed(&mut self) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.storage.changed()? {
+            return Ok(false);
+        }
+        self.reload()?;
+        Ok(true)
+    }
+
+    // Helper method to store applied edits along with the history. When another program
+    // changed the stored ledger in the meantime, nothing is written and the working copy is
+    // replaced with the stored ledger instead.
+    fn persist(&mut self, edits: &[Edit]) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self.write(edits);
+        if result.as_ref().is_err_and(|err| err.is::<Conflict>()) {
+            self.reload()?;
+        }
+        result
+    }
+
+    // Helper method to write single transactions one at a time and the whole ledger when
+    // several changed together
+    fn write(&mut self, edits: &[Edit]) -> Result<(), Box<dyn std::error::Error>> {
+        match edits {
+            [Edit::Transaction {
+                kind,
+                before,
+                after,
+                ..
+            }] => {
+                let kind = list_key(kind)?;
+                match after {
+                    Some(transaction) => {
+                        let stored = self
+                            .list(kind)
+                            .iter()
+                            .find(|t| t.id == transaction.id)
+                            .cloned()
+                            .ok_or("Transaction not found")?;
+                        if before.is_some() {
+                            self.storage.update(&self.data, kind, &stored)
+                        } else {
+                            self.storage.add(&self.data, kind, &stored)
+                        }
+                    }
+                    None => match before {
+                        Some(transaction) => self.storage.delete(&self.data, kind, transaction.id),
+                        None => Ok(()),
+                    },
+                }
+            }
+            _ if edits.iter().all(|e| matches!(e, Edit::Settings { .. })) => {
+                self.storage.save_settings(&self.data)
+            }
+            _ => self.storage.save(&self.data),
+        }
+    }
+
     // Method to get a reference to the transactions data
     pub fn get_transactions(&self) -> &Transactions {
         &self.data.transactions
     }
 
-    // Helper method to save the current state of data to the file
-    fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string(&self.data)?;
-        fs::write(&self.file_path, json)?;
-        Ok(())
+    // Method to hand the storage back, e.g. to load it again in tests
+    #[cfg(test)]
+    pub fn into_storage(self) -> Box<dyn Storage> {
+        self.storage
+    }
+}
+
+// Short description of a transaction for the undo history, e.g. "expense 'Lunch' (-12.50)"
+fn label(kind: &str, transaction: &Transaction) -> String {
+    let name = if transaction.description.is_empty() {
+        &transaction.category
+    } else {
+        &transaction.description
+    };
+    let noun = if kind == "income" {
+        "income"
+    } else {
+        "expense"
+    };
+    format!("{} '{}' ({:.2})", noun, name, transaction.amount)
+}
+
+// Refuse changes to a transaction that is part of a finished reconciliation
+fn check_unlocked(transaction: &Transaction) -> Result<(), Box<dyn std::error::Error>> {
+    if transaction.status == Status::Reconciled {
+        return Err("The transaction is reconciled and can't be changed".into());
+    }
+    Ok(())
+}
+
+// Fields that differ between two versions of a transaction, with values as shown to the user
+fn field_changes(before: &Transaction, after: &Transaction) -> Vec<FieldChange> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return Vec::new();
+    };
+    new.iter()
+        .filter(|(name, value)| {
+            !UNLOGGED_FIELDS.contains(&name.as_str()) && old.get(*name) != Some(value)
+        })
+        .map(|(name, value)| FieldChange {
+            field: name.clone(),
+            before: old.get(name).map(show_value).unwrap_or_default(),
+            after: show_value(value),
+        })
+        .collect()
+}
+
+// JSON value as plain text, without the quotes around strings
+fn show_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+// Transaction put back by undo or redo, pending again if its date is still ahead
+fn posted(transaction: &Transaction) -> Transaction {
+    let mut transaction = transaction.clone();
+    transaction.pending = transaction.date > Local::now().date_naive();
+    transaction
+}
+
+// Settings snapshots reduced to the fields that differ, keeping the history small
+fn changed_settings(mut before: Value, mut after: Value) -> (Value, Value) {
+    if let (Value::Object(old), Value::Object(new)) = (&mut before, &mut after) {
+        let unchanged: Vec<String> = old
+            .iter()
+            .filter(|(name, value)| new.get(*name) == Some(value))
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in unchanged {
+            old.remove(&name);
+            new.remove(&name);
+        }
+    }
+    (before, after)
+}
+
+// Normalize a transaction type to the "expenses" or "income" list key
+fn list_key(transaction_type: &str) -> Result<&'static str, Box<dyn std::error::Error>> {
+    match transaction_type.to_lowercase().as_str() {
+        "expenses" => Ok("expenses"),
+        "income" => Ok("income"),
+        _ => Err("Invalid transaction type".into()), // Return an error if the type is invalid
     }
 }
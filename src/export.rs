@@ -0,0 +1,354 @@
+use crate::data::{ExportSettings, Status, Transaction, Transactions};
+use crate::report::round_cents;
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+
+// Width the account names are padded to, so the amounts line up
+const ACCOUNT_WIDTH: usize = 36;
+// Top-level accounts beancount allows
+const ROOTS: [&str; 5] = ["Assets", "Liabilities", "Equity", "Income", "Expenses"];
+
+// Plain-text accounting format to export to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Ledger,
+    Hledger,
+    Beancount,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Self, Box<dyn Error>> {
+        match name {
+            "ledger" => Ok(Format::Ledger),
+            "hledger" => Ok(Format::Hledger),
+            "beancount" => Ok(Format::Beancount),
+            other => Err(format!(
+                "Unknown format '{}', expected ledger, hledger or beancount",
+                other
+            )
+            .into()),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Ledger => write!(f, "ledger"),
+            Format::Hledger => write!(f, "hledger"),
+            Format::Beancount => write!(f, "beancount"),
+        }
+    }
+}
+
+// Account a category of the "expenses" or "income" list is booked to: the configured one, or
+// Expenses:<Category> and Income:<Category>
+pub fn account(settings: &ExportSettings, kind: &str, category: &str) -> String {
+    let (accounts, root) = match kind {
+        "income" => (&settings.income_accounts, "Income"),
+        _ => (&settings.expense_accounts, "Expenses"),
+    };
+    accounts
+        .get(category)
+        .cloned()
+        .unwrap_or_else(|| format!("{}:{}", root, component(category)))
+}
+
+// Account names as beancount accepts them, which ledger and hledger read as well: one of the
+// five roots followed by components that start with an uppercase letter or a digit and hold
+// only letters, digits and dashes, e.g. "Expenses:Food"
+pub fn check_account(name: &str) -> Result<(), Box<dyn Error>> {
+    let mut parts = name.split(':');
+    let valid = parts.next().is_some_and(|root| ROOTS.contains(&root))
+        && parts.clone().count() > 0
+        && parts.all(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_uppercase() || c.is_ascii_digit())
+                && chars.all(|c| c.is_alphanumeric() || c == '-')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid account '{}', expected e.g. Expenses:Food below Assets, Liabilities, Equity, Income or Expenses",
+            name
+        )
+        .into())
+    }
+}
+
+// Commodity codes as beancount accepts them, e.g. "USD"
+pub fn check_commodity(code: &str) -> Result<(), Box<dyn Error>> {
+    let mut chars = code.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || "'._-".contains(c));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid commodity '{}', expected e.g. USD", code).into())
+    }
+}
+
+// Category as an account name component that all three formats accept,
+// e.g. "eating out" -> "EatingOut"
+fn component(category: &str) -> String {
+    let name: String = category
+        .split(|c: char| !c.is_alphanumeric())
+        .filter_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+        })
+        .collect();
+    if name.is_empty() {
+        "Uncategorized".to_string()
+    } else {
+        name
+    }
+}
+
+// Journal of all transactions in `format`. The output only depends on the ledger: transactions
+// are ordered by date, then expenses before income, then id.
+pub fn export(transactions: &Transactions, settings: &ExportSettings, format: Format) -> String {
+    let mut entries: Vec<(&str, &Transaction)> = transactions
+        .expenses
+        .iter()
+        .map(|t| ("expenses", t))
+        .chain(transactions.income.iter().map(|t| ("income", t)))
+        .collect();
+    entries.sort_by_key(|(kind, t)| (t.date, *kind, t.id));
+
+    let mut journal = match format {
+        Format::Ledger | Format::Hledger => "; Exported from FinTrackR\n".to_string(),
+        Format::Beancount => beancount_header(&entries, settings),
+    };
+    for (kind, transaction) in entries {
+        journal.push('\n');
+        journal.push_str(&entry(kind, transaction, settings, format));
+    }
+    journal
+}
+
+// Options and the open directives beancount needs before the first transaction
+fn beancount_header(entries: &[(&str, &Transaction)], settings: &ExportSettings) -> String {
+    let mut header = format!(
+        "; Exported from FinTrackR\noption \"operating_currency\" \"{}\"\n",
+        settings.commodity
+    );
+    if let Some((_, first)) = entries.first() {
+        let accounts: BTreeSet<String> = entries
+            .iter()
+            .flat_map(|(kind, t)| postings(kind, t, settings))
+            .map(|(account, _)| account)
+            .collect();
+        header.push('\n');
+        for account in accounts {
+            header.push_str(&format!(
+                "{} open {} {}\n",
+                first.date, account, settings.commodity
+            ));
+        }
+    }
+    header
+}
+
+// Category accounts, one per split line, followed by the asset account. The amounts are
+// rounded to cents first and the asset account takes their sum, so every entry balances.
+fn postings(
+    kind: &str,
+    transaction: &Transaction,
+    settings: &ExportSettings,
+) -> Vec<(String, f64)> {
+    let mut postings: Vec<(String, f64)> = transaction
+        .category_amounts()
+        .into_iter()
+        .map(|(category, amount)| (account(settings, kind, category), round_cents(-amount)))
+        .collect();
+    let total: f64 = postings.iter().map(|(_, amount)| amount).sum();
+    postings.push((settings.asset_account.clone(), round_cents(-total)));
+    postings
+}
+
+fn entry(kind: &str, t: &Transaction, settings: &ExportSettings, format: Format) -> String {
+    let payee = single_line(&t.payee);
+    let description = single_line(&t.description);
+    let mut entry = match format {
+        Format::Ledger | Format::Hledger => {
+            // Cleared and reconciled transactions are marked cleared, scheduled ones pending
+            let mark = match t.status {
+                _ if t.pending => " !",
+                Status::Cleared | Status::Reconciled => " *",
+                Status::Uncleared => "",
+            };
+            let title = match (payee.is_empty(), description.is_empty()) {
+                (true, true) => t.category.clone(),
+                (true, false) => description.clone(),
+                (false, true) => payee.clone(),
+                // hledger reads "payee | note"; ledger keeps the description as a comment
+                (false, false) if format == Format::Hledger => {
+                    format!("{} | {}", payee, description)
+                }
+                (false, false) => payee.clone(),
+            };
+            let mut lines = format!("{}{} {}\n", t.date, mark, title);
+            if format == Format::Ledger && !payee.is_empty() && !description.is_empty() {
+                lines.push_str(&format!("    ; {}\n", description));
+            }
+            if !t.tags.is_empty() {
+                let tags: Vec<String> = match format {
+                    Format::Ledger => vec![format!(":{}:", t.tags.join(":"))],
+                    _ => t.tags.iter().map(|tag| format!("{}:", tag)).collect(),
+                };
+                lines.push_str(&format!("    ; {}\n", tags.join(", ")));
+            }
+            lines
+        }
+        Format::Beancount => {
+            let flag = if t.pending { "!" } else { "*" };
+            let mut line = format!("{} {}", t.date, flag);
+            if !payee.is_empty() {
+                line.push_str(&format!(" \"{}\"", quoted(&payee)));
+            }
+            line.push_str(&format!(" \"{}\"", quoted(&description)));
+            for tag in &t.tags {
+                line.push_str(&format!(" #{}", beancount_tag(tag)));
+            }
+            line + "\n"
+        }
+    };
+    for (account, amount) in postings(kind, t, settings) {
+        entry.push_str(&format!(
+            "    {:<width$}  {:>10} {}\n",
+            account,
+            money(amount),
+            settings.commodity,
+            width = ACCOUNT_WIDTH
+        ));
+    }
+    entry
+}
+
+// Amount with two decimals, never "-0.00"
+fn money(amount: f64) -> String {
+    let rounded = round_cents(amount);
+    format!("{:.2}", if rounded == 0.0 { 0.0 } else { rounded })
+}
+
+fn single_line(text: &str) -> String {
+    text.replace(['\n', '\r'], " ").trim().to_string()
+}
+
+// Text inside a beancount string literal
+fn quoted(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Tag with the characters beancount allows in tags
+fn beancount_tag(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_/.".contains(c) {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DataStructure, Split};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn golden(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(name)
+    }
+
+    // Compare the export of the sample ledger with the file checked in next to it;
+    // run with UPDATE_GOLDEN=1 to rewrite the files after an intended change
+    fn check(format: Format, name: &str) {
+        let sample = fs::read_to_string(golden("transactions.json")).unwrap();
+        let data: DataStructure = serde_json::from_str(&sample).unwrap();
+        let journal = export(&data.transactions, &data.export, format);
+        if std::env::var("UPDATE_GOLDEN").is_ok() {
+            fs::write(golden(name), &journal).unwrap();
+        }
+        assert_eq!(journal, fs::read_to_string(golden(name)).unwrap());
+        // The same ledger always gives the same file
+        assert_eq!(journal, export(&data.transactions, &data.export, format));
+    }
+
+    #[test]
+    fn ledger_matches_the_golden_file() {
+        check(Format::Ledger, "export.ledger");
+    }
+
+    #[test]
+    fn hledger_matches_the_golden_file() {
+        check(Format::Hledger, "export.journal");
+    }
+
+    #[test]
+    fn beancount_matches_the_golden_file() {
+        check(Format::Beancount, "export.beancount");
+    }
+
+    #[test]
+    fn rounded_splits_still_balance() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses.push(Transaction {
+            id: 1,
+            amount: -99.999,
+            category: "Food".to_string(),
+            splits: ["Food", "Health", "Other"]
+                .iter()
+                .map(|category| Split {
+                    amount: -33.333,
+                    category: category.to_string(),
+                    note: String::new(),
+                })
+                .collect(),
+            ..Default::default()
+        });
+        let postings = postings(
+            "expenses",
+            &transactions.expenses[0],
+            &ExportSettings::default(),
+        );
+        let amounts: Vec<f64> = postings.iter().map(|(_, amount)| *amount).collect();
+        assert_eq!(amounts, vec![33.33, 33.33, 33.33, -99.99]);
+        let journal = export(&transactions, &ExportSettings::default(), Format::Ledger);
+        assert!(journal.contains("Assets:Checking                           -99.99 USD"));
+    }
+
+    #[test]
+    fn accounts_follow_the_beancount_syntax() {
+        for valid in [
+            "Expenses:Food",
+            "Assets:Bank-1:Checking",
+            "Income:2026Bonus",
+        ] {
+            assert!(check_account(valid).is_ok(), "{}", valid);
+        }
+        for invalid in [
+            "Expenses",
+            "expenses:food",
+            "Expenses:food",
+            "Spending:Food",
+            "Expenses::Food",
+            "Expenses:Eating Out",
+            "Expenses:Food_Out",
+        ] {
+            assert!(check_account(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
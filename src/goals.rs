@@ -0,0 +1,223 @@
+use crate::data::{Goal, Transactions};
+use crate::investments::Portfolio;
+use crate::report;
+use chrono::{Datelike, Days, Months, NaiveDate};
+
+// How a goal is doing at a given date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub saved: f64,            // Contributions plus the value of the linked holding
+    pub expected: f64,         // What should be saved by now to reach the target in time
+    pub required_monthly: f64, // Contribution needed each remaining month
+    pub months_left: u32,
+}
+
+impl Progress {
+    // Share of the target reached, between 0 and 1; 0 when it can't be computed, as the
+    // progress gauge can't show NaN
+    pub fn ratio(&self, goal: &Goal) -> f64 {
+        let ratio = if goal.target <= 0.0 {
+            1.0
+        } else {
+            self.saved / goal.target
+        };
+        if ratio.is_nan() {
+            0.0
+        } else {
+            ratio.clamp(0.0, 1.0)
+        }
+    }
+
+    // Amount saved beyond (positive) or short of (negative) the schedule
+    pub fn ahead(&self) -> f64 {
+        self.saved - self.expected
+    }
+
+    // "ahead by 120.00" or "behind by 80.00"
+    pub fn status(&self) -> String {
+        if self.ahead() > -0.005 {
+            format!("ahead by {:.2}", self.ahead().max(0.0))
+        } else {
+            format!("behind by {:.2}", -self.ahead())
+        }
+    }
+}
+
+// Parse a target date given as YYYY-MM-DD, or as YYYY-MM meaning the end of that month
+pub fn parse_due(input: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(&format!("{}-01", input), "%Y-%m-%d")
+                .map(|first| first + Months::new(1) - Days::new(1))
+        })
+        .map_err(|_| format!("Invalid date '{}', use YYYY-MM or YYYY-MM-DD", input))
+}
+
+// Tag used for contributions when none is given, e.g. "goal:emergency-fund"
+pub fn default_tag(name: &str) -> String {
+    let slug = name
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    format!("goal:{}", slug)
+}
+
+// Money put towards the goal: transactions tagged with its tag count as contributions
+// (spending moves money into savings, tagged income takes it back out)
+pub fn contributed(goal: &Goal, transactions: &Transactions) -> f64 {
+    report::sum(
+        transactions
+            .expenses
+            .iter()
+            .chain(transactions.income.iter())
+            .filter(|t| !t.pending && t.tags.contains(&goal.tag))
+            .map(|t| -t.amount),
+    )
+}
+
+// Whole months from `from` until `to`, counting a started month
+fn months_between(from: NaiveDate, to: NaiveDate) -> u32 {
+    if to <= from {
+        return 0;
+    }
+    let months = (to.year() - from.year()) * 12 + to.month() as i32 - from.month() as i32;
+    let months = if to.day() > from.day() {
+        months + 1
+    } else {
+        months
+    };
+    months.max(1) as u32
+}
+
+pub fn progress(
+    goal: &Goal,
+    transactions: &Transactions,
+    portfolio: &Portfolio,
+    today: NaiveDate,
+) -> Progress {
+    let holding = goal
+        .symbol
+        .as_ref()
+        .and_then(|symbol| portfolio.positions.iter().find(|p| &p.symbol == symbol))
+        .map(|p| p.value())
+        .unwrap_or(0.0);
+    let saved = goal.starting_amount + contributed(goal, transactions) + holding;
+
+    // Saving is expected to go linearly from the starting amount to the target
+    let total_days = (goal.due - goal.start).num_days().max(1) as f64;
+    let elapsed = (today - goal.start).num_days().clamp(0, total_days as i64) as f64;
+    let expected =
+        goal.starting_amount + (goal.target - goal.starting_amount) * elapsed / total_days;
+
+    let months_left = months_between(today, goal.due);
+    let missing = (goal.target - saved).max(0.0);
+    Progress {
+        saved,
+        expected,
+        required_monthly: if months_left == 0 {
+            missing
+        } else {
+            missing / months_left as f64
+        },
+        months_left,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DataStructure, Transaction};
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn goal(target: f64) -> Goal {
+        Goal {
+            id: 1,
+            name: "Trip".to_string(),
+            target,
+            start: date("2026-01-01"),
+            due: date("2026-12-31"),
+            tag: "goal:trip".to_string(),
+            symbol: None,
+            starting_amount: 0.0,
+        }
+    }
+
+    fn saved(amounts: &[f64]) -> Transactions {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses = amounts
+            .iter()
+            .enumerate()
+            .map(|(i, amount)| Transaction {
+                id: i as u32 + 1,
+                amount: -amount,
+                category: "Savings".to_string(),
+                date: date("2026-03-01"),
+                tags: vec!["goal:trip".to_string()],
+                ..Default::default()
+            })
+            .collect();
+        transactions
+    }
+
+    #[test]
+    fn ratio_stays_between_zero_and_one() {
+        let progress = |saved| Progress {
+            saved,
+            expected: 0.0,
+            required_monthly: 0.0,
+            months_left: 1,
+        };
+        assert_eq!(progress(250.0).ratio(&goal(1000.0)), 0.25);
+        assert_eq!(progress(1500.0).ratio(&goal(1000.0)), 1.0);
+        assert_eq!(progress(-10.0).ratio(&goal(1000.0)), 0.0);
+        assert_eq!(progress(10.0).ratio(&goal(0.0)), 1.0);
+        assert_eq!(progress(10.0).ratio(&goal(f64::NAN)), 0.0);
+        assert_eq!(progress(f64::NAN).ratio(&goal(1000.0)), 0.0);
+    }
+
+    #[test]
+    fn behind_schedule_needs_the_rest_spread_over_the_remaining_months() {
+        let today = date("2026-07-02");
+        let progress = progress(
+            &goal(1200.0),
+            &saved(&[200.0, 100.0]),
+            &Portfolio::default(),
+            today,
+        );
+        assert_eq!(progress.saved, 300.0);
+        assert_eq!(progress.expected, 600.0);
+        assert_eq!(progress.months_left, 6);
+        assert_eq!(progress.required_monthly, 150.0);
+        assert_eq!(progress.status(), "behind by 300.00");
+    }
+
+    #[test]
+    fn ahead_of_schedule_and_past_the_due_date() {
+        let progress_on = |today| {
+            progress(
+                &goal(1200.0),
+                &saved(&[700.0]),
+                &Portfolio::default(),
+                date(today),
+            )
+        };
+        assert_eq!(progress_on("2026-07-02").status(), "ahead by 100.00");
+        let late = progress_on("2027-02-01");
+        assert_eq!(late.months_left, 0);
+        assert_eq!(late.required_monthly, 500.0);
+        assert_eq!(late.status(), "behind by 500.00");
+    }
+
+    #[test]
+    fn due_dates_accept_a_month() {
+        assert_eq!(parse_due("2027-06").unwrap(), date("2027-06-30"));
+        assert_eq!(parse_due("2028-02").unwrap(), date("2028-02-29"));
+        assert_eq!(parse_due("2027-06-15").unwrap(), date("2027-06-15"));
+        assert!(parse_due("June").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use crate::data::{DataStructure, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Number of changes that can be undone, also after a restart
+pub const HISTORY_LIMIT: usize = 50;
+
+// Fields of the ledger that settings snapshots leave out
+const NOT_SETTINGS: [&str; 2] = ["transactions", "history"];
+
+// One reversible change to the ledger
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Edit {
+    // A transaction was added (no `before`), changed, or deleted (no `after`)
+    Transaction {
+        kind: String,    // "expenses" or "income"
+        position: usize, // Index in its list, so a deleted transaction goes back to its place
+        before: Option<Box<Transaction>>,
+        after: Option<Box<Transaction>>,
+    },
+    // Loans, goals, bills, the budget or another collection besides the transactions changed
+    Settings {
+        before: Value,
+        after: Value,
+    },
+}
+
+impl Edit {
+    pub fn inverse(&self) -> Edit {
+        match self {
+            Edit::Transaction {
+                kind,
+                position,
+                before,
+                after,
+            } => Edit::Transaction {
+                kind: kind.clone(),
+                position: *position,
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Edit::Settings { before, after } => Edit::Settings {
+                before: after.clone(),
+                after: before.clone(),
+            },
+        }
+    }
+}
+
+// Edits made by one user action, undone and redone together
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Command {
+    pub description: String, // e.g. "Added expense 'Lunch' (12.50)"
+    pub edits: Vec<Edit>,
+}
+
+impl Command {
+    // Edits that revert this command, in reverse order
+    pub fn inverse(&self) -> Vec<Edit> {
+        self.edits.iter().rev().map(Edit::inverse).collect()
+    }
+}
+
+// Changes that can be undone and changes that were undone and can be redone
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct History {
+    pub undo: Vec<Command>,
+    pub redo: Vec<Command>,
+}
+
+impl History {
+    // Remember a new change; anything undone before can no longer be redone
+    pub fn record(&mut self, command: Command) {
+        self.undo.push(command);
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.drain(..self.undo.len() - HISTORY_LIMIT);
+        }
+        self.redo.clear();
+    }
+}
+
+// Snapshot of everything besides the transactions and the history itself
+pub fn settings(data: &DataStructure) -> Result<Value, serde_json::Error> {
+    let mut value = serde_json::to_value(data)?;
+    if let Value::Object(fields) = &mut value {
+        for name in NOT_SETTINGS {
+            fields.remove(name);
+        }
+    }
+    Ok(value)
+}
+
+// `data` with the settings of a snapshot put back
+pub fn restore_settings(
+    data: &DataStructure,
+    settings: &Value,
+) -> Result<DataStructure, serde_json::Error> {
+    let mut value = serde_json::to_value(data)?;
+    if let (Value::Object(fields), Value::Object(snapshot)) = (&mut value, settings) {
+        for (name, field) in snapshot {
+            fields.insert(name.clone(), field.clone());
+        }
+    }
+    serde_json::from_value(value)
+}
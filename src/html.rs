@@ -0,0 +1,273 @@
+use crate::data::Transactions;
+use crate::report::{self, Comparison, Grouping, Period};
+use chrono::NaiveDate;
+use std::fmt::Write;
+
+// Number of months shown in the trend chart
+const TREND_MONTHS: u32 = 12;
+const INCOME_COLOR: &str = "#2e9e4f";
+const EXPENSE_COLOR: &str = "#d64545";
+
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 960px; margin: 2em auto; color: #222; }
+h1 { color: #0a7f8f; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { padding: 0.3em 0.8em; text-align: right; border-bottom: 1px solid #ddd; }
+th:first-child, td:first-child { text-align: left; }
+th { background: #eee; }
+.increase { color: #d64545; font-weight: bold; }
+.decrease { color: #2e9e4f; }
+.total td { font-weight: bold; }
+svg text { font-size: 12px; fill: #222; }
+";
+
+// Escape text for use inside HTML elements and attributes
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Build a self-contained HTML report for the period containing `anchor`
+pub fn render(transactions: &Transactions, period: Period, anchor: NaiveDate) -> String {
+    let start = period.start_of(anchor);
+    let end = period.shift(start, 1);
+    let title = format!("FinTrackR report {}", period.label(start));
+
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape(&title),
+        STYLE,
+        escape(&title)
+    );
+
+    summary(&mut html, &report::totals(transactions, start, end));
+    categories(
+        &mut html,
+        &report::category_spending(transactions, start, end)
+            .into_iter()
+            .collect::<Vec<_>>(),
+    );
+    comparison(
+        &mut html,
+        period,
+        &report::compare(transactions, Grouping::Category, period, start),
+    );
+    trend(
+        &mut html,
+        &report::monthly_totals(transactions, end.pred_opt().unwrap_or(end), TREND_MONTHS),
+    );
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn summary(html: &mut String, totals: &report::Totals) {
+    let _ = write!(
+        html,
+        "<h2>Summary</h2>\n<table>\n<tr><th>Income</th><th>Expenses</th><th>Net</th></tr>\n\
+         <tr><td>{:.2}</td><td>{:.2}</td><td class=\"{}\">{:.2}</td></tr>\n</table>\n",
+        totals.income,
+        totals.expenses,
+        if totals.net() < 0.0 {
+            "increase"
+        } else {
+            "decrease"
+        },
+        totals.net()
+    );
+}
+
+// Table and horizontal bar chart of spending per category
+fn categories(html: &mut String, spending: &[(String, f64)]) {
+    html.push_str("<h2>Spending by category</h2>\n");
+    if spending.is_empty() {
+        html.push_str("<p>No expenses in this period.</p>\n");
+        return;
+    }
+    let total: f64 = spending.iter().map(|(_, amount)| amount).sum();
+    let max = spending
+        .iter()
+        .map(|(_, amount)| *amount)
+        .fold(0.0, f64::max);
+
+    html.push_str("<table>\n<tr><th>Category</th><th>Amount</th><th>Share</th></tr>\n");
+    for (category, amount) in spending {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}</td><td>{:.2}</td><td>{:.1}%</td></tr>",
+            escape(category),
+            amount,
+            amount / total * 100.0
+        );
+    }
+    html.push_str("</table>\n");
+
+    let (label_width, bar_width, row_height) = (140.0, 420.0, 24.0);
+    let height = spending.len() as f64 * row_height;
+    let _ = writeln!(
+        html,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\">",
+        label_width + bar_width + 80.0,
+        height
+    );
+    for (i, (category, amount)) in spending.iter().enumerate() {
+        let y = i as f64 * row_height;
+        let width = (amount / max * bar_width).max(0.0);
+        let _ = writeln!(
+            html,
+            "<text x=\"0\" y=\"{:.1}\">{}</text>\
+             <rect x=\"{}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>\
+             <text x=\"{:.1}\" y=\"{:.1}\">{:.2}</text>",
+            y + 16.0,
+            escape(category),
+            label_width,
+            y + 4.0,
+            width,
+            row_height - 8.0,
+            EXPENSE_COLOR,
+            label_width + width + 6.0,
+            y + 16.0,
+            amount
+        );
+    }
+    html.push_str("</svg>\n");
+}
+
+// Category spending next to the previous period and the same period last year
+fn comparison(html: &mut String, period: Period, rows: &[Comparison]) {
+    let _ = writeln!(
+        html,
+        "<h2>Compared to the previous {} and last year</h2>\n<table>\n\
+         <tr><th>Category</th><th>Current</th><th>Previous</th><th>Change</th>\
+         <th>Last year</th><th>Change</th></tr>",
+        period
+    );
+    let class = |delta: f64| {
+        if delta > 0.0 {
+            "increase"
+        } else if delta < 0.0 {
+            "decrease"
+        } else {
+            ""
+        }
+    };
+    let total = Comparison::total(Grouping::Category, rows);
+    for row in rows.iter().chain(total.iter()) {
+        let _ = writeln!(
+            html,
+            "<tr{}><td>{}</td><td>{:.2}</td><td>{:.2}</td><td class=\"{}\">{}</td>\
+             <td>{:.2}</td><td class=\"{}\">{}</td></tr>",
+            if total.as_ref().is_some_and(|total| std::ptr::eq(row, total)) {
+                " class=\"total\""
+            } else {
+                ""
+            },
+            escape(&row.name),
+            row.current,
+            row.previous,
+            class(row.previous_delta()),
+            report::format_delta(row.previous_delta(), row.previous_pct()),
+            row.last_year,
+            class(row.last_year_delta()),
+            report::format_delta(row.last_year_delta(), row.last_year_pct())
+        );
+    }
+    html.push_str("</table>\n");
+}
+
+// Grouped bar chart of income and spending per month
+fn trend(html: &mut String, months: &[(NaiveDate, report::Totals)]) {
+    let _ = writeln!(html, "<h2>Monthly trend</h2>");
+    let max = months
+        .iter()
+        .map(|(_, t)| t.income.max(t.expenses))
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let (group_width, bar_width, chart_height, axis) = (60.0, 22.0, 200.0, 20.0);
+    let _ = writeln!(
+        html,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\">",
+        months.len() as f64 * group_width,
+        chart_height + axis
+    );
+    for (i, (month, totals)) in months.iter().enumerate() {
+        let x = i as f64 * group_width + 6.0;
+        for (offset, value, color) in [
+            (0.0, totals.income, INCOME_COLOR),
+            (bar_width, totals.expenses, EXPENSE_COLOR),
+        ] {
+            let height = value / max * chart_height;
+            let _ = writeln!(
+                html,
+                "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{}\" height=\"{:.1}\" fill=\"{}\">\
+                 <title>{} {:.2}</title></rect>",
+                x + offset,
+                chart_height - height,
+                bar_width - 2.0,
+                height,
+                color,
+                month.format("%Y-%m"),
+                value
+            );
+        }
+        let _ = writeln!(
+            html,
+            "<text x=\"{:.1}\" y=\"{}\">{}</text>",
+            x,
+            chart_height + 15.0,
+            month.format("%b %y")
+        );
+    }
+    html.push_str("</svg>\n");
+    let _ = writeln!(
+        html,
+        "<p><span style=\"color:{}\">&#9632;</span> Income \
+         <span style=\"color:{}\">&#9632;</span> Expenses</p>",
+        INCOME_COLOR, EXPENSE_COLOR
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DataStructure, Transaction};
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            escape("<b>Tom & \"Jerry\"</b>"),
+            "&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;"
+        );
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses.push(Transaction {
+            id: 1,
+            amount: -12.0,
+            category: "<script>".to_string(),
+            date: date("2026-10-05"),
+            ..Default::default()
+        });
+        let html = render(&transactions, Period::Month, date("2026-10-19"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<td>&lt;script&gt;</td><td>12.00</td><td>100.0%</td>"));
+    }
+
+    #[test]
+    fn empty_period_still_gives_a_complete_page() {
+        let transactions = DataStructure::default().transactions;
+        let html = render(&transactions, Period::Month, date("2026-10-19"));
+        assert!(html.contains("<title>FinTrackR report 2026-10</title>"));
+        assert!(html.contains("<p>No expenses in this period.</p>"));
+        assert!(html.contains("<tr><td>0.00</td><td>0.00</td>"));
+        assert!(!html.contains("NaN"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
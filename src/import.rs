@@ -0,0 +1,103 @@
+use crate::data::Transaction;
+use chrono::NaiveDate;
+
+// Read transactions from CSV lines "date,amount,category,description[,payee]", e.g. a bank
+// export reduced to these columns. A header line and blank lines are skipped. Amounts may be
+// written with either sign and get the sign of the list they are imported into; categories
+// must be among `categories`. Ids are left at 0 for the ledger to assign.
+pub fn parse(
+    text: &str,
+    negative: bool,
+    categories: &[String],
+) -> Result<Vec<Transaction>, String> {
+    let mut transactions = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if line.trim().is_empty() || (number == 0 && fields[0].eq_ignore_ascii_case("date")) {
+            continue;
+        }
+        let (date, amount, category, description, payee) = match fields[..] {
+            [date, amount, category, description] => (date, amount, category, description, ""),
+            [date, amount, category, description, payee] => {
+                (date, amount, category, description, payee)
+            }
+            _ => {
+                return Err(format!(
+                    "Line {}: expected date,amount,category,description[,payee]",
+                    number + 1
+                ))
+            }
+        };
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("Line {}: invalid date '{}'", number + 1, date))?;
+        let amount = amount
+            .parse::<f64>()
+            .ok()
+            .filter(|amount| *amount != 0.0 && amount.is_finite())
+            .ok_or_else(|| format!("Line {}: invalid amount '{}'", number + 1, amount))?
+            .abs();
+        if !categories.iter().any(|known| known == category) {
+            return Err(format!(
+                "Line {}: unknown category '{}'",
+                number + 1,
+                category
+            ));
+        }
+        transactions.push(Transaction {
+            amount: if negative { -amount } else { amount },
+            category: category.to_string(),
+            date,
+            description: description.to_string(),
+            payee: payee.to_string(),
+            ..Default::default()
+        });
+    }
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories() -> Vec<String> {
+        vec!["Food".to_string(), "Bills".to_string()]
+    }
+
+    #[test]
+    fn lines_become_transactions_with_the_sign_of_the_list() {
+        let text = "Date,Amount,Category,Description,Payee\n\
+                    2026-03-01,-12.50,Food,Lunch,Cafe\n\
+                    \n\
+                    2026-03-02,80,Bills,Power\n";
+        let expenses = parse(text, true, &categories()).unwrap();
+        assert_eq!(expenses.len(), 2);
+        assert_eq!(expenses[0].amount, -12.5);
+        assert_eq!(expenses[0].payee, "Cafe");
+        assert_eq!(expenses[1].amount, -80.0);
+        assert_eq!(
+            expenses[1].date,
+            NaiveDate::from_ymd_opt(2026, 3, 2).unwrap()
+        );
+        assert_eq!(parse(text, false, &categories()).unwrap()[0].amount, 12.5);
+    }
+
+    #[test]
+    fn invalid_lines_are_refused_with_their_number() {
+        for (line, message) in [
+            ("2026-03-01,12,Food", "expected date"),
+            ("2026-13-01,12,Food,Lunch", "invalid date"),
+            ("2026-03-01,NaN,Food,Lunch", "invalid amount"),
+            ("2026-03-01,0,Food,Lunch", "invalid amount"),
+            ("2026-03-01,12,Travel,Lunch", "unknown category"),
+        ] {
+            let err = parse(
+                &format!("2026-03-01,5,Food,Ok\n{}", line),
+                true,
+                &categories(),
+            )
+            .unwrap_err();
+            assert!(err.starts_with("Line 2:"), "{}", err);
+            assert!(err.contains(message), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,323 @@
+use crate::data::{Loan, Trade, TradeKind, Transactions};
+use crate::loans;
+use crate::report;
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, VecDeque};
+use std::fs;
+use std::path::Path;
+
+// Default location of the price history, relative to the working directory
+pub const PRICES_FILE: &str = "prices.csv";
+
+// Closing prices per symbol and date
+pub type Prices = BTreeMap<String, BTreeMap<NaiveDate, f64>>;
+
+// Read a price history CSV with lines "date,symbol,price". A header line and blank lines are
+// skipped; a missing file means no prices.
+pub fn load_prices(path: &str) -> Result<Prices, Box<dyn std::error::Error>> {
+    let mut prices = Prices::new();
+    if !Path::new(path).exists() {
+        return Ok(prices);
+    }
+    for (number, line) in fs::read_to_string(path)?.lines().enumerate() {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if line.trim().is_empty() || (number == 0 && fields[0].eq_ignore_ascii_case("date")) {
+            continue;
+        }
+        let [date, symbol, price] = fields[..] else {
+            return Err(format!("{}:{}: expected date,symbol,price", path, number + 1).into());
+        };
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| format!("{}:{}: invalid date '{}'", path, number + 1, date))?;
+        let price = price
+            .parse::<f64>()
+            .map_err(|_| format!("{}:{}: invalid price '{}'", path, number + 1, price))?;
+        prices
+            .entry(symbol.to_uppercase())
+            .or_default()
+            .insert(date, price);
+    }
+    Ok(prices)
+}
+
+// Latest known price of `symbol` on or before `date`
+pub fn price_on(prices: &Prices, symbol: &str, date: NaiveDate) -> Option<(NaiveDate, f64)> {
+    prices
+        .get(symbol)
+        .and_then(|history| history.range(..=date).next_back())
+        .map(|(date, price)| (*date, *price))
+}
+
+// Shares bought together, still (partly) held
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lot {
+    pub date: NaiveDate,
+    pub quantity: f64,
+    pub price: f64, // Price paid per share
+}
+
+// Gain or loss of one sale, matched against the oldest lots first
+#[derive(Debug, Clone, PartialEq)]
+pub struct Realized {
+    pub date: NaiveDate,
+    pub symbol: String,
+    pub quantity: f64,
+    pub proceeds: f64,
+    pub cost: f64, // Cost basis of the lots sold
+}
+
+impl Realized {
+    pub fn gain(&self) -> f64 {
+        self.proceeds - self.cost
+    }
+}
+
+// Shares of one symbol still held
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub symbol: String,
+    pub lots: Vec<Lot>,
+    pub price: Option<(NaiveDate, f64)>, // Latest market price and its date
+}
+
+impl Position {
+    pub fn quantity(&self) -> f64 {
+        report::sum(self.lots.iter().map(|l| l.quantity))
+    }
+
+    pub fn cost(&self) -> f64 {
+        report::sum(self.lots.iter().map(|l| l.quantity * l.price))
+    }
+
+    // Market value, falling back to the cost when there is no price yet
+    pub fn value(&self) -> f64 {
+        match self.price {
+            Some((_, price)) => self.quantity() * price,
+            None => self.cost(),
+        }
+    }
+
+    pub fn unrealized(&self) -> f64 {
+        self.value() - self.cost()
+    }
+}
+
+// Open positions and realized gains of all trades, using FIFO lot matching
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Portfolio {
+    pub positions: Vec<Position>,
+    pub realized: Vec<Realized>,
+}
+
+impl Portfolio {
+    pub fn value(&self) -> f64 {
+        report::sum(self.positions.iter().map(|p| p.value()))
+    }
+
+    pub fn unrealized(&self) -> f64 {
+        report::sum(self.positions.iter().map(|p| p.unrealized()))
+    }
+
+    pub fn realized(&self) -> f64 {
+        report::sum(self.realized.iter().map(|r| r.gain()))
+    }
+}
+
+// Match sales against the oldest lots and value what is left at the prices known on `date`
+pub fn portfolio(trades: &[Trade], prices: &Prices, date: NaiveDate) -> Result<Portfolio, String> {
+    let mut trades: Vec<&Trade> = trades.iter().filter(|t| t.date <= date).collect();
+    trades.sort_by_key(|t| (t.date, t.id));
+
+    let mut lots: BTreeMap<&str, VecDeque<Lot>> = BTreeMap::new();
+    let mut realized = Vec::new();
+    for trade in trades {
+        let held = lots.entry(trade.symbol.as_str()).or_default();
+        match trade.kind {
+            TradeKind::Buy => held.push_back(Lot {
+                date: trade.date,
+                quantity: trade.quantity,
+                price: trade.price,
+            }),
+            TradeKind::Sell => {
+                let mut left = trade.quantity;
+                let mut cost = 0.0;
+                while left > 1e-9 {
+                    let lot = held.front_mut().ok_or_else(|| {
+                        format!(
+                            "Sale of {} {} on {} exceeds the shares held",
+                            trade.quantity, trade.symbol, trade.date
+                        )
+                    })?;
+                    let sold = left.min(lot.quantity);
+                    cost += sold * lot.price;
+                    lot.quantity -= sold;
+                    left -= sold;
+                    if lot.quantity <= 1e-9 {
+                        held.pop_front();
+                    }
+                }
+                realized.push(Realized {
+                    date: trade.date,
+                    symbol: trade.symbol.clone(),
+                    quantity: trade.quantity,
+                    proceeds: trade.quantity * trade.price,
+                    cost,
+                });
+            }
+        }
+    }
+
+    Ok(Portfolio {
+        positions: lots
+            .into_iter()
+            .filter(|(_, lots)| !lots.is_empty())
+            .map(|(symbol, lots)| Position {
+                symbol: symbol.to_string(),
+                lots: lots.into_iter().collect(),
+                price: price_on(prices, symbol, date),
+            })
+            .collect(),
+        realized,
+    })
+}
+
+// Cash, investments and debts adding up to the net worth
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NetWorth {
+    pub cash: f64,        // Posted transactions, less money moved into trades
+    pub investments: f64, // Market value of open positions
+    pub debts: f64,       // Remaining loan balances, as a positive number
+}
+
+impl NetWorth {
+    pub fn total(&self) -> f64 {
+        self.cash + self.investments - self.debts
+    }
+}
+
+// Net worth on `date`, with `portfolio` valued on the same date. Trades dated later don't
+// move any cash yet.
+pub fn net_worth(
+    transactions: &Transactions,
+    trades: &[Trade],
+    loans: &[Loan],
+    portfolio: &Portfolio,
+    date: NaiveDate,
+) -> NetWorth {
+    // Buying moves cash into the portfolio and selling moves it back
+    let trade_cash = report::sum(
+        trades
+            .iter()
+            .filter(|t| t.date <= date)
+            .map(|t| match t.kind {
+                TradeKind::Buy => -t.quantity * t.price,
+                TradeKind::Sell => t.quantity * t.price,
+            }),
+    );
+    NetWorth {
+        cash: report::totals(transactions, NaiveDate::MIN, NaiveDate::MAX).net() + trade_cash,
+        investments: portfolio.value(),
+        debts: report::sum(loans.iter().map(|l| loans::balance(l, transactions))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{DataStructure, Transaction};
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn trade(id: u32, kind: TradeKind, day: &str, quantity: f64, price: f64) -> Trade {
+        Trade {
+            id,
+            kind,
+            symbol: "ACME".to_string(),
+            date: date(day),
+            quantity,
+            price,
+        }
+    }
+
+    fn prices() -> Prices {
+        Prices::from([(
+            "ACME".to_string(),
+            BTreeMap::from([(date("2026-01-01"), 10.0), (date("2026-06-01"), 20.0)]),
+        )])
+    }
+
+    #[test]
+    fn sales_use_the_oldest_lots_first() {
+        let trades = [
+            trade(1, TradeKind::Buy, "2026-01-10", 10.0, 10.0),
+            trade(2, TradeKind::Buy, "2026-02-10", 10.0, 15.0),
+            trade(3, TradeKind::Sell, "2026-03-10", 15.0, 18.0),
+        ];
+        let portfolio = portfolio(&trades, &prices(), date("2026-06-30")).unwrap();
+
+        // 10 shares at 10 and 5 at 15 were sold for 18 each
+        assert_eq!(portfolio.realized.len(), 1);
+        assert_eq!(portfolio.realized[0].proceeds, 270.0);
+        assert_eq!(portfolio.realized[0].cost, 175.0);
+        assert_eq!(portfolio.realized(), 95.0);
+
+        // 5 shares bought at 15 are left, worth 20 each
+        let position = &portfolio.positions[0];
+        assert_eq!(
+            position.lots,
+            vec![Lot {
+                date: date("2026-02-10"),
+                quantity: 5.0,
+                price: 15.0,
+            }]
+        );
+        assert_eq!(position.price, Some((date("2026-06-01"), 20.0)));
+        assert_eq!(portfolio.value(), 100.0);
+        assert_eq!(portfolio.unrealized(), 25.0);
+    }
+
+    #[test]
+    fn portfolio_on_a_date_ignores_later_trades_and_prices() {
+        let trades = [
+            trade(1, TradeKind::Buy, "2026-01-10", 10.0, 10.0),
+            trade(2, TradeKind::Sell, "2026-07-01", 10.0, 25.0),
+        ];
+        let portfolio = portfolio(&trades, &prices(), date("2026-03-01")).unwrap();
+        assert!(portfolio.realized.is_empty());
+        assert_eq!(portfolio.positions[0].quantity(), 10.0);
+        assert_eq!(portfolio.value(), 100.0);
+    }
+
+    #[test]
+    fn selling_more_than_held_fails() {
+        let trades = [
+            trade(1, TradeKind::Buy, "2026-01-10", 10.0, 10.0),
+            trade(2, TradeKind::Sell, "2026-02-10", 12.0, 10.0),
+        ];
+        assert!(portfolio(&trades, &prices(), date("2026-06-30")).is_err());
+    }
+
+    #[test]
+    fn net_worth_leaves_out_future_trades() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.income.push(Transaction {
+            id: 1,
+            amount: 1000.0,
+            category: "Salary".to_string(),
+            date: date("2026-01-01"),
+            ..Default::default()
+        });
+        let trades = [
+            trade(1, TradeKind::Buy, "2026-01-10", 10.0, 10.0),
+            trade(2, TradeKind::Buy, "2026-09-10", 10.0, 30.0),
+        ];
+        let today = date("2026-06-30");
+        let portfolio = portfolio(&trades, &prices(), today).unwrap();
+        let worth = net_worth(&transactions, &trades, &[], &portfolio, today);
+        assert_eq!(worth.cash, 900.0);
+        assert_eq!(worth.investments, 200.0);
+        assert_eq!(worth.total(), 1100.0);
+    }
+}
@@ -0,0 +1,423 @@
+use crate::data::{Loan, Split, Transaction, Transactions};
+use crate::report::{self, round_cents};
+use chrono::{Datelike, Months, NaiveDate};
+use std::error::Error;
+use std::fmt;
+
+// Notes of the split lines a loan payment is divided into
+pub const INTEREST_NOTE: &str = "interest";
+pub const PRINCIPAL_NOTE: &str = "principal";
+
+// Loan kinds accepted on the command line
+pub const KINDS: [&str; 3] = ["mortgage", "car", "personal"];
+
+// Longest payoff simulated by the planner and longest term accepted, in months
+const MAX_MONTHS: u32 = 1200;
+// Highest yearly interest rate accepted, in percent
+const MAX_RATE: f64 = 100.0;
+
+// One payment of an amortization schedule
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Installment {
+    pub number: u32, // 1 for the first payment
+    pub date: NaiveDate,
+    pub payment: f64,   // Interest plus principal
+    pub interest: f64,  // Part of the payment that is interest
+    pub principal: f64, // Part of the payment that pays the loan down
+    pub balance: f64,   // Balance left after the payment
+}
+
+// Refuse loans the schedule can't be computed for, e.g. a negative or NaN interest rate
+pub fn validate(loan: &Loan) -> Result<(), Box<dyn Error>> {
+    if !KINDS.contains(&loan.kind.as_str()) {
+        return Err(format!(
+            "Invalid kind '{}', expected {}",
+            loan.kind,
+            KINDS.join(", ")
+        )
+        .into());
+    }
+    if !loan.principal.is_finite() || loan.principal <= 0.0 {
+        return Err("The principal must be greater than zero".into());
+    }
+    if !loan.rate.is_finite() || !(0.0..=MAX_RATE).contains(&loan.rate) {
+        return Err(format!("The interest rate must be between 0 and {}%", MAX_RATE).into());
+    }
+    if loan.term_months == 0 || loan.term_months > MAX_MONTHS {
+        return Err(format!("The term must be between 1 and {} months", MAX_MONTHS).into());
+    }
+    if !(1..=31).contains(&loan.payment_day) {
+        return Err("The payment day must be between 1 and 31".into());
+    }
+    Ok(())
+}
+
+// Expense category loan payments are booked to
+pub fn category(loan: &Loan) -> &'static str {
+    match loan.kind.as_str() {
+        "mortgage" => "Housing",
+        "car" => "Transportation",
+        _ => "Bills",
+    }
+}
+
+fn monthly_rate(loan: &Loan) -> f64 {
+    loan.rate / 100.0 / 12.0
+}
+
+// Fixed monthly payment that pays the loan off over its term
+pub fn monthly_payment(loan: &Loan) -> f64 {
+    let rate = monthly_rate(loan);
+    let term = loan.term_months.max(1) as f64;
+    if rate == 0.0 {
+        round_cents(loan.principal / term)
+    } else {
+        round_cents(loan.principal * rate / (1.0 - (1.0 + rate).powf(-term)))
+    }
+}
+
+// Due date of payment `number`, on the payment day of the months after the start.
+// The payment day is moved back in months that are too short.
+pub fn due_date(loan: &Loan, number: u32) -> NaiveDate {
+    let month = loan.start.with_day(1).unwrap() + Months::new(number);
+    (1..=loan.payment_day.clamp(1, 31))
+        .rev()
+        .find_map(|day| month.with_day(day))
+        .unwrap_or(month)
+}
+
+// Remaining payments starting from `balance`, with `number` being the next payment
+fn amortize(loan: &Loan, mut balance: f64, mut number: u32) -> Vec<Installment> {
+    let payment = monthly_payment(loan);
+    let rate = monthly_rate(loan);
+    let mut installments = Vec::new();
+    while balance > 0.005 && number <= loan.term_months.max(1) + MAX_MONTHS {
+        let interest = round_cents(balance * rate);
+        let principal = if number >= loan.term_months {
+            balance
+        } else {
+            (payment - interest).min(balance)
+        };
+        if principal <= 0.0 {
+            break; // The payment doesn't even cover the interest
+        }
+        balance = round_cents(balance - principal);
+        installments.push(Installment {
+            number,
+            date: due_date(loan, number),
+            payment: round_cents(interest + principal),
+            interest,
+            principal,
+            balance,
+        });
+        number += 1;
+    }
+    installments
+}
+
+// Full amortization schedule of the loan
+pub fn schedule(loan: &Loan) -> Vec<Installment> {
+    amortize(loan, loan.principal, 1)
+}
+
+// Payments recorded for the loan, including scheduled ones
+pub fn payments<'a>(loan: &Loan, transactions: &'a Transactions) -> Vec<&'a Transaction> {
+    transactions
+        .expenses
+        .iter()
+        .filter(|t| t.loan_id == Some(loan.id))
+        .collect()
+}
+
+// Principal still owed after all recorded payments
+pub fn balance(loan: &Loan, transactions: &Transactions) -> f64 {
+    let paid = report::sum(
+        payments(loan, transactions)
+            .into_iter()
+            .flat_map(|t| t.splits.iter())
+            .filter(|s| s.note == PRINCIPAL_NOTE)
+            .map(|s| s.amount.abs()),
+    );
+    round_cents((loan.principal - paid).max(0.0))
+}
+
+// Payments still to make, from the current balance on
+pub fn remaining_schedule(loan: &Loan, transactions: &Transactions) -> Vec<Installment> {
+    let made = payments(loan, transactions).len() as u32;
+    amortize(loan, balance(loan, transactions), made + 1)
+}
+
+// Expense recording `installment`, split into interest and principal
+pub fn payment_transaction(loan: &Loan, installment: &Installment, id: u32) -> Transaction {
+    let category = category(loan).to_string();
+    Transaction {
+        id,
+        amount: -installment.payment,
+        category: category.clone(),
+        date: installment.date,
+        description: format!(
+            "{} payment {}/{}",
+            loan.name, installment.number, loan.term_months
+        ),
+        payee: loan.name.clone(),
+        splits: vec![
+            Split {
+                amount: -installment.interest,
+                category: category.clone(),
+                note: INTEREST_NOTE.to_string(),
+            },
+            Split {
+                amount: -installment.principal,
+                category,
+                note: PRINCIPAL_NOTE.to_string(),
+            },
+        ],
+        loan_id: Some(loan.id),
+        ..Default::default()
+    }
+}
+
+// Order in which extra money is put towards debts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Strategy {
+    Snowball,  // Smallest balance first
+    Avalanche, // Highest interest rate first
+}
+
+impl Strategy {
+    pub fn toggle(&self) -> Strategy {
+        match self {
+            Strategy::Snowball => Strategy::Avalanche,
+            Strategy::Avalanche => Strategy::Snowball,
+        }
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Strategy::Snowball => write!(f, "snowball"),
+            Strategy::Avalanche => write!(f, "avalanche"),
+        }
+    }
+}
+
+// Outcome of paying off all debts with one strategy
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub strategy: Strategy,
+    pub total_interest: f64,
+    pub payoff: Vec<(String, Option<NaiveDate>)>, // Debts in payoff order; `None` if never paid off
+}
+
+impl Plan {
+    // Date the last debt is paid off, `None` if some debt is never paid off
+    pub fn debt_free(&self) -> Option<NaiveDate> {
+        if self.payoff.iter().any(|(_, date)| date.is_none()) {
+            return None;
+        }
+        self.payoff.iter().filter_map(|(_, date)| *date).max()
+    }
+}
+
+// Simulate paying every loan's minimum payment each month plus `extra`, starting with the
+// next payment due. Minimums of paid-off loans roll over to the next target.
+pub fn plan(loans: &[Loan], transactions: &Transactions, strategy: Strategy, extra: f64) -> Plan {
+    struct Debt<'a> {
+        loan: &'a Loan,
+        balance: f64,
+        number: u32, // Next installment
+        paid_off: Option<NaiveDate>,
+    }
+    let mut debts: Vec<Debt> = loans
+        .iter()
+        .map(|loan| Debt {
+            loan,
+            balance: balance(loan, transactions),
+            number: payments(loan, transactions).len() as u32 + 1,
+            paid_off: None,
+        })
+        .filter(|d| d.balance > 0.005)
+        .collect();
+    let budget = report::sum(debts.iter().map(|d| monthly_payment(d.loan))) + extra.max(0.0);
+    let mut total_interest = 0.0;
+    let mut order: Vec<usize> = Vec::new();
+
+    for _ in 0..MAX_MONTHS {
+        if debts.iter().all(|d| d.paid_off.is_some()) {
+            break;
+        }
+        let mut available = budget;
+        for debt in debts.iter_mut().filter(|d| d.paid_off.is_none()) {
+            let interest = debt.balance * monthly_rate(debt.loan);
+            debt.balance += interest;
+            total_interest += interest;
+            // The last payment of the term also covers rounding leftovers, as in the schedule
+            let payment = if debt.number >= debt.loan.term_months {
+                debt.balance
+            } else {
+                monthly_payment(debt.loan).min(debt.balance)
+            };
+            debt.balance -= payment;
+            available -= payment;
+        }
+        // Put what is left towards the targets in strategy order
+        let mut targets: Vec<usize> = (0..debts.len())
+            .filter(|&i| debts[i].paid_off.is_none())
+            .collect();
+        targets.sort_by(|&a, &b| match strategy {
+            Strategy::Snowball => debts[a].balance.total_cmp(&debts[b].balance),
+            Strategy::Avalanche => debts[b].loan.rate.total_cmp(&debts[a].loan.rate),
+        });
+        for i in targets {
+            let payment = available.min(debts[i].balance).max(0.0);
+            debts[i].balance -= payment;
+            available -= payment;
+        }
+        for (i, debt) in debts.iter_mut().enumerate() {
+            if debt.paid_off.is_some() {
+                continue;
+            }
+            if debt.balance < 0.005 {
+                debt.paid_off = Some(due_date(debt.loan, debt.number));
+                order.push(i);
+            }
+            debt.number += 1;
+        }
+    }
+
+    order.extend((0..debts.len()).filter(|&i| debts[i].paid_off.is_none()));
+    Plan {
+        strategy,
+        total_interest: round_cents(total_interest),
+        payoff: order
+            .into_iter()
+            .map(|i| (debts[i].loan.name.clone(), debts[i].paid_off))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::DataStructure;
+
+    fn personal_loan(id: u32, principal: f64, rate: f64, term_months: u32) -> Loan {
+        Loan {
+            id,
+            name: format!("Loan {}", id),
+            kind: "personal".to_string(),
+            principal,
+            rate,
+            term_months,
+            start: NaiveDate::from_ymd_opt(2026, 1, 31).unwrap(),
+            payment_day: 31,
+        }
+    }
+
+    #[test]
+    fn schedule_pays_the_loan_off_over_its_term() {
+        let loan = personal_loan(1, 10_000.0, 6.0, 12);
+        assert_eq!(monthly_payment(&loan), 860.66);
+        let installments = schedule(&loan);
+        assert_eq!(installments.len(), 12);
+        assert_eq!(installments[0].interest, 50.0);
+        assert_eq!(installments[0].principal, 810.66);
+        assert_eq!(installments[0].balance, 9_189.34);
+        // Payments are due on the last day of short months
+        assert_eq!(
+            installments[0].date,
+            NaiveDate::from_ymd_opt(2026, 2, 28).unwrap()
+        );
+        // The last payment takes care of the rounding leftovers
+        let last = installments.last().unwrap();
+        assert_eq!(last.date, NaiveDate::from_ymd_opt(2027, 1, 31).unwrap());
+        assert_eq!(last.payment, 860.7);
+        assert_eq!(last.balance, 0.0);
+        let principal = report::sum(installments.iter().map(|i| i.principal));
+        assert_eq!(round_cents(principal), 10_000.0);
+
+        let interest_free = personal_loan(2, 1_000.0, 0.0, 3);
+        assert_eq!(monthly_payment(&interest_free), 333.33);
+        let payments: Vec<f64> = schedule(&interest_free).iter().map(|i| i.payment).collect();
+        assert_eq!(payments, vec![333.33, 333.33, 333.34]);
+    }
+
+    #[test]
+    fn recorded_payments_lower_the_balance() {
+        let loan = personal_loan(1, 10_000.0, 6.0, 12);
+        let mut transactions = DataStructure::default().transactions;
+        let installments = schedule(&loan);
+        for (id, installment) in installments.iter().take(2).enumerate() {
+            transactions
+                .expenses
+                .push(payment_transaction(&loan, installment, id as u32 + 1));
+        }
+        assert_eq!(transactions.expenses[0].amount, -860.66);
+        assert_eq!(balance(&loan, &transactions), installments[1].balance);
+        assert_eq!(remaining_schedule(&loan, &transactions), installments[2..]);
+    }
+
+    #[test]
+    fn extra_payments_go_to_the_target_of_the_strategy() {
+        let transactions = DataStructure::default().transactions;
+        let small = personal_loan(1, 1_000.0, 5.0, 24);
+        let expensive = personal_loan(2, 5_000.0, 20.0, 36);
+        let loans = [small, expensive];
+        let date = |y, m, d| Some(NaiveDate::from_ymd_opt(y, m, d).unwrap());
+
+        // Without extra money both strategies only roll the minimums over
+        let minimum = plan(&loans, &transactions, Strategy::Snowball, 0.0);
+        assert_eq!(
+            minimum.payoff,
+            vec![
+                ("Loan 1".to_string(), date(2028, 1, 31)),
+                ("Loan 2".to_string(), date(2028, 11, 30)),
+            ]
+        );
+        assert_eq!(
+            plan(&loans, &transactions, Strategy::Avalanche, 0.0).total_interest,
+            minimum.total_interest
+        );
+
+        // Snowball pays the smallest balance off first, avalanche the highest rate
+        let snowball = plan(&loans, &transactions, Strategy::Snowball, 100.0);
+        assert_eq!(
+            snowball.payoff[0],
+            ("Loan 1".to_string(), date(2026, 9, 30))
+        );
+        assert_eq!(snowball.debt_free(), date(2027, 11, 30));
+        let avalanche = plan(&loans, &transactions, Strategy::Avalanche, 100.0);
+        assert_eq!(
+            avalanche.payoff[0],
+            ("Loan 2".to_string(), date(2027, 10, 31))
+        );
+        assert_eq!(avalanche.debt_free(), date(2027, 11, 30));
+        assert!(avalanche.total_interest < snowball.total_interest);
+        assert!(snowball.total_interest < minimum.total_interest);
+    }
+
+    #[test]
+    fn invalid_loans_are_refused() {
+        assert!(validate(&personal_loan(1, 10_000.0, 6.0, 12)).is_ok());
+        assert!(validate(&personal_loan(1, 10_000.0, 0.0, 12)).is_ok());
+        for invalid in [
+            personal_loan(1, 10_000.0, -1.0, 12),
+            personal_loan(1, 10_000.0, f64::NAN, 12),
+            personal_loan(1, 10_000.0, f64::INFINITY, 12),
+            personal_loan(1, 0.0, 6.0, 12),
+            personal_loan(1, f64::NAN, 6.0, 12),
+            personal_loan(1, 10_000.0, 6.0, 0),
+            Loan {
+                payment_day: 32,
+                ..personal_loan(1, 10_000.0, 6.0, 12)
+            },
+            Loan {
+                kind: "boat".to_string(),
+                ..personal_loan(1, 10_000.0, 6.0, 12)
+            },
+        ] {
+            assert!(validate(&invalid).is_err(), "{:?}", invalid);
+        }
+    }
+}
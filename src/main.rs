@@ -1,14 +1,58 @@
+mod anomaly;
 mod app;
+mod bills;
+mod budget;
+mod cli;
+mod crypto;
 mod data;
+mod export;
+mod goals;
+mod history;
+mod html;
+mod import;
+mod investments;
+mod loans;
+mod payee;
+mod reconcile;
+mod refund;
+mod report;
+mod shared;
+mod splits;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod storage;
+mod tags;
+mod tax;
 mod ui;
 
 use app::App;
 use crossterm::execute;
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use data::DATA_FILE;
+use std::env;
 use std::error::Error;
 use std::io::stdout;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Run a one-off command instead of the TUI when arguments are given
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&args) {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Open the ledger before taking over the screen, so an encrypted one can ask for its passphrase
+    let storage = match storage::open(DATA_FILE) {
+        Ok(storage) => storage,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
+
     // Enable raw mode for the terminal to handle input directly
     enable_raw_mode()?;
 
@@ -19,8 +63,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     execute!(stdout, crossterm::terminal::EnterAlternateScreen)?;
 
     // Create a new App instance and run it
-    // The `and_then` method ensures that `app.run()` is only called if `App::new()` succeeds
-    let result = App::new().and_then(|mut app| app.run());
+    // The `and_then` method ensures that `app.run()` is only called if `App::with_storage()` succeeds
+    let result = App::with_storage(storage).and_then(|mut app| app.run());
 
     // Restore the terminal to its previous state
     execute!(stdout, crossterm::terminal::LeaveAlternateScreen)?;
@@ -0,0 +1,79 @@
+use crate::data::Transaction;
+use std::collections::HashMap;
+
+// Payee name as it is compared: "  LIDL " and "Lidl" are the same payee
+fn normalize(payee: &str) -> String {
+    payee.trim().to_lowercase()
+}
+
+// Known payees starting with `prefix` (ignoring case), most frequently used first
+pub fn suggestions(transactions: &[Transaction], prefix: &str) -> Vec<String> {
+    let prefix = normalize(prefix);
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for t in transactions {
+        if !t.payee.is_empty() && normalize(&t.payee).starts_with(&prefix) {
+            *counts.entry(t.payee.as_str()).or_insert(0) += 1;
+        }
+    }
+    let mut payees: Vec<(&str, usize)> = counts.into_iter().collect();
+    payees.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    payees.into_iter().map(|(p, _)| p.to_string()).collect()
+}
+
+// Category used most often together with `payee` (ignoring case)
+pub fn most_common_category(transactions: &[Transaction], payee: &str) -> Option<String> {
+    let payee = normalize(payee);
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for t in transactions.iter().filter(|t| normalize(&t.payee) == payee) {
+        *counts.entry(t.category.as_str()).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(category, _)| category.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paid(payee: &str, category: &str) -> Transaction {
+        Transaction {
+            payee: payee.to_string(),
+            category: category.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn payees_match_ignoring_case_and_surrounding_spaces() {
+        let history = vec![
+            paid("Lidl", "Food"),
+            paid("Lidl", "Food"),
+            paid("Library", "Other"),
+            paid("", "Other"),
+            paid("Aldi", "Food"),
+        ];
+        assert_eq!(suggestions(&history, "  LI"), vec!["Lidl", "Library"]);
+        assert_eq!(suggestions(&history, "lib"), vec!["Library"]);
+        assert!(suggestions(&history, "   ").is_empty());
+    }
+
+    #[test]
+    fn most_common_category_of_a_payee() {
+        let history = vec![
+            paid("Lidl", "Food"),
+            paid("LIDL ", "Household"),
+            paid("lidl", "Household"),
+            paid("Aldi", "Food"),
+        ];
+        assert_eq!(
+            most_common_category(&history, "Lidl").as_deref(),
+            Some("Household")
+        );
+        assert_eq!(most_common_category(&history, "Rewe"), None);
+    }
+}
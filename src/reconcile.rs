@@ -0,0 +1,134 @@
+use crate::data::{Status, Transaction, Transactions};
+use crate::report;
+use chrono::NaiveDate;
+
+// Largest difference still treated as a balanced statement
+const TOLERANCE: f64 = 0.005;
+
+// Bank statement the transactions are reconciled against
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Statement {
+    pub balance: f64,    // Ending balance printed on the statement
+    pub date: NaiveDate, // Closing date of the statement
+}
+
+impl Statement {
+    // Parse "1520.40 2026-09-30"; the date defaults to `today` when left out
+    pub fn parse(input: &str, today: NaiveDate) -> Result<Self, String> {
+        let mut parts = input.split_whitespace();
+        let balance = parts.next().ok_or("Enter the statement ending balance")?;
+        let balance = balance
+            .parse::<f64>()
+            .ok()
+            .filter(|balance| balance.is_finite())
+            .ok_or_else(|| format!("Invalid balance '{}'", balance))?;
+        let date = match parts.next() {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| format!("Invalid date '{}', use YYYY-MM-DD", date))?,
+            None => today,
+        };
+        if parts.next().is_some() {
+            return Err("Enter only a balance and a date".to_string());
+        }
+        Ok(Self { balance, date })
+    }
+
+    // Whether the transaction can appear on this statement
+    pub fn covers(&self, transaction: &Transaction) -> bool {
+        transaction.date <= self.date
+    }
+
+    // Statement balance minus the balance of everything ticked off so far
+    pub fn difference(&self, transactions: &Transactions) -> f64 {
+        self.balance - cleared_balance(transactions, self.date)
+    }
+
+    pub fn is_balanced(&self, transactions: &Transactions) -> bool {
+        self.difference(transactions).abs() < TOLERANCE
+    }
+}
+
+// Balance of all cleared and reconciled transactions up to `date`
+pub fn cleared_balance(transactions: &Transactions, date: NaiveDate) -> f64 {
+    report::sum(
+        transactions
+            .expenses
+            .iter()
+            .chain(transactions.income.iter())
+            .filter(|t| t.status != Status::Uncleared && t.date <= date)
+            .map(|t| t.amount),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Data, DataStructure};
+    use crate::storage::Memory;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn transaction(id: u32, day: &str, amount: f64, status: Status) -> Transaction {
+        Transaction {
+            id,
+            amount,
+            category: "Food".to_string(),
+            date: date(day),
+            status,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn statements_parse_with_an_optional_date() {
+        let today = date("2026-10-19");
+        assert_eq!(
+            Statement::parse("1520.40 2026-09-30", today).unwrap(),
+            Statement {
+                balance: 1520.4,
+                date: date("2026-09-30"),
+            }
+        );
+        assert_eq!(Statement::parse("-5", today).unwrap().date, today);
+        for input in ["", "abc", "NaN", "10 30.09.2026", "10 2026-09-30 extra"] {
+            assert!(Statement::parse(input, today).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn only_ticked_off_transactions_up_to_the_statement_count() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses = vec![
+            transaction(1, "2026-09-10", -30.0, Status::Cleared),
+            transaction(2, "2026-09-12", -99.0, Status::Uncleared),
+            transaction(3, "2026-10-02", -50.0, Status::Cleared),
+        ];
+        transactions.income = vec![transaction(1, "2026-09-01", 100.0, Status::Reconciled)];
+        let statement = Statement::parse("70 2026-09-30", date("2026-10-19")).unwrap();
+        assert!(statement.is_balanced(&transactions));
+        assert!(!statement.covers(&transactions.expenses[2]));
+        let statement = Statement::parse("60 2026-09-30", date("2026-10-19")).unwrap();
+        assert_eq!(statement.difference(&transactions), -10.0);
+    }
+
+    #[test]
+    fn reconciled_rows_are_locked_against_edits() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        data.add_transaction(
+            transaction(1, "2026-09-10", -30.0, Status::Cleared),
+            "expenses",
+        )
+        .unwrap();
+        assert_eq!(data.reconcile(date("2026-09-30")).unwrap(), 1);
+
+        let mut edited = data.get_transactions().expenses[0].clone();
+        assert_eq!(edited.status, Status::Reconciled);
+        edited.amount = -300.0;
+        edited.status = Status::Uncleared;
+        assert!(data.update_transaction(edited, "expenses").is_err());
+        let stored = &data.get_transactions().expenses[0];
+        assert_eq!((stored.amount, stored.status), (-30.0, Status::Reconciled));
+    }
+}
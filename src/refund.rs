@@ -0,0 +1,111 @@
+use crate::data::{Transaction, Transactions};
+use crate::report;
+
+// Tag marking expenses someone else is expected to pay back
+pub const REIMBURSABLE_TAG: &str = "reimbursable";
+
+// A reimbursable expense that has not been paid back in full
+#[derive(Debug, Clone)]
+pub struct Outstanding<'a> {
+    pub expense: &'a Transaction,
+    pub refunded: f64,  // Refunded so far
+    pub remaining: f64, // Still to be paid back
+}
+
+// Refunds linked to the expense with `id`
+pub fn refunds_of(transactions: &Transactions, id: u32) -> impl Iterator<Item = &Transaction> {
+    transactions
+        .expenses
+        .iter()
+        .filter(move |t| t.refund_of == Some(id))
+}
+
+// Total refunded for the expense with `id`
+pub fn refunded(transactions: &Transactions, id: u32) -> f64 {
+    report::sum(refunds_of(transactions, id).map(|t| t.amount))
+}
+
+// Part of an expense that has not been refunded yet
+pub fn remaining(transactions: &Transactions, expense: &Transaction) -> f64 {
+    (expense.amount.abs() - refunded(transactions, expense.id)).max(0.0)
+}
+
+// Reimbursable expenses still waiting for (part of) their money, oldest first
+pub fn outstanding(transactions: &Transactions) -> Vec<Outstanding<'_>> {
+    let mut items: Vec<Outstanding> = transactions
+        .expenses
+        .iter()
+        .filter(|t| t.refund_of.is_none() && t.tags.iter().any(|tag| tag == REIMBURSABLE_TAG))
+        .map(|expense| Outstanding {
+            expense,
+            refunded: refunded(transactions, expense.id),
+            remaining: remaining(transactions, expense),
+        })
+        .filter(|item| item.remaining > 0.005)
+        .collect();
+    items.sort_by_key(|item| item.expense.date);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::DataStructure;
+    use crate::report::{category_spending, totals};
+    use chrono::NaiveDate;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn expense(id: u32, day: &str, amount: f64, refund_of: Option<u32>) -> Transaction {
+        Transaction {
+            id,
+            amount,
+            category: "Travel".to_string(),
+            date: date(day),
+            refund_of,
+            tags: vec![REIMBURSABLE_TAG.to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn refunds_net_against_the_original_expense() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses = vec![
+            expense(1, "2026-09-03", -200.0, None),
+            expense(2, "2026-09-20", 50.0, Some(1)),
+            expense(3, "2026-10-02", 30.0, Some(1)),
+            expense(4, "2026-09-05", -40.0, None),
+        ];
+        assert_eq!(refunded(&transactions, 1), 80.0);
+        assert_eq!(remaining(&transactions, &transactions.expenses[0]), 120.0);
+
+        // The refund lowers the spending of the category instead of counting as income
+        let (start, end) = (date("2026-09-01"), date("2026-10-01"));
+        assert_eq!(
+            category_spending(&transactions, start, end)["Travel"],
+            190.0
+        );
+        assert_eq!(totals(&transactions, start, end).income, 0.0);
+
+        let outstanding = outstanding(&transactions);
+        let ids: Vec<(u32, f64)> = outstanding
+            .iter()
+            .map(|item| (item.expense.id, item.remaining))
+            .collect();
+        assert_eq!(ids, vec![(1, 120.0), (4, 40.0)]);
+    }
+
+    #[test]
+    fn fully_refunded_expenses_are_no_longer_outstanding() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses = vec![
+            expense(1, "2026-09-03", -20.0, None),
+            expense(2, "2026-09-20", 25.0, Some(1)),
+        ];
+        assert_eq!(remaining(&transactions, &transactions.expenses[0]), 0.0);
+        assert!(outstanding(&transactions).is_empty());
+    }
+}
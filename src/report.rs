@@ -0,0 +1,472 @@
+use crate::data::{Transaction, Transactions};
+use chrono::{Datelike, Months, NaiveDate};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Length of the window a report covers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Period {
+    Month,
+    Year,
+}
+
+impl Period {
+    // First day of the period that contains `date`
+    pub fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Month => NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap(),
+            Period::Year => NaiveDate::from_ymd_opt(date.year(), 1, 1).unwrap(),
+        }
+    }
+
+    // Move a period start forward (positive `n`) or backward (negative `n`) by whole periods
+    pub fn shift(&self, start: NaiveDate, n: i32) -> NaiveDate {
+        let months = match self {
+            Period::Month => n.unsigned_abs(),
+            Period::Year => n.unsigned_abs() * 12,
+        };
+        if n >= 0 {
+            start + Months::new(months)
+        } else {
+            start - Months::new(months)
+        }
+    }
+
+    // Human readable name of the period starting at `start` (e.g. "2026-10" or "2026")
+    pub fn label(&self, start: NaiveDate) -> String {
+        match self {
+            Period::Month => start.format("%Y-%m").to_string(),
+            Period::Year => start.format("%Y").to_string(),
+        }
+    }
+
+    pub fn toggle(&self) -> Period {
+        match self {
+            Period::Month => Period::Year,
+            Period::Year => Period::Month,
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Period::Month => write!(f, "month"),
+            Period::Year => write!(f, "year"),
+        }
+    }
+}
+
+// Income and spending over a range of dates
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Totals {
+    pub income: f64,   // Sum of all income
+    pub expenses: f64, // Sum of all spending, as a positive number, net of refunds
+}
+
+impl Totals {
+    pub fn net(&self) -> f64 {
+        self.income - self.expenses
+    }
+}
+
+// Income and spending for dates in [start, end)
+pub fn totals(transactions: &Transactions, start: NaiveDate, end: NaiveDate) -> Totals {
+    let in_range = |t: &&Transaction| !t.pending && t.date >= start && t.date < end;
+    Totals {
+        income: sum(transactions
+            .income
+            .iter()
+            .filter(in_range)
+            .map(|t| t.amount)),
+        expenses: sum(transactions
+            .expenses
+            .iter()
+            .filter(in_range)
+            .map(|t| -t.amount)),
+    }
+}
+
+// Actual balance next to what it will be once all pending transactions have posted
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Forecast {
+    pub balance: f64,             // Net of all posted transactions
+    pub pending_income: f64,      // Scheduled income
+    pub pending_expenses: f64,    // Scheduled spending, as a positive number
+    pub until: Option<NaiveDate>, // Date of the last pending transaction
+}
+
+impl Forecast {
+    pub fn forecast_balance(&self) -> f64 {
+        self.balance + self.pending_income - self.pending_expenses
+    }
+}
+
+pub fn forecast(transactions: &Transactions) -> Forecast {
+    let pending = || {
+        transactions
+            .expenses
+            .iter()
+            .chain(transactions.income.iter())
+            .filter(|t| t.pending)
+    };
+    Forecast {
+        balance: totals(transactions, NaiveDate::MIN, NaiveDate::MAX).net(),
+        pending_income: sum(pending().filter(|t| t.amount > 0.0).map(|t| t.amount)),
+        pending_expenses: sum(pending().filter(|t| t.amount < 0.0).map(|t| t.amount.abs())),
+        until: pending().map(|t| t.date).max(),
+    }
+}
+
+// Amount rounded to whole cents
+pub fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+// Add up amounts starting from positive zero, so empty ranges print as 0.00 instead of -0.00
+pub fn sum(amounts: impl Iterator<Item = f64>) -> f64 {
+    amounts.fold(0.0, |total, amount| total + amount)
+}
+
+// Income and spending of each of the `months` months ending with the one containing `anchor`
+pub fn monthly_totals(
+    transactions: &Transactions,
+    anchor: NaiveDate,
+    months: u32,
+) -> Vec<(NaiveDate, Totals)> {
+    let last = Period::Month.start_of(anchor);
+    (0..months)
+        .rev()
+        .map(|back| {
+            let start = last - Months::new(back);
+            (start, totals(transactions, start, start + Months::new(1)))
+        })
+        .collect()
+}
+
+// What spending is grouped by in reports
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grouping {
+    Category,
+    Payee,
+    Tag,
+}
+
+impl Grouping {
+    // Amounts a transaction contributes to each group.
+    // Split transactions count per split line towards their categories, and a transaction counts
+    // towards each of its tags. Untagged transactions are left out of tag reports.
+    pub fn amounts(&self, transaction: &Transaction) -> Vec<(String, f64)> {
+        match self {
+            Grouping::Category => transaction
+                .category_amounts()
+                .into_iter()
+                .map(|(category, amount)| (category.to_string(), amount))
+                .collect(),
+            Grouping::Payee if transaction.payee.is_empty() => {
+                vec![("(no payee)".to_string(), transaction.amount)]
+            }
+            Grouping::Payee => vec![(transaction.payee.clone(), transaction.amount)],
+            Grouping::Tag => transaction
+                .tags
+                .iter()
+                .map(|tag| (tag.clone(), transaction.amount))
+                .collect(),
+        }
+    }
+
+    pub fn next(&self) -> Grouping {
+        match self {
+            Grouping::Category => Grouping::Payee,
+            Grouping::Payee => Grouping::Tag,
+            Grouping::Tag => Grouping::Category,
+        }
+    }
+}
+
+impl fmt::Display for Grouping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Grouping::Category => write!(f, "category"),
+            Grouping::Payee => write!(f, "payee"),
+            Grouping::Tag => write!(f, "tag"),
+        }
+    }
+}
+
+// Total spending per group for dates in [start, end). Refunds are stored as positive expenses,
+// so they reduce the spending of their group.
+pub fn spending_by(
+    transactions: &Transactions,
+    grouping: Grouping,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> BTreeMap<String, f64> {
+    let mut totals = BTreeMap::new();
+    for t in transactions
+        .expenses
+        .iter()
+        .filter(|t| !t.pending && t.date >= start && t.date < end)
+    {
+        for (key, amount) in grouping.amounts(t) {
+            *totals.entry(key).or_insert(0.0) -= amount;
+        }
+    }
+    totals
+}
+
+// Total spending per expense category for dates in [start, end)
+pub fn category_spending(
+    transactions: &Transactions,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> BTreeMap<String, f64> {
+    spending_by(transactions, Grouping::Category, start, end)
+}
+
+// Spending of one group in a period next to the previous period and the same period last year
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub name: String,   // Category or payee (or "Total")
+    pub current: f64,   // Spending in the selected period
+    pub previous: f64,  // Spending in the period right before it
+    pub last_year: f64, // Spending in the same period one year earlier
+}
+
+impl Comparison {
+    pub fn previous_delta(&self) -> f64 {
+        self.current - self.previous
+    }
+
+    pub fn last_year_delta(&self) -> f64 {
+        self.current - self.last_year
+    }
+
+    // Percentage change against the previous period, `None` when there is nothing to compare to
+    pub fn previous_pct(&self) -> Option<f64> {
+        percentage(self.current, self.previous)
+    }
+
+    // Percentage change against the same period last year
+    pub fn last_year_pct(&self) -> Option<f64> {
+        percentage(self.current, self.last_year)
+    }
+
+    // Sum a list of comparisons into a single "Total" row. None when grouped by tag: a
+    // transaction with several tags is in several rows, so their sum would overstate spending.
+    pub fn total(grouping: Grouping, rows: &[Comparison]) -> Option<Comparison> {
+        if grouping == Grouping::Tag {
+            return None;
+        }
+        Some(Comparison {
+            name: "Total".to_string(),
+            current: sum(rows.iter().map(|r| r.current)),
+            previous: sum(rows.iter().map(|r| r.previous)),
+            last_year: sum(rows.iter().map(|r| r.last_year)),
+        })
+    }
+}
+
+fn percentage(current: f64, base: f64) -> Option<f64> {
+    if base.abs() < f64::EPSILON {
+        None
+    } else {
+        Some((current - base) / base * 100.0)
+    }
+}
+
+// Compare the spending of every group for the period containing `anchor`.
+// For yearly reports the previous period and last year are the same window.
+pub fn compare(
+    transactions: &Transactions,
+    grouping: Grouping,
+    period: Period,
+    anchor: NaiveDate,
+) -> Vec<Comparison> {
+    let start = period.start_of(anchor);
+    let end = period.shift(start, 1);
+    let previous_start = period.shift(start, -1);
+    let last_year_start = start - Months::new(12);
+    let last_year_end = end - Months::new(12);
+
+    let current = spending_by(transactions, grouping, start, end);
+    let previous = spending_by(transactions, grouping, previous_start, start);
+    let last_year = spending_by(transactions, grouping, last_year_start, last_year_end);
+
+    let mut names: Vec<&String> = current
+        .keys()
+        .chain(previous.keys())
+        .chain(last_year.keys())
+        .collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .map(|name| Comparison {
+            name: name.clone(),
+            current: current.get(name).copied().unwrap_or(0.0),
+            previous: previous.get(name).copied().unwrap_or(0.0),
+            last_year: last_year.get(name).copied().unwrap_or(0.0),
+        })
+        .collect()
+}
+
+// Format a delta with its percentage, e.g. "+20.00 (+25.0%)"
+pub fn format_delta(delta: f64, pct: Option<f64>) -> String {
+    match pct {
+        Some(pct) => format!("{:+.2} ({:+.1}%)", delta, pct),
+        None => format!("{:+.2} (n/a)", delta),
+    }
+}
+
+// Total spending per day for dates in [start, end)
+pub fn daily_spending(
+    transactions: &Transactions,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> BTreeMap<NaiveDate, f64> {
+    let mut totals = BTreeMap::new();
+    for t in transactions
+        .expenses
+        .iter()
+        .filter(|t| !t.pending && t.date >= start && t.date < end)
+    {
+        *totals.entry(t.date).or_insert(0.0) -= t.amount;
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::DataStructure;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn expense(id: u32, day: &str, category: &str, amount: f64) -> Transaction {
+        Transaction {
+            id,
+            amount,
+            category: category.to_string(),
+            date: date(day),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn shifting_keeps_month_ends_in_shorter_months() {
+        assert_eq!(
+            Period::Month.shift(date("2026-01-31"), 1),
+            date("2026-02-28")
+        );
+        assert_eq!(
+            Period::Month.shift(date("2028-01-31"), 1),
+            date("2028-02-29")
+        );
+        assert_eq!(
+            Period::Month.shift(date("2026-03-31"), -1),
+            date("2026-02-28")
+        );
+        assert_eq!(
+            Period::Month.shift(date("2026-01-01"), -1),
+            date("2025-12-01")
+        );
+        assert_eq!(
+            Period::Year.shift(date("2028-02-29"), 1),
+            date("2029-02-28")
+        );
+        assert_eq!(
+            Period::Year.shift(date("2026-01-01"), -2),
+            date("2024-01-01")
+        );
+    }
+
+    #[test]
+    fn comparison_uses_the_previous_month_and_the_same_month_last_year() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses = vec![
+            expense(1, "2028-02-29", "Food", -120.0),
+            expense(2, "2028-01-31", "Food", -100.0),
+            expense(3, "2027-02-28", "Food", -80.0),
+            expense(4, "2027-03-01", "Food", -500.0),
+            expense(5, "2028-02-01", "Health", -30.0),
+        ];
+        let rows = compare(
+            &transactions,
+            Grouping::Category,
+            Period::Month,
+            date("2028-02-29"),
+        );
+        assert_eq!(
+            rows[0],
+            Comparison {
+                name: "Food".to_string(),
+                current: 120.0,
+                previous: 100.0,
+                last_year: 80.0,
+            }
+        );
+        assert_eq!(rows[0].previous_pct(), Some(20.0));
+        assert_eq!(rows[0].last_year_pct(), Some(50.0));
+        assert_eq!(rows[1].name, "Health");
+        assert_eq!(rows[1].previous_pct(), None);
+    }
+
+    #[test]
+    fn yearly_comparison_uses_last_year_for_both() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses = vec![
+            expense(1, "2026-12-31", "Food", -50.0),
+            expense(2, "2025-01-01", "Food", -40.0),
+        ];
+        let rows = compare(
+            &transactions,
+            Grouping::Category,
+            Period::Year,
+            date("2026-06-15"),
+        );
+        assert_eq!(rows.len(), 1);
+        assert_eq!((rows[0].current, rows[0].previous), (50.0, 40.0));
+        assert_eq!(rows[0].previous, rows[0].last_year);
+    }
+
+    #[test]
+    fn tag_reports_have_no_total_counting_a_transaction_twice() {
+        let mut transactions = DataStructure::default().transactions;
+        let mut trip = expense(1, "2026-10-05", "Food", -40.0);
+        trip.tags = vec!["kids".to_string(), "vacation-2026".to_string()];
+        transactions.expenses = vec![trip, expense(2, "2026-10-06", "Food", -10.0)];
+
+        let by_tag = compare(
+            &transactions,
+            Grouping::Tag,
+            Period::Month,
+            date("2026-10-01"),
+        );
+        let names: Vec<(&str, f64)> = by_tag
+            .iter()
+            .map(|r| (r.name.as_str(), r.current))
+            .collect();
+        assert_eq!(names, vec![("kids", 40.0), ("vacation-2026", 40.0)]);
+        assert_eq!(Comparison::total(Grouping::Tag, &by_tag), None);
+
+        let by_category = compare(
+            &transactions,
+            Grouping::Category,
+            Period::Month,
+            date("2026-10-01"),
+        );
+        let total = Comparison::total(Grouping::Category, &by_category).unwrap();
+        assert_eq!(total.current, 50.0);
+    }
+
+    #[test]
+    fn deltas_show_signs_and_missing_percentages() {
+        assert_eq!(format_delta(20.0, Some(25.0)), "+20.00 (+25.0%)");
+        assert_eq!(format_delta(-5.5, Some(-10.0)), "-5.50 (-10.0%)");
+        assert_eq!(format_delta(12.0, None), "+12.00 (n/a)");
+    }
+}
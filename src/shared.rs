@@ -0,0 +1,237 @@
+use crate::data::{Share, Transactions, Transfer};
+use crate::report::round_cents;
+use std::collections::BTreeMap;
+
+// Amounts below this are treated as settled
+const TOLERANCE: f64 = 0.005;
+
+// Parse a comma separated list of names
+fn names(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Parse "Alice:2,Bob:1" into names and numbers
+fn weights(input: &str) -> Result<Vec<(String, f64)>, String> {
+    input
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(|entry| {
+            let (name, value) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("'{}' needs Name:Number", entry))?;
+            let value = value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Invalid number in '{}'", entry))?;
+            if !(value >= 0.0 && value.is_finite()) {
+                return Err(format!("'{}' needs a number of zero or more", entry));
+            }
+            Ok((name.trim().to_string(), value))
+        })
+        .collect()
+}
+
+// Split `total` equally; leftover cents go to the first people
+pub fn equal(total: f64, people: &str) -> Result<Vec<Share>, String> {
+    let people = names(people);
+    if people.is_empty() {
+        return Err("Name at least one person to split with".to_string());
+    }
+    by_weight(total, people.into_iter().map(|p| (p, 1.0)).collect())
+}
+
+// Split `total` proportionally to "Alice:2,Bob:1"
+pub fn shares(total: f64, input: &str) -> Result<Vec<Share>, String> {
+    by_weight(total, weights(input)?)
+}
+
+fn by_weight(total: f64, weights: Vec<(String, f64)>) -> Result<Vec<Share>, String> {
+    let sum: f64 = weights.iter().map(|(_, w)| w).sum();
+    if !(sum > 0.0 && sum.is_finite()) {
+        return Err("The shares must add up to more than zero".to_string());
+    }
+    let mut result: Vec<Share> = weights
+        .iter()
+        .map(|(person, weight)| Share {
+            person: person.clone(),
+            amount: round_cents(total * weight / sum),
+        })
+        .collect();
+    // Put the rounding difference on the first person so the shares add up exactly
+    let difference = round_cents(total - result.iter().map(|s| s.amount).sum::<f64>());
+    result[0].amount = round_cents(result[0].amount + difference);
+    Ok(result)
+}
+
+// Exact amounts "Alice:30,Bob:20", which must add up to `total`
+pub fn exact(total: f64, input: &str) -> Result<Vec<Share>, String> {
+    let result: Vec<Share> = weights(input)?
+        .into_iter()
+        .map(|(person, amount)| Share { person, amount })
+        .collect();
+    let sum: f64 = result.iter().map(|s| s.amount).sum();
+    if !total.is_finite() || (sum - total).abs() > TOLERANCE {
+        return Err(format!(
+            "The amounts add up to {:.2} instead of {:.2}",
+            sum, total
+        ));
+    }
+    Ok(result)
+}
+
+// What everyone is owed (positive) or owes (negative) after all shared expenses and transfers
+pub fn balances(transactions: &Transactions, transfers: &[Transfer]) -> BTreeMap<String, f64> {
+    let mut balances: BTreeMap<String, f64> = BTreeMap::new();
+    for t in &transactions.expenses {
+        if let Some(shared) = &t.shared {
+            *balances.entry(shared.paid_by.clone()).or_insert(0.0) += t.amount.abs();
+            for share in &shared.shares {
+                *balances.entry(share.person.clone()).or_insert(0.0) -= share.amount;
+            }
+        }
+    }
+    for transfer in transfers {
+        *balances.entry(transfer.from.clone()).or_insert(0.0) += transfer.amount;
+        *balances.entry(transfer.to.clone()).or_insert(0.0) -= transfer.amount;
+    }
+    balances
+        .into_iter()
+        .map(|(person, balance)| (person, round_cents(balance)))
+        .collect()
+}
+
+// Payments (from, to, amount) that settle all balances. Repeatedly paying the biggest creditor
+// from the biggest debtor needs at most one payment less than there are people.
+pub fn settle_up(balances: &BTreeMap<String, f64>) -> Vec<(String, String, f64)> {
+    let mut open: Vec<(String, f64)> = balances
+        .iter()
+        .filter(|(_, b)| b.abs() > TOLERANCE)
+        .map(|(p, b)| (p.clone(), *b))
+        .collect();
+    let mut payments = Vec::new();
+    loop {
+        let creditor = open
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+            .map(|(i, _)| i);
+        let debtor = open
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1 .1.total_cmp(&b.1 .1))
+            .map(|(i, _)| i);
+        let (Some(creditor), Some(debtor)) = (creditor, debtor) else {
+            break;
+        };
+        let amount = round_cents(open[creditor].1.min(-open[debtor].1));
+        if amount <= TOLERANCE {
+            break;
+        }
+        payments.push((open[debtor].0.clone(), open[creditor].0.clone(), amount));
+        open[creditor].1 -= amount;
+        open[debtor].1 += amount;
+    }
+    payments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Data, DataStructure, Shared, Transaction};
+    use crate::storage::Memory;
+    use chrono::NaiveDate;
+
+    fn amounts(shares: &[Share]) -> Vec<(&str, f64)> {
+        shares
+            .iter()
+            .map(|s| (s.person.as_str(), s.amount))
+            .collect()
+    }
+
+    fn transfer(from: &str, to: &str, amount: f64) -> Transfer {
+        Transfer {
+            id: 0,
+            date: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+            from: from.to_string(),
+            to: to.to_string(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn equal_splits_give_the_leftover_cents_to_the_first_person() {
+        let shares = equal(100.0, "Alex, Sam,Kim,").unwrap();
+        assert_eq!(
+            amounts(&shares),
+            vec![("Alex", 33.34), ("Sam", 33.33), ("Kim", 33.33)]
+        );
+        assert!(equal(100.0, " , ").is_err());
+    }
+
+    #[test]
+    fn shares_split_proportionally() {
+        let shares = shares(10.0, "Alex:2,Sam:1").unwrap();
+        assert_eq!(amounts(&shares), vec![("Alex", 6.67), ("Sam", 3.33)]);
+        assert!(super::shares(10.0, "Alex:0,Sam:0").is_err());
+        for input in ["Alex:-1", "Alex:NaN", "Alex:inf,Sam:1", "Alex", "Alex:two"] {
+            assert!(super::shares(10.0, input).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn exact_amounts_must_add_up_to_the_total() {
+        let shares = exact(50.0, "Alex:30,Sam:20").unwrap();
+        assert_eq!(amounts(&shares), vec![("Alex", 30.0), ("Sam", 20.0)]);
+        assert!(exact(50.0, "Alex:30,Sam:10").is_err());
+        assert!(exact(50.0, "Alex:inf,Sam:-inf").is_err());
+        assert!(exact(f64::NAN, "Alex:10").is_err());
+    }
+
+    #[test]
+    fn settling_up_needs_fewer_payments_than_people() {
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses.push(Transaction {
+            id: 1,
+            amount: -90.0,
+            category: "Food".to_string(),
+            shared: Some(Shared {
+                paid_by: "Alex".to_string(),
+                shares: equal(90.0, "Alex,Sam,Kim").unwrap(),
+            }),
+            ..Default::default()
+        });
+        let transfers = vec![transfer("Kim", "Alex", 10.0)];
+        let balances = balances(&transactions, &transfers);
+        assert_eq!(balances["Alex"], 50.0);
+        assert_eq!(balances["Kim"], -20.0);
+        assert_eq!(
+            settle_up(&balances),
+            vec![
+                ("Sam".to_string(), "Alex".to_string(), 30.0),
+                ("Kim".to_string(), "Alex".to_string(), 20.0),
+            ]
+        );
+        assert!(settle_up(&BTreeMap::from([("Alex".to_string(), 0.001)])).is_empty());
+    }
+
+    #[test]
+    fn a_settle_up_is_recorded_as_one_change() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        let ids = data
+            .add_transfers(vec![
+                transfer("Sam", "Alex", 30.0),
+                transfer("Kim", "Alex", 20.0),
+            ])
+            .unwrap();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(data.get_history().undo.len(), 1);
+        data.undo().unwrap();
+        assert!(data.get_transfers().is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::data::Split;
+
+// Largest rounding difference tolerated between the split lines and the total
+const TOLERANCE: f64 = 0.005;
+
+// Parse split lines written as "Food:40; Other:12.50:detergent" into splits.
+// Amounts are entered as positive numbers and get the sign of the transaction.
+pub fn parse(input: &str, negative: bool) -> Result<Vec<Split>, String> {
+    let mut splits = Vec::new();
+    for entry in input.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.splitn(3, ':').map(str::trim);
+        let category = parts.next().unwrap_or_default();
+        let amount = parts
+            .next()
+            .ok_or_else(|| format!("'{}' needs Category:Amount", entry))?;
+        let note = parts.next().unwrap_or_default();
+        if category.is_empty() {
+            return Err(format!("'{}' has no category", entry));
+        }
+        let amount = amount
+            .parse::<f64>()
+            .ok()
+            .filter(|amount| amount.is_finite())
+            .ok_or_else(|| format!("Invalid amount '{}'", amount))?
+            .abs();
+        splits.push(Split {
+            amount: if negative { -amount } else { amount },
+            category: category.to_string(),
+            note: note.to_string(),
+        });
+    }
+    Ok(splits)
+}
+
+// Part of `total` not yet covered by the split lines
+pub fn remaining(splits: &[Split], total: f64) -> f64 {
+    total.abs() - splits.iter().map(|s| s.amount.abs()).sum::<f64>()
+}
+
+pub fn is_balanced(splits: &[Split], total: f64) -> bool {
+    splits.is_empty() || remaining(splits, total).abs() < TOLERANCE
+}
+
+// Write splits back in the format accepted by `parse`
+pub fn format(splits: &[Split]) -> String {
+    splits
+        .iter()
+        .map(|s| {
+            if s.note.is_empty() {
+                format!("{}:{}", s.category, s.amount.abs())
+            } else {
+                format!("{}:{}:{}", s.category, s.amount.abs(), s.note)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_take_the_sign_of_the_transaction() {
+        let splits = parse("Food:40; Other:12.50:detergent;", true).unwrap();
+        assert_eq!(
+            splits,
+            vec![
+                Split {
+                    amount: -40.0,
+                    category: "Food".to_string(),
+                    note: String::new(),
+                },
+                Split {
+                    amount: -12.5,
+                    category: "Other".to_string(),
+                    note: "detergent".to_string(),
+                },
+            ]
+        );
+        assert!(is_balanced(&splits, -52.5));
+        assert!(!is_balanced(&splits, -60.0));
+        assert_eq!(remaining(&splits, -60.0), 7.5);
+        assert_eq!(parse(&format(&splits), true).unwrap(), splits);
+    }
+
+    #[test]
+    fn invalid_lines_are_refused() {
+        for input in [
+            "Food",
+            ":10",
+            "Food:ten",
+            "Food:NaN",
+            "Food:inf",
+            "Food:10; Other:-inf",
+        ] {
+            assert!(parse(input, true).is_err(), "{}", input);
+        }
+        assert!(parse("", true).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,532 @@
+use crate::data::{DataStructure, Transaction};
+use crate::storage::{Conflict, Query, Storage};
+use rusqlite::{params, Connection, Row, Transaction as Write, TransactionBehavior};
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::path::Path;
+
+// Transaction fields stored in their own columns; all other fields go to the `details` column
+const COLUMNS: [&str; 6] = ["id", "date", "amount", "category", "description", "payee"];
+const LISTS: [&str; 2] = ["expenses", "income"];
+const SELECT: &str = "id, date, amount, category, description, payee, details";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS transactions (
+    kind TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    date TEXT NOT NULL,
+    amount REAL NOT NULL,
+    category TEXT NOT NULL,
+    description TEXT NOT NULL,
+    payee TEXT NOT NULL,
+    details TEXT NOT NULL,
+    PRIMARY KEY (kind, id)
+);
+CREATE INDEX IF NOT EXISTS transactions_date ON transactions (date);
+CREATE INDEX IF NOT EXISTS transactions_category ON transactions (category);
+CREATE TABLE IF NOT EXISTS categories (
+    kind TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (kind, position)
+);
+CREATE TABLE IF NOT EXISTS documents (
+    name TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+// Ledger stored in a SQLite database. Transactions are written one row at a time instead of
+// rewriting the whole ledger; loans, goals, settings and the like are kept as small JSON documents.
+pub struct Database {
+    connection: Connection,
+    seen: i64, // SQLite's data_version when the ledger was last loaded or saved
+}
+
+impl Database {
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        let seen = data_version(&connection)?;
+        Ok(Self { connection, seen })
+    }
+
+    // Start a write, refusing it when another connection changed the database since it was
+    // last read. The check runs once the write lock is held, so no other commit can slip in
+    // between; data_version only moves on commits of other connections.
+    fn begin(&mut self) -> Result<Write<'_>, Box<dyn Error>> {
+        let seen = self.seen;
+        let write = self
+            .connection
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        if data_version(&write)? != seen {
+            return Err(Box::new(Conflict));
+        }
+        Ok(write)
+    }
+}
+
+impl Storage for Database {
+    fn load(&mut self) -> Result<Option<DataStructure>, Box<dyn Error>> {
+        self.seen = data_version(&self.connection)?;
+        let mut transactions = Map::new();
+        for kind in LISTS {
+            let mut statement = self.connection.prepare(&format!(
+                "SELECT {} FROM transactions WHERE kind = ?1 ORDER BY date, id",
+                SELECT
+            ))?;
+            let list = statement
+                .query_map([kind], read_transaction)?
+                .collect::<Result<Vec<Value>, _>>()?;
+            transactions.insert(kind.to_string(), Value::Array(list));
+
+            let mut statement = self
+                .connection
+                .prepare("SELECT name FROM categories WHERE kind = ?1 ORDER BY position")?;
+            let names = statement
+                .query_map([kind], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            transactions.insert(format!("{}_categories", category_kind(kind)), names.into());
+        }
+
+        let mut data = Map::new();
+        data.insert("transactions".to_string(), Value::Object(transactions));
+        let mut statement = self
+            .connection
+            .prepare("SELECT name, value FROM documents")?;
+        let documents = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for document in documents {
+            let (name, value) = document?;
+            data.insert(name, serde_json::from_str(&value)?);
+        }
+        // A database without documents has never been written to
+        if data.len() == 1 {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_value(Value::Object(data))?))
+    }
+
+    fn save(&mut self, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+        let transaction = self.begin()?;
+        transaction.execute("DELETE FROM transactions", [])?;
+        for (kind, list) in [
+            ("expenses", &data.transactions.expenses),
+            ("income", &data.transactions.income),
+        ] {
+            for t in list {
+                insert_transaction(&transaction, kind, t)?;
+            }
+        }
+        save_documents(&transaction, data)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn save_settings(&mut self, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+        let transaction = self.begin()?;
+        save_documents(&transaction, data)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn add(
+        &mut self,
+        data: &DataStructure,
+        kind: &str,
+        transaction: &Transaction,
+    ) -> Result<(), Box<dyn Error>> {
+        self.update(data, kind, transaction)
+    }
+
+    // The row and the undo history are written in one database transaction
+    fn update(
+        &mut self,
+        data: &DataStructure,
+        kind: &str,
+        transaction: &Transaction,
+    ) -> Result<(), Box<dyn Error>> {
+        let write = self.begin()?;
+        insert_transaction(&write, kind, transaction)?;
+        save_history(&write, data)?;
+        write.commit()?;
+        Ok(())
+    }
+
+    fn changed(&mut self) -> Result<bool, Box<dyn Error>> {
+        Ok(data_version(&self.connection)? != self.seen)
+    }
+
+    fn delete(&mut self, data: &DataStructure, kind: &str, id: u32) -> Result<(), Box<dyn Error>> {
+        let write = self.begin()?;
+        write.execute(
+            "DELETE FROM transactions WHERE kind = ?1 AND id = ?2",
+            params![kind, id],
+        )?;
+        save_history(&write, data)?;
+        write.commit()?;
+        Ok(())
+    }
+
+    // Uses the date and category indexes instead of scanning the ledger in memory
+    fn query(
+        &self,
+        _data: &DataStructure,
+        kind: &str,
+        query: &Query,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT {} FROM transactions WHERE kind = ?1
+             AND (?2 IS NULL OR date >= ?2) AND (?3 IS NULL OR date <= ?3)
+             AND (?4 IS NULL OR category = ?4) ORDER BY date, id",
+            SELECT
+        ))?;
+        let rows = statement.query_map(
+            params![
+                kind,
+                query.from.map(|d| d.to_string()),
+                query.until.map(|d| d.to_string()),
+                query.category
+            ],
+            read_transaction,
+        )?;
+        let mut transactions = Vec::new();
+        for row in rows {
+            transactions.push(serde_json::from_value(row?)?);
+        }
+        Ok(transactions)
+    }
+}
+
+fn data_version(connection: &Connection) -> rusqlite::Result<i64> {
+    connection.query_row("PRAGMA data_version", [], |row| row.get(0))
+}
+
+// Transaction row as the JSON object `Transaction` deserializes from
+fn read_transaction(row: &Row) -> rusqlite::Result<Value> {
+    let details: String = row.get(6)?;
+    let mut fields: Map<String, Value> = serde_json::from_str(&details).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(err))
+    })?;
+    fields.insert("id".to_string(), row.get::<_, u32>(0)?.into());
+    fields.insert("date".to_string(), row.get::<_, String>(1)?.into());
+    fields.insert("amount".to_string(), row.get::<_, f64>(2)?.into());
+    fields.insert("category".to_string(), row.get::<_, String>(3)?.into());
+    fields.insert("description".to_string(), row.get::<_, String>(4)?.into());
+    fields.insert("payee".to_string(), row.get::<_, String>(5)?.into());
+    Ok(Value::Object(fields))
+}
+
+// "expenses" -> "expense", matching the `expense_categories` field
+fn category_kind(kind: &str) -> &str {
+    match kind {
+        "expenses" => "expense",
+        other => other,
+    }
+}
+
+fn insert_transaction(
+    connection: &Connection,
+    kind: &str,
+    t: &Transaction,
+) -> Result<(), Box<dyn Error>> {
+    let mut details = match serde_json::to_value(t)? {
+        Value::Object(fields) => fields,
+        _ => Map::new(),
+    };
+    for column in COLUMNS {
+        details.remove(column);
+    }
+    connection.execute(
+        "INSERT INTO transactions (kind, id, date, amount, category, description, payee, details)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT (kind, id) DO UPDATE SET date = ?3, amount = ?4, category = ?5,
+         description = ?6, payee = ?7, details = ?8",
+        params![
+            kind,
+            t.id,
+            t.date.to_string(),
+            t.amount,
+            t.category,
+            t.description,
+            t.payee,
+            Value::Object(details).to_string()
+        ],
+    )?;
+    Ok(())
+}
+
+// The undo history, which changes with every transaction written
+fn save_history(connection: &Connection, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+    connection.execute(
+        "INSERT INTO documents (name, value) VALUES ('history', ?1)
+         ON CONFLICT (name) DO UPDATE SET value = ?1",
+        params![serde_json::to_string(&data.history)?],
+    )?;
+    Ok(())
+}
+
+// Categories and everything besides the transactions
+fn save_documents(connection: &Connection, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+    connection.execute("DELETE FROM categories", [])?;
+    for (kind, names) in [
+        ("expenses", &data.transactions.expense_categories),
+        ("income", &data.transactions.income_categories),
+    ] {
+        for (position, name) in names.iter().enumerate() {
+            connection.execute(
+                "INSERT INTO categories (kind, position, name) VALUES (?1, ?2, ?3)",
+                params![kind, position, name],
+            )?;
+        }
+    }
+
+    connection.execute("DELETE FROM documents", [])?;
+    if let Value::Object(fields) = serde_json::to_value(data)? {
+        for (name, value) in fields
+            .into_iter()
+            .filter(|(name, _)| name != "transactions")
+        {
+            connection.execute(
+                "INSERT INTO documents (name, value) VALUES (?1, ?2)",
+                params![name, value.to_string()],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Bill, Data, Loan, Share, Shared, Split, Status, Trade, TradeKind};
+    use crate::storage::Memory;
+    use chrono::NaiveDate;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn stores_transactions_row_by_row() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut database = Database::open(&path).unwrap();
+        assert!(database.load().unwrap().is_none());
+
+        let mut data = DataStructure::default();
+        database.save(&data).unwrap();
+        let mut expense = Transaction {
+            id: 1,
+            amount: -30.0,
+            category: "Food".to_string(),
+            date: NaiveDate::from_ymd_opt(2026, 5, 1).unwrap(),
+            tags: vec!["party".to_string()],
+            ..Default::default()
+        };
+        data.transactions.expenses.push(expense.clone());
+        database.add(&data, "expenses", &expense).unwrap();
+        expense.amount = -35.0;
+        database.update(&data, "expenses", &expense).unwrap();
+
+        let found = database
+            .query(
+                &data,
+                "expenses",
+                &Query {
+                    category: Some("Food".to_string()),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].amount, -35.0);
+        assert_eq!(found[0].tags, vec!["party".to_string()]);
+
+        let loaded = database.load().unwrap().unwrap();
+        assert_eq!(loaded.transactions.expenses.len(), 1);
+        assert_eq!(
+            loaded.transactions.expense_categories,
+            data.transactions.expense_categories
+        );
+
+        database.delete(&data, "expenses", 1).unwrap();
+        let loaded = database.load().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.transactions.expenses.is_empty());
+    }
+
+    #[test]
+    fn loads_transactions_by_date_whatever_order_they_were_written_in() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-order.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut database = Database::open(&path).unwrap();
+        let data = DataStructure::default();
+        database.save(&data).unwrap();
+        let early = Transaction {
+            id: 1,
+            amount: -10.0,
+            date: date("2026-05-01"),
+            ..Default::default()
+        };
+        let late = Transaction {
+            id: 2,
+            amount: -20.0,
+            date: date("2026-05-20"),
+            ..Default::default()
+        };
+        database.add(&data, "expenses", &early).unwrap();
+        database.add(&data, "expenses", &late).unwrap();
+        // Undoing a delete writes the row again after the ones that stayed
+        database.delete(&data, "expenses", 1).unwrap();
+        database.add(&data, "expenses", &early).unwrap();
+
+        let loaded = database.load().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let ids: Vec<u32> = loaded.transactions.expenses.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn notices_and_refuses_writes_after_another_connection_wrote() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-two.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let data = DataStructure::default();
+        let mut first = Database::open(&path).unwrap();
+        first.save(&data).unwrap();
+        let mut second = Database::open(&path).unwrap();
+        second.load().unwrap();
+
+        assert!(!second.changed().unwrap());
+        first.save_settings(&data).unwrap();
+        assert!(second.changed().unwrap());
+        let err = second.save_settings(&data).unwrap_err();
+        assert!(err.is::<Conflict>());
+        second.load().unwrap();
+        second.save_settings(&data).unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn round_trips_a_full_ledger() {
+        // Fill every part of the ledger, including the change log and the undo history
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        let expense = Transaction {
+            id: 1,
+            amount: -90.0,
+            category: "Food".to_string(),
+            date: date("2026-05-01"),
+            description: "Groceries and a gift".to_string(),
+            payee: "Market".to_string(),
+            tags: vec!["party".to_string()],
+            deductible: true,
+            tax_code: Some("X1".to_string()),
+            splits: vec![
+                Split {
+                    amount: -60.0,
+                    category: "Food".to_string(),
+                    note: String::new(),
+                },
+                Split {
+                    amount: -30.0,
+                    category: "Gifts".to_string(),
+                    note: "Birthday".to_string(),
+                },
+            ],
+            shared: Some(Shared {
+                paid_by: "Alex".to_string(),
+                shares: vec![Share {
+                    person: "Sam".to_string(),
+                    amount: 45.0,
+                }],
+            }),
+            ..Default::default()
+        };
+        data.add_transaction(expense.clone(), "expenses").unwrap();
+        data.update_transaction(
+            Transaction {
+                status: Status::Cleared,
+                ..expense
+            },
+            "expenses",
+        )
+        .unwrap();
+        data.add_loan(Loan {
+            id: 0,
+            name: "Car".to_string(),
+            kind: "car".to_string(),
+            principal: 10000.0,
+            rate: 3.5,
+            term_months: 48,
+            start: date("2026-01-15"),
+            payment_day: 15,
+        })
+        .unwrap();
+        data.add_trade(Trade {
+            id: 0,
+            kind: TradeKind::Buy,
+            symbol: "ABC".to_string(),
+            date: date("2026-02-01"),
+            quantity: 3.0,
+            price: 25.5,
+        })
+        .unwrap();
+        data.add_bill(Bill {
+            id: 0,
+            payee: "Landlord".to_string(),
+            category: "Housing".to_string(),
+            amount: 900.0,
+            first_due: date("2026-06-01"),
+            every_months: 1,
+            paid: vec![date("2026-06-01")],
+        })
+        .unwrap();
+        let mut storage = data.into_storage();
+        let ledger = storage.load().unwrap().unwrap();
+
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-full.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Database::open(&path).unwrap().save(&ledger).unwrap();
+        let loaded = Database::open(&path).unwrap().load().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&ledger).unwrap()
+        );
+        assert_eq!(loaded.transactions.expenses[0].changes.len(), 2);
+        assert_eq!(loaded.history.undo.len(), 5);
+    }
+
+    #[test]
+    fn transactions_are_written_with_their_history() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-hist.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut data = Data::with_storage(Box::new(Database::open(&path).unwrap())).unwrap();
+        let expense = Transaction {
+            id: 1,
+            amount: -30.0,
+            category: "Food".to_string(),
+            date: date("2026-05-01"),
+            ..Default::default()
+        };
+        data.add_transaction(expense.clone(), "expenses").unwrap();
+        data.update_transaction(
+            Transaction {
+                amount: -35.0,
+                ..expense
+            },
+            "expenses",
+        )
+        .unwrap();
+        data.undo().unwrap();
+        data.undo().unwrap();
+
+        // Each step stored the row and the history together
+        let loaded = Database::open(&path).unwrap().load().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.transactions.expenses.is_empty());
+        assert!(loaded.history.undo.is_empty());
+        assert_eq!(loaded.history.redo.len(), 2);
+    }
+}
@@ -0,0 +1,823 @@
+use crate::crypto::{self, Key};
+use crate::data::{DataStructure, Transaction};
+#[cfg(feature = "sqlite")]
+use crate::sqlite;
+use chrono::NaiveDate;
+use std::collections::hash_map::DefaultHasher;
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+// Age after which a lock file is taken to be left behind by a crashed program; saving takes
+// milliseconds
+const STALE_LOCK: Duration = Duration::from_secs(10);
+// How long saving waits for another program to finish writing the same ledger
+const LOCK_WAIT: Duration = Duration::from_secs(1);
+
+// Where a ledger is kept. `Data` holds the working copy in memory and tells the storage about
+// every change, so backends can write just the affected records. Each change is written in one
+// go, so it is either stored completely or not at all. Backends that can only store the ledger
+// as a whole get that behavior from the default methods. Writes fail with `Conflict`
+// when another program changed the stored ledger since this one loaded or saved it.
+pub trait Storage {
+    // Read the stored ledger, None when nothing has been stored yet
+    fn load(&mut self) -> Result<Option<DataStructure>, Box<dyn Error>>;
+
+    // Write the whole ledger
+    fn save(&mut self, data: &DataStructure) -> Result<(), Box<dyn Error>>;
+
+    // Write the categories, settings and smaller collections after one of them changed
+    fn save_settings(&mut self, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+        self.save(data)
+    }
+
+    // Store a transaction just added to the "expenses" or "income" list of `data`, together with
+    // the undo history of `data`
+    fn add(
+        &mut self,
+        data: &DataStructure,
+        _kind: &str,
+        _transaction: &Transaction,
+    ) -> Result<(), Box<dyn Error>> {
+        self.save(data)
+    }
+
+    // Store a transaction just changed in `data`, together with the undo history
+    fn update(
+        &mut self,
+        data: &DataStructure,
+        _kind: &str,
+        _transaction: &Transaction,
+    ) -> Result<(), Box<dyn Error>> {
+        self.save(data)
+    }
+
+    // Forget a transaction just removed from `data`, storing the undo history with it
+    fn delete(
+        &mut self,
+        data: &DataStructure,
+        _kind: &str,
+        _id: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.save(data)
+    }
+
+    // Whether another program changed the stored ledger since it was last loaded or saved
+    fn changed(&mut self) -> Result<bool, Box<dyn Error>> {
+        Ok(false)
+    }
+
+    // Transactions of the "expenses" or "income" list matching `query`
+    fn query(
+        &self,
+        data: &DataStructure,
+        kind: &str,
+        query: &Query,
+    ) -> Result<Vec<Transaction>, Box<dyn Error>> {
+        let list = match kind {
+            "income" => &data.transactions.income,
+            _ => &data.transactions.expenses,
+        };
+        Ok(list.iter().filter(|t| query.matches(t)).cloned().collect())
+    }
+}
+
+// Error of a write refused because another program changed the stored ledger since it was
+// loaded; writing would silently throw those changes away
+#[derive(Debug)]
+pub struct Conflict;
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Another FinTrackR or another program changed the ledger; the change was not saved and the latest ledger was loaded"
+        )
+    }
+}
+
+impl Error for Conflict {}
+
+// Filter for `Storage::query`; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    pub from: Option<NaiveDate>,  // First date included
+    pub until: Option<NaiveDate>, // Last date included
+    pub category: Option<String>, // Category of the transaction itself, ignoring split lines
+}
+
+impl Query {
+    pub fn matches(&self, transaction: &Transaction) -> bool {
+        self.from.is_none_or(|from| transaction.date >= from)
+            && self.until.is_none_or(|until| transaction.date <= until)
+            && self
+                .category
+                .as_ref()
+                .is_none_or(|category| &transaction.category == category)
+    }
+}
+
+// Ledger kept as a single JSON file, rewritten on every change and optionally encrypted
+pub struct JsonFile {
+    path: String,
+    key: Option<Key>,  // Key the file is encrypted with, None for plain JSON
+    seen: Option<u64>, // Fingerprint of the contents last loaded or saved, None if there was no file
+    checked: Option<(SystemTime, u64)>, // Modification time and size when last read, written or compared, None if there was no file
+}
+
+impl JsonFile {
+    pub fn new(path: &str) -> Self {
+        Self::with_key(path, None)
+    }
+
+    // JSON file encrypted with `key`, or plain JSON when `key` is None
+    pub fn with_key(path: &str, key: Option<Key>) -> Self {
+        Self {
+            path: path.to_string(),
+            key,
+            seen: None,
+            checked: None,
+        }
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    // Encrypt the file with `key` from the next save on, or store plain JSON when it is None
+    pub fn set_key(&mut self, key: Option<Key>) {
+        self.key = key;
+    }
+
+    // Modification time and size of the file, None when it doesn't exist
+    fn metadata(&self) -> Option<(SystemTime, u64)> {
+        let metadata = fs::metadata(&self.path).ok()?;
+        Some((metadata.modified().ok()?, metadata.len()))
+    }
+}
+
+impl Storage for JsonFile {
+    fn load(&mut self) -> Result<Option<DataStructure>, Box<dyn Error>> {
+        self.checked = self.metadata();
+        if !Path::new(&self.path).exists() {
+            self.seen = None;
+            return Ok(None);
+        }
+        let contents = fs::read(&self.path)?;
+        self.seen = Some(fingerprint(&contents));
+        let json = match &self.key {
+            Some(key) => key.decrypt(&contents)?,
+            None if crypto::is_encrypted(&contents) => {
+                return Err(format!("{} is encrypted and needs its passphrase", self.path).into())
+            }
+            None => contents,
+        };
+        Ok(Some(serde_json::from_slice(&json)?))
+    }
+
+    // Holds the lock file from the conflict check until the new file is in place, so two
+    // FinTrackR instances can't both pass the check and overwrite each other. Programs that
+    // ignore the lock, such as sync tools or editors, can still replace the file in the
+    // moment between the check and the rename; their change is then lost.
+    fn save(&mut self, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+        let _lock = FileLock::acquire(&self.path)?;
+        let on_disk = fs::read(&self.path)
+            .ok()
+            .map(|contents| fingerprint(&contents));
+        if on_disk != self.seen {
+            return Err(Box::new(Conflict));
+        }
+        let json = serde_json::to_vec(data)?;
+        let contents = match &self.key {
+            Some(key) => key.encrypt(&json)?,
+            None => json,
+        };
+        // Write a copy first so a crash never leaves a half-written ledger behind
+        let temporary = format!("{}.tmp", self.path);
+        fs::write(&temporary, &contents)?;
+        fs::rename(&temporary, &self.path)?;
+        self.seen = Some(fingerprint(&contents));
+        self.checked = self.metadata();
+        Ok(())
+    }
+
+    // Only reads the file again when its modification time or size moved, or it appeared or
+    // disappeared; a deleted file is reported once
+    fn changed(&mut self) -> Result<bool, Box<dyn Error>> {
+        let metadata = self.metadata();
+        if metadata == self.checked {
+            return Ok(false);
+        }
+        self.checked = metadata;
+        let on_disk = fs::read(&self.path)
+            .ok()
+            .map(|contents| fingerprint(&contents));
+        Ok(on_disk != self.seen)
+    }
+}
+
+// Advisory lock next to a file (`<path>.lock`), removed again when dropped
+struct FileLock {
+    path: String,
+}
+
+impl FileLock {
+    // Create the lock file, waiting a moment while another program holds it and taking over
+    // a lock that has gone stale
+    fn acquire(path: &str) -> Result<Self, Box<dyn Error>> {
+        let path = format!("{}.lock", path);
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                    let age = fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok());
+                    if age.is_some_and(|age| age > STALE_LOCK) {
+                        let _ = fs::remove_file(&path);
+                    } else if started.elapsed() > LOCK_WAIT {
+                        return Err(format!(
+                            "Another program is saving the ledger (remove {} if none is)",
+                            path
+                        )
+                        .into());
+                    } else {
+                        thread::sleep(Duration::from_millis(20));
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Hash of file contents, to notice when another program rewrote the file
+fn fingerprint(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+// Ledger that lives only as long as the program, for tests
+#[cfg(test)]
+#[derive(Default)]
+pub struct Memory {
+    data: Option<DataStructure>,
+}
+
+#[cfg(test)]
+impl Memory {
+    pub fn new(data: DataStructure) -> Self {
+        Self { data: Some(data) }
+    }
+}
+
+#[cfg(test)]
+impl Storage for Memory {
+    fn load(&mut self) -> Result<Option<DataStructure>, Box<dyn Error>> {
+        Ok(self.data.clone())
+    }
+
+    fn save(&mut self, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+        self.data = Some(data.clone());
+        Ok(())
+    }
+}
+
+// Storage for the ledger file at `path`: the SQLite database next to it once the ledger has
+// been migrated (with the sqlite feature), otherwise the JSON file itself
+pub fn open(path: &str) -> Result<Box<dyn Storage>, Box<dyn Error>> {
+    #[cfg(feature = "sqlite")]
+    {
+        let database_path = Path::new(path).with_extension("db");
+        if database_path.exists() {
+            return Ok(Box::new(sqlite::Database::open(&database_path)?));
+        }
+    }
+    Ok(Box::new(open_json(path)?))
+}
+
+// The JSON ledger file at `path`, asking for the passphrase if it is encrypted
+pub fn open_json(path: &str) -> Result<JsonFile, Box<dyn Error>> {
+    #[cfg(feature = "sqlite")]
+    if Path::new(path).with_extension("db").exists() {
+        return Err("The ledger has moved into a SQLite database, which can't be encrypted".into());
+    }
+    let mut file = JsonFile::new(path);
+    if is_encrypted(path) {
+        file.set_key(Some(crypto::unlock(&fs::read(path)?)?));
+    }
+    Ok(file)
+}
+
+// Whether the ledger file at `path` is encrypted
+pub fn is_encrypted(path: &str) -> bool {
+    fs::read(path).is_ok_and(|contents| crypto::is_encrypted(&contents))
+}
+
+// Move the JSON ledger at `path` into a new SQLite database next to it. The JSON file is
+// renamed so it is not picked up again; returns the database path.
+#[cfg(feature = "sqlite")]
+pub fn migrate_to_sqlite(path: &str) -> Result<std::path::PathBuf, Box<dyn Error>> {
+    let database_path = Path::new(path).with_extension("db");
+    if database_path.exists() {
+        return Err(format!("{} already exists", database_path.display()).into());
+    }
+    if is_encrypted(path) {
+        return Err(
+            "The database would not be encrypted; run `fintrackr encrypt off` first".into(),
+        );
+    }
+    let data = JsonFile::new(path)
+        .load()?
+        .ok_or_else(|| format!("{} not found", path))?;
+    let mut database = sqlite::Database::open(&database_path)?;
+    if let Err(err) = database.save(&data) {
+        drop(database);
+        let _ = fs::remove_file(&database_path);
+        return Err(err);
+    }
+    fs::rename(path, format!("{}.migrated", path))?;
+    Ok(database_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{Data, Goal, Source, Split, Status};
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn expense(id: u32, date: &str, category: &str, amount: f64) -> Transaction {
+        Transaction {
+            id,
+            amount,
+            category: category.to_string(),
+            date: NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap(),
+            description: format!("Expense {}", id),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn memory_starts_empty_and_keeps_saved_data() {
+        let mut memory = Memory::default();
+        assert!(memory.load().unwrap().is_none());
+        let mut data = DataStructure::default();
+        data.transactions
+            .expenses
+            .push(expense(1, "2026-01-05", "Food", -12.5));
+        memory.save(&data).unwrap();
+        let loaded = memory.load().unwrap().unwrap();
+        assert_eq!(loaded.transactions.expenses.len(), 1);
+        assert_eq!(loaded.transactions.expenses[0].amount, -12.5);
+    }
+
+    #[test]
+    fn data_writes_changes_through_to_the_storage() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        for (date, category, amount) in [
+            ("2026-01-05", "Food", -20.0),
+            ("2026-02-05", "Bills", -80.0),
+        ] {
+            let id = data.next_transaction_id("expenses");
+            data.add_transaction(expense(id, date, category, amount), "expenses")
+                .unwrap();
+        }
+        let ids: Vec<u32> = data
+            .get_transactions()
+            .expenses
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+
+        // Undoing the second addition deletes it again
+        data.undo().unwrap();
+        let mut changed = data.get_transactions().expenses[0].clone();
+        changed.amount = -25.0;
+        data.update_transaction(changed, "expenses").unwrap();
+
+        let reloaded = Data::with_storage(data.into_storage()).unwrap();
+        let expenses = &reloaded.get_transactions().expenses;
+        assert_eq!(expenses.len(), 1);
+        assert_eq!(expenses[0].amount, -25.0);
+    }
+
+    // Memory storage counting how often the whole ledger was written
+    struct Counting {
+        memory: Memory,
+        saves: Rc<Cell<usize>>,
+    }
+
+    impl Storage for Counting {
+        fn load(&mut self) -> Result<Option<DataStructure>, Box<dyn Error>> {
+            self.memory.load()
+        }
+
+        fn save(&mut self, data: &DataStructure) -> Result<(), Box<dyn Error>> {
+            self.saves.set(self.saves.get() + 1);
+            self.memory.save(data)
+        }
+    }
+
+    #[test]
+    fn single_transaction_edits_write_the_ledger_once() {
+        let saves = Rc::new(Cell::new(0));
+        let mut data = Data::with_storage(Box::new(Counting {
+            memory: Memory::new(DataStructure::default()),
+            saves: saves.clone(),
+        }))
+        .unwrap();
+        data.add_transaction(expense(1, "2026-01-05", "Food", -20.0), "expenses")
+            .unwrap();
+        assert_eq!(saves.get(), 1);
+        let mut changed = data.get_transactions().expenses[0].clone();
+        changed.amount = -25.0;
+        data.update_transaction(changed, "expenses").unwrap();
+        data.undo().unwrap();
+        assert_eq!(saves.get(), 3);
+    }
+
+    #[test]
+    fn reconciled_transactions_are_locked() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        data.add_transaction(expense(1, "2026-01-05", "Food", -20.0), "expenses")
+            .unwrap();
+        let mut cleared = data.get_transactions().expenses[0].clone();
+        cleared.status = Status::Cleared;
+        data.update_transaction(cleared, "expenses").unwrap();
+        assert_eq!(
+            data.reconcile(NaiveDate::from_ymd_opt(2026, 1, 31).unwrap())
+                .unwrap(),
+            1
+        );
+
+        let mut changed = data.get_transactions().expenses[0].clone();
+        changed.flagged = true;
+        assert!(data
+            .update_transaction(changed.clone(), "expenses")
+            .is_err());
+        let stored = &data.get_transactions().expenses[0];
+        assert_eq!(stored.status, Status::Reconciled);
+        assert!(!stored.flagged);
+
+        // Undoing the reconciliation unlocks it again
+        data.undo().unwrap();
+        data.update_transaction(changed, "expenses").unwrap();
+        assert!(data.get_transactions().expenses[0].flagged);
+    }
+
+    #[test]
+    fn pending_transactions_are_posted_without_writing_on_load() {
+        let mut stored = DataStructure::default();
+        let mut due = expense(1, "2026-01-05", "Food", -20.0);
+        due.pending = true;
+        stored.transactions.expenses = vec![due, expense(2, "2099-01-05", "Bills", -80.0)];
+        let data = Data::with_storage(Box::new(Memory::new(stored))).unwrap();
+        let pending: Vec<bool> = data
+            .get_transactions()
+            .expenses
+            .iter()
+            .map(|t| t.pending)
+            .collect();
+        assert_eq!(pending, vec![false, true]);
+
+        // Loading alone left the stored ledger as it was
+        let mut storage = data.into_storage();
+        let unchanged = storage.load().unwrap().unwrap();
+        assert!(unchanged.transactions.expenses[0].pending);
+        assert!(!unchanged.transactions.expenses[1].pending);
+
+        // Later on the flags are checked again and stored when one changed
+        let mut data = Data::with_storage(storage).unwrap();
+        let today = NaiveDate::from_ymd_opt(2099, 1, 5).unwrap();
+        assert_eq!(data.update_pending(today).unwrap(), 1);
+        assert_eq!(data.update_pending(today).unwrap(), 0);
+        let stored = data.into_storage().load().unwrap().unwrap();
+        assert!(stored.transactions.expenses.iter().all(|t| !t.pending));
+        assert!(Data::with_storage(Box::new(Memory::new(stored)))
+            .unwrap()
+            .get_history()
+            .undo
+            .is_empty());
+    }
+
+    #[test]
+    fn undo_and_redo_survive_reloading() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        for (date, amount) in [("2026-01-05", -20.0), ("2026-01-06", -30.0)] {
+            let id = data.next_transaction_id("expenses");
+            data.add_transaction(expense(id, date, "Food", amount), "expenses")
+                .unwrap();
+        }
+        let mut changed = data.get_transactions().expenses[0].clone();
+        changed.amount = -25.0;
+        data.update_transaction(changed, "expenses").unwrap();
+        data.update_tax_settings(Default::default()).unwrap();
+
+        let mut data = Data::with_storage(data.into_storage()).unwrap();
+        assert_eq!(
+            data.undo().unwrap().as_deref(),
+            Some("Changed the tax settings")
+        );
+        assert_eq!(
+            data.undo().unwrap().as_deref(),
+            Some("Edited expense 'Expense 1' (-25.00)")
+        );
+        assert_eq!(data.get_transactions().expenses[0].amount, -20.0);
+
+        let mut data = Data::with_storage(data.into_storage()).unwrap();
+        assert_eq!(data.get_history().redo.len(), 2);
+        data.redo().unwrap();
+        assert_eq!(data.get_transactions().expenses[0].amount, -25.0);
+        // A new change drops what could be redone
+        data.add_goal(Goal {
+            id: 0,
+            name: "Trip".to_string(),
+            target: 500.0,
+            start: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+            due: NaiveDate::from_ymd_opt(2026, 6, 30).unwrap(),
+            tag: "goal:trip".to_string(),
+            symbol: None,
+            starting_amount: 0.0,
+        })
+        .unwrap();
+        assert!(data.redo().unwrap().is_none());
+        data.undo().unwrap();
+        assert!(data.get_goals().is_empty());
+    }
+
+    #[test]
+    fn deleting_can_be_undone_unless_refunds_point_to_the_expense() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        data.add_transaction(expense(1, "2026-01-05", "Food", -20.0), "expenses")
+            .unwrap();
+        data.add_transaction(expense(2, "2026-01-06", "Bills", -80.0), "expenses")
+            .unwrap();
+        let mut refund = expense(3, "2026-01-07", "Food", 5.0);
+        refund.refund_of = Some(1);
+        data.add_transaction(refund, "expenses").unwrap();
+        assert!(data.delete_transaction(1, "expenses").is_err());
+        assert!(data.delete_transaction(9, "expenses").is_err());
+
+        data.delete_transaction(2, "expenses").unwrap();
+        let mut data = Data::with_storage(data.into_storage()).unwrap();
+        assert_eq!(data.get_transactions().expenses.len(), 2);
+        assert_eq!(
+            data.undo().unwrap().as_deref(),
+            Some("Deleted expense 'Expense 2' (-80.00)")
+        );
+        let ids: Vec<u32> = data
+            .get_transactions()
+            .expenses
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        let stored = data.into_storage().load().unwrap().unwrap();
+        assert_eq!(stored.transactions.expenses.len(), 3);
+    }
+
+    #[test]
+    fn recategorizing_is_one_undoable_step_that_skips_reconciled_rows() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        let mut split = expense(1, "2026-01-05", "Bills", -30.0);
+        split.splits = vec![
+            Split {
+                amount: -10.0,
+                category: "Food".to_string(),
+                note: String::new(),
+            },
+            Split {
+                amount: -20.0,
+                category: "Bills".to_string(),
+                note: String::new(),
+            },
+        ];
+        data.add_transaction(split, "expenses").unwrap();
+        data.add_transaction(expense(2, "2026-01-06", "Food", -20.0), "expenses")
+            .unwrap();
+        let mut locked = expense(3, "2026-01-07", "Food", -5.0);
+        locked.status = Status::Reconciled;
+        data.add_transaction(locked, "expenses").unwrap();
+        assert!(data.recategorize("expenses", "Food", "Nowhere").is_err());
+
+        assert_eq!(data.recategorize("expenses", "Food", "Other").unwrap(), 2);
+        let categories = |data: &Data| -> Vec<String> {
+            data.get_transactions()
+                .expenses
+                .iter()
+                .map(|t| t.category.clone())
+                .collect()
+        };
+        assert_eq!(categories(&data), vec!["Bills", "Other", "Food"]);
+        assert_eq!(
+            data.get_transactions().expenses[0].splits[0].category,
+            "Other"
+        );
+
+        let mut data = Data::with_storage(data.into_storage()).unwrap();
+        assert_eq!(
+            data.undo().unwrap().as_deref(),
+            Some("Moved 2 transactions from Food to Other")
+        );
+        assert_eq!(categories(&data), vec!["Bills", "Food", "Food"]);
+        assert_eq!(
+            data.get_transactions().expenses[0].splits[0].category,
+            "Food"
+        );
+    }
+
+    #[test]
+    fn imports_are_logged_as_such_and_undone_in_one_step() {
+        let mut data = Data::with_storage(Box::new(Memory::default())).unwrap();
+        data.add_transaction(expense(1, "2026-01-05", "Food", -20.0), "expenses")
+            .unwrap();
+        let imported = vec![
+            expense(0, "2026-01-06", "Food", -12.0),
+            expense(0, "2026-01-07", "Bills", -80.0),
+        ];
+        assert_eq!(data.import_transactions(imported, "expenses").unwrap(), 2);
+        let expenses = &data.get_transactions().expenses;
+        let ids: Vec<u32> = expenses.iter().map(|t| t.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(expenses[1].changes[0].source, Source::Import);
+        assert!(expenses[1].created_at.is_some());
+        // Later changes are logged with the usual source again
+        let mut changed = expenses[1].clone();
+        changed.amount = -13.0;
+        data.update_transaction(changed, "expenses").unwrap();
+        assert_eq!(
+            data.get_transactions().expenses[1].changes[1].source,
+            Source::Cli
+        );
+
+        let mut data = Data::with_storage(data.into_storage()).unwrap();
+        data.undo().unwrap();
+        assert_eq!(data.undo().unwrap().as_deref(), Some("Imported 2 expenses"));
+        assert_eq!(data.get_transactions().expenses.len(), 1);
+    }
+
+    #[test]
+    fn query_filters_by_date_and_category() {
+        let mut data = DataStructure::default();
+        data.transactions.expenses = vec![
+            expense(1, "2026-01-05", "Food", -10.0),
+            expense(2, "2026-01-20", "Bills", -50.0),
+            expense(3, "2026-02-01", "Food", -15.0),
+        ];
+        let memory = Memory::new(data.clone());
+        let january_food = Query {
+            from: NaiveDate::from_ymd_opt(2026, 1, 1),
+            until: NaiveDate::from_ymd_opt(2026, 1, 31),
+            category: Some("Food".to_string()),
+        };
+        let found = memory.query(&data, "expenses", &january_food).unwrap();
+        assert_eq!(found.iter().map(|t| t.id).collect::<Vec<_>>(), vec![1]);
+        assert!(memory
+            .query(&data, "income", &Query::default())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn json_file_round_trips() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut file = JsonFile::new(path);
+        assert!(file.load().unwrap().is_none());
+        let mut data = DataStructure::default();
+        data.transactions
+            .expenses
+            .push(expense(1, "2026-03-01", "Health", -40.0));
+        file.save(&data).unwrap();
+        let loaded = file.load().unwrap().unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded.transactions.expenses[0].category, "Health");
+        assert_eq!(
+            loaded.transactions.expense_categories,
+            data.transactions.expense_categories
+        );
+    }
+
+    #[test]
+    fn deleted_json_file_is_noticed_once() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-del.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut file = JsonFile::new(path);
+        file.save(&DataStructure::default()).unwrap();
+        assert!(!file.changed().unwrap());
+        fs::remove_file(path).unwrap();
+        assert!(file.changed().unwrap());
+        assert!(!file.changed().unwrap());
+        assert!(!file.changed().unwrap());
+    }
+
+    #[test]
+    fn encrypted_json_file_needs_the_key() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-enc.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let params = argon2::Params::new(8, 1, 1, Some(32)).unwrap();
+        let mut file = JsonFile::with_key(path, Some(Key::with_params("secret", params).unwrap()));
+        let mut data = DataStructure::default();
+        data.transactions
+            .expenses
+            .push(expense(1, "2026-03-01", "Health", -40.0));
+        file.save(&data).unwrap();
+        assert!(is_encrypted(path));
+        assert!(JsonFile::new(path).load().is_err());
+        let loaded = file.load().unwrap().unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded.transactions.expenses[0].category, "Health");
+    }
+
+    #[test]
+    fn saving_waits_for_the_lock_file_unless_it_is_stale() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-lock.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let lock = format!("{}.lock", path);
+        let _ = fs::remove_file(path);
+        let mut file = JsonFile::new(path);
+        file.save(&DataStructure::default()).unwrap();
+        assert!(!Path::new(&lock).exists());
+
+        // Another program is writing
+        let held = fs::File::create(&lock).unwrap();
+        let err = file.save(&DataStructure::default()).unwrap_err();
+        assert!(!err.is::<Conflict>());
+        assert!(Path::new(&lock).exists());
+
+        // The program crashed long ago
+        held.set_modified(SystemTime::now() - 2 * STALE_LOCK)
+            .unwrap();
+        file.save(&DataStructure::default()).unwrap();
+        assert!(!Path::new(&lock).exists());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn second_instance_is_refused_and_reloads() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-two.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = fs::remove_file(path);
+        let mut first = Data::with_storage(Box::new(JsonFile::new(path))).unwrap();
+        let mut second = Data::with_storage(Box::new(JsonFile::new(path))).unwrap();
+
+        first
+            .add_transaction(expense(1, "2026-03-01", "Food", -10.0), "expenses")
+            .unwrap();
+        let err = second
+            .add_transaction(expense(1, "2026-03-02", "Bills", -99.0), "expenses")
+            .unwrap_err();
+        assert!(err.is::<Conflict>());
+        // The refused change is gone and the first instance's change shows up instead
+        let expenses = &second.get_transactions().expenses;
+        assert_eq!(expenses.len(), 1);
+        assert_eq!(expenses[0].category, "Food");
+
+        second
+            .add_transaction(expense(2, "2026-03-02", "Bills", -99.0), "expenses")
+            .unwrap();
+        let reloaded = Data::new(path).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(reloaded.get_transactions().expenses.len(), 2);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn migrating_moves_the_ledger_into_a_database() {
+        let path = std::env::temp_dir().join(format!("fintrackr-{}-mig.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let migrated = format!("{}.migrated", path);
+        let mut data = DataStructure::default();
+        data.transactions
+            .expenses
+            .push(expense(1, "2026-03-01", "Health", -40.0));
+        JsonFile::new(path).save(&data).unwrap();
+
+        let database_path = migrate_to_sqlite(path).unwrap();
+        let loaded = sqlite::Database::open(&database_path)
+            .unwrap()
+            .load()
+            .unwrap()
+            .unwrap();
+        fs::remove_file(&database_path).unwrap();
+        fs::remove_file(&migrated).unwrap();
+        assert!(!Path::new(path).exists());
+        assert_eq!(loaded.transactions.expenses[0].category, "Health");
+    }
+}
@@ -0,0 +1,64 @@
+use crate::data::Transaction;
+use std::collections::{BTreeSet, HashMap};
+
+// Characters allowed in a tag besides letters and digits
+const TAG_PUNCTUATION: [char; 4] = ['-', '_', ':', '/'];
+
+// Split user input like "vacation-2026, kids" into tags, dropping duplicates
+pub fn parse(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|t| t.trim().trim_start_matches('#'))
+        .filter(|t| !t.is_empty())
+    {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+pub fn is_valid(tag: &str) -> bool {
+    !tag.is_empty()
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || TAG_PUNCTUATION.contains(&c))
+}
+
+// Every tag used so far, sorted by name
+pub fn all_tags<'a>(transactions: impl Iterator<Item = &'a Transaction>) -> Vec<String> {
+    transactions
+        .flat_map(|t| t.tags.iter().cloned())
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect()
+}
+
+// Known tags starting with `prefix` (ignoring case), most frequently used first
+pub fn suggestions<'a>(
+    transactions: impl Iterator<Item = &'a Transaction>,
+    prefix: &str,
+) -> Vec<String> {
+    let prefix = prefix.trim().trim_start_matches('#').to_lowercase();
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for tag in transactions.flat_map(|t| t.tags.iter()) {
+        if tag.to_lowercase().starts_with(&prefix) {
+            *counts.entry(tag.as_str()).or_insert(0) += 1;
+        }
+    }
+    let mut tags: Vec<(&str, usize)> = counts.into_iter().collect();
+    tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    tags.into_iter().map(|(t, _)| t.to_string()).collect()
+}
+
+// The tag currently being typed, i.e. the text after the last separator
+pub fn current_token(input: &str) -> &str {
+    input
+        .rsplit(|c: char| c == ',' || c.is_whitespace())
+        .next()
+        .unwrap_or("")
+}
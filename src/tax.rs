@@ -0,0 +1,277 @@
+use crate::data::{TaxSettings, Transaction, Transactions};
+use chrono::{Datelike, Days, NaiveDate};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Whether a line reduces or adds to the taxable amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TaxKind {
+    Deductible,
+    Taxable,
+}
+
+impl fmt::Display for TaxKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaxKind::Deductible => write!(f, "deductible"),
+            TaxKind::Taxable => write!(f, "taxable"),
+        }
+    }
+}
+
+// A single transaction relevant for the tax return
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxLine {
+    pub kind: TaxKind,
+    pub code: String, // Tax code, empty when none was configured
+    pub date: NaiveDate,
+    pub category: String,
+    pub description: String,
+    pub amount: f64, // Positive, except for refunds of deductible expenses
+}
+
+// Total of all lines sharing a kind and tax code
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaxTotal {
+    pub kind: TaxKind,
+    pub code: String,
+    pub count: usize,
+    pub total: f64,
+}
+
+// First day of the fiscal year starting in the calendar year `year`. A start on February 29
+// moves to March 1 in years without one.
+fn fiscal_year_start(settings: &TaxSettings, year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, settings.fiscal_year_start_month, 1)
+        .and_then(|first| {
+            first.checked_add_days(Days::new(
+                u64::from(settings.fiscal_year_start_day.max(1)) - 1,
+            ))
+        })
+        .unwrap_or_else(|| NaiveDate::from_ymd_opt(year, 1, 1).unwrap())
+}
+
+// Dates [start, end) of the fiscal year starting in `year`
+pub fn fiscal_year_range(settings: &TaxSettings, year: i32) -> (NaiveDate, NaiveDate) {
+    (
+        fiscal_year_start(settings, year),
+        fiscal_year_start(settings, year + 1),
+    )
+}
+
+// Fiscal year (named after the calendar year it starts in) that contains `date`
+pub fn fiscal_year_of(settings: &TaxSettings, date: NaiveDate) -> i32 {
+    if date >= fiscal_year_start(settings, date.year()) {
+        date.year()
+    } else {
+        date.year() - 1
+    }
+}
+
+// "2026" for calendar fiscal years, "2026/27" when the year spans two calendar years
+pub fn fiscal_year_label(settings: &TaxSettings, year: i32) -> String {
+    if settings.fiscal_year_start_month == 1 && settings.fiscal_year_start_day == 1 {
+        year.to_string()
+    } else {
+        format!("{}/{:02}", year, (year + 1) % 100)
+    }
+}
+
+// Tax lines of one transaction. A transaction marked on its own counts as a whole,
+// otherwise every split line (or the transaction itself) whose category has a tax code counts.
+fn transaction_lines(
+    kind: TaxKind,
+    transaction: &Transaction,
+    categories: &BTreeMap<String, String>,
+    marked: bool,
+) -> Vec<TaxLine> {
+    let line = |code: String, category: &str, amount: f64| TaxLine {
+        kind,
+        code,
+        date: transaction.date,
+        category: category.to_string(),
+        description: transaction.description.clone(),
+        amount: match kind {
+            TaxKind::Deductible => -amount,
+            TaxKind::Taxable => amount,
+        },
+    };
+    if marked || transaction.tax_code.is_some() {
+        let code = transaction
+            .tax_code
+            .clone()
+            .or_else(|| categories.get(&transaction.category).cloned())
+            .unwrap_or_default();
+        return vec![line(code, &transaction.category, transaction.amount)];
+    }
+    transaction
+        .category_amounts()
+        .into_iter()
+        .filter_map(|(category, amount)| {
+            categories
+                .get(category)
+                .map(|code| line(code.clone(), category, amount))
+        })
+        .collect()
+}
+
+// Deductible expenses and taxable income of the fiscal year starting in `year`
+pub fn tax_lines(transactions: &Transactions, settings: &TaxSettings, year: i32) -> Vec<TaxLine> {
+    let (start, end) = fiscal_year_range(settings, year);
+    let in_year = |t: &&Transaction| !t.pending && t.date >= start && t.date < end;
+
+    let deductible = transactions.expenses.iter().filter(in_year).flat_map(|t| {
+        transaction_lines(
+            TaxKind::Deductible,
+            t,
+            &settings.deductible_categories,
+            t.deductible,
+        )
+    });
+    let taxable =
+        transactions.income.iter().filter(in_year).flat_map(|t| {
+            transaction_lines(TaxKind::Taxable, t, &settings.taxable_categories, false)
+        });
+
+    let mut lines: Vec<TaxLine> = deductible.chain(taxable).collect();
+    lines.sort_by(|a, b| (a.kind, &a.code, a.date).cmp(&(b.kind, &b.code, b.date)));
+    lines
+}
+
+// Totals per kind and tax code
+pub fn summarize(lines: &[TaxLine]) -> Vec<TaxTotal> {
+    let mut totals: BTreeMap<(TaxKind, String), (usize, f64)> = BTreeMap::new();
+    for line in lines {
+        let entry = totals
+            .entry((line.kind, line.code.clone()))
+            .or_insert((0, 0.0));
+        entry.0 += 1;
+        entry.1 += line.amount;
+    }
+    totals
+        .into_iter()
+        .map(|((kind, code), (count, total))| TaxTotal {
+            kind,
+            code,
+            count,
+            total,
+        })
+        .collect()
+}
+
+// Quote a CSV field when it contains separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Detailed tax lines as CSV, one transaction per row
+pub fn to_csv(lines: &[TaxLine]) -> String {
+    let mut csv = String::from("kind,tax_code,date,category,description,amount\n");
+    for line in lines {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{:.2}\n",
+            line.kind,
+            csv_field(&line.code),
+            line.date,
+            csv_field(&line.category),
+            csv_field(&line.description),
+            line.amount
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::DataStructure;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn starting(month: u32, day: u32) -> TaxSettings {
+        TaxSettings {
+            fiscal_year_start_month: month,
+            fiscal_year_start_day: day,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn calendar_fiscal_year() {
+        let settings = TaxSettings::default();
+        assert_eq!(
+            fiscal_year_range(&settings, 2026),
+            (date("2026-01-01"), date("2027-01-01"))
+        );
+        assert_eq!(fiscal_year_of(&settings, date("2026-01-01")), 2026);
+        assert_eq!(fiscal_year_of(&settings, date("2026-12-31")), 2026);
+        assert_eq!(fiscal_year_label(&settings, 2026), "2026");
+    }
+
+    #[test]
+    fn fiscal_year_spanning_two_calendar_years() {
+        // The UK tax year starts on April 6
+        let settings = starting(4, 6);
+        assert_eq!(
+            fiscal_year_range(&settings, 2026),
+            (date("2026-04-06"), date("2027-04-06"))
+        );
+        assert_eq!(fiscal_year_of(&settings, date("2026-04-05")), 2025);
+        assert_eq!(fiscal_year_of(&settings, date("2026-04-06")), 2026);
+        assert_eq!(fiscal_year_of(&settings, date("2027-04-05")), 2026);
+        assert_eq!(fiscal_year_label(&settings, 2026), "2026/27");
+        assert_eq!(fiscal_year_label(&settings, 2099), "2099/00");
+    }
+
+    #[test]
+    fn february_29_start_moves_to_march_1_outside_leap_years() {
+        let settings = starting(2, 29);
+        assert_eq!(
+            fiscal_year_range(&settings, 2027),
+            (date("2027-03-01"), date("2028-02-29"))
+        );
+        assert_eq!(fiscal_year_of(&settings, date("2027-02-28")), 2026);
+        assert_eq!(fiscal_year_of(&settings, date("2028-02-28")), 2027);
+        assert_eq!(fiscal_year_of(&settings, date("2028-02-29")), 2028);
+    }
+
+    #[test]
+    fn lines_stay_within_the_fiscal_year() {
+        let settings = TaxSettings {
+            deductible_categories: BTreeMap::from([("Health".to_string(), "MED".to_string())]),
+            ..starting(7, 1)
+        };
+        let expense = |id, day: &str, amount| Transaction {
+            id,
+            amount,
+            category: "Health".to_string(),
+            date: date(day),
+            ..Default::default()
+        };
+        let mut transactions = DataStructure::default().transactions;
+        transactions.expenses = vec![
+            expense(1, "2026-06-30", -10.0),
+            expense(2, "2026-07-01", -20.0),
+            expense(3, "2027-06-30", -30.0),
+            expense(4, "2027-07-01", -40.0),
+        ];
+        let lines = tax_lines(&transactions, &settings, 2026);
+        let amounts: Vec<f64> = lines.iter().map(|l| l.amount).collect();
+        assert_eq!(amounts, vec![20.0, 30.0]);
+        assert_eq!(
+            summarize(&lines),
+            vec![TaxTotal {
+                kind: TaxKind::Deductible,
+                code: "MED".to_string(),
+                count: 2,
+                total: 50.0,
+            }]
+        );
+    }
+}
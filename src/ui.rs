@@ -1,4 +1,16 @@
-use crate::app::{App, Tab};
+use crate::app::{App, ReportView, Tab};
+use crate::bills::REMINDER_DAYS;
+use crate::budget;
+use crate::data::Status;
+use crate::goals;
+use crate::investments::{self, PRICES_FILE};
+use crate::loans::{self, Strategy};
+use crate::reconcile;
+use crate::refund;
+use crate::report::{self, Comparison, Grouping, Period};
+use crate::shared;
+use crate::tax::{self, TaxKind};
+use chrono::{DateTime, Datelike, Days, Local, NaiveDate, Utc, Weekday};
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Constraint, Direction, Layout, Rect},
@@ -6,13 +18,15 @@ use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{
-        Bar, BarChart, BarGroup, Block, BorderType, Borders, Cell, List, ListItem, Padding,
-        Paragraph, Row, Scrollbar, ScrollbarOrientation, Table, TableState, Tabs,
+        Bar, BarChart, BarGroup, Block, BorderType, Borders, Cell, Clear, Gauge, List, ListItem,
+        Padding, Paragraph, Row, Scrollbar, ScrollbarOrientation, Table, TableState, Tabs, Wrap,
     },
     Frame, Terminal,
 };
+use std::collections::BTreeMap;
 use std::io;
-const INFO_TEXT: &str = "(Esc) Quit | (Tab) Next |  (Shift+Tab) Prev  |  (↓) Down  |  (↑) Up  ";
+const INFO_TEXT: &str =
+    "(Esc) Quit | (Tab) Next |  (Shift+Tab) Prev  |  (↓) Down  |  (↑) Up  |  (Ctrl+Z) Undo  |  (Ctrl+Y) Redo  ";
 const ITEM_HEIGHT: usize = 4;
 pub struct Ui {
     terminal: Terminal<CrosstermBackend<io::Stdout>>,
@@ -82,35 +96,50 @@ fn render_ui(f: &mut Frame, app: &mut App) {
     f.render_widget(tabs, main_chunks[1]);
 
     match app.current_tab {
-        Tab::Home => instruct(f, app, main_chunks[2]),
+        Tab::Home => render_home(f, app, main_chunks[2]),
         Tab::Transactions => render_transactions(f, app, main_chunks[2]),
         Tab::AddExpense | Tab::AddIncome => {
-            let category_choices = match app.current_tab {
-                Tab::AddExpense => app
-                    .expense_categories
-                    .iter()
-                    .map(|c| Span::styled(*c, Style::default().fg(Color::Yellow)))
-                    .collect::<Vec<Span>>(),
-                Tab::AddIncome => app
-                    .income_categories
-                    .iter()
-                    .map(|c| Span::styled(*c, Style::default().fg(Color::Yellow)))
-                    .collect::<Vec<Span>>(),
-                _ => vec![],
-            };
             render_add_transaction(f, app, main_chunks[2], app.current_tab);
         }
-        Tab::Report => render_chart(f, app, main_chunks[2]),
+        Tab::Report => render_report(f, app, main_chunks[2]),
+        Tab::Calendar => render_calendar(f, app, main_chunks[2]),
+        Tab::Debts => render_debts(f, app, main_chunks[2]),
+        Tab::Budget => render_budget(f, app, main_chunks[2]),
     }
 
     //  -------------- FOOTER SECTION --------------
-    render_footer(f, main_chunks[3]); // Now use chunks[2]
+    render_footer(f, main_chunks[3], app.toast()); // Now use chunks[2]
+
+    // Bill reminders shown on startup, on top of everything else
+    if app.bill_popup {
+        let area = f.area();
+        let height = (app.bill_reminders().len() as u16 + 2).min(area.height);
+        let popup = Rect {
+            x: area.width / 6,
+            y: area.height.saturating_sub(height) / 2,
+            width: area.width * 2 / 3,
+            height,
+        };
+        f.render_widget(Clear, popup);
+        render_bills(
+            f,
+            app,
+            popup,
+            " Bills to pay | (↑/↓) Select  (p) Pay  (Enter) Close ",
+        );
+    }
 }
 
 // Now render_footer is an independent function with proper arguments.
-fn render_footer(frame: &mut Frame, area: Rect) {
-    let footer_text = Paragraph::new(Line::from(INFO_TEXT)) // INFO_TEXT is now in scope
-        .style(Style::default().fg(Color::Yellow))
+// A recent undo or redo message takes the place of the key help for a few seconds.
+fn render_footer(frame: &mut Frame, area: Rect, toast: Option<&str>) {
+    let (text, color) = match toast {
+        Some(message) if message.starts_with("ERROR") => (message, Color::Red),
+        Some(message) => (message, Color::LightGreen),
+        None => (INFO_TEXT, Color::Yellow),
+    };
+    let footer_text = Paragraph::new(Line::from(text)) // INFO_TEXT is now in scope
+        .style(Style::default().fg(color))
         .block(
             Block::default()
                 .borders(Borders::BOTTOM)
@@ -137,7 +166,44 @@ fn render_scrollbar(f: &mut Frame, app: &mut App, area: Rect) {
 }
 
 fn render_transactions(f: &mut Frame, app: &mut App, area: Rect) {
+    // Show the review panel next to the table while there are flagged transactions
+    let flagged = app.flagged_transactions().len();
+    let area = if flagged > 0 {
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .split(area);
+        render_review(f, app, chunks[1]);
+        chunks[0]
+    } else {
+        area
+    };
+    // Prompt for the statement above the table when starting to reconcile
+    let area = match &app.statement_input {
+        Some(input) => {
+            let chunks = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(1)])
+                .split(area);
+            f.render_widget(input, chunks[0]);
+            chunks[1]
+        }
+        None => area,
+    };
+    // Details of the selected transaction below the table
+    let area = if app.show_details {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(10)])
+            .split(area);
+        render_details(f, app, chunks[1]);
+        chunks[0]
+    } else {
+        area
+    };
+
     let header = Row::new(vec![
+        Cell::from(Text::from("")),
         Cell::from(Text::from("Date")).style(
             Style::default()
                 .fg(Color::Cyan)
@@ -148,6 +214,11 @@ fn render_transactions(f: &mut Frame, app: &mut App, area: Rect) {
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
         ),
+        Cell::from(Text::from("Payee")).style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
         Cell::from(Text::from("Category")).style(
             Style::default()
                 .fg(Color::Cyan)
@@ -158,19 +229,38 @@ fn render_transactions(f: &mut Frame, app: &mut App, area: Rect) {
                 .fg(Color::Cyan)
                 .add_modifier(Modifier::BOLD),
         ),
+        Cell::from(Text::from("Tags")).style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
     ])
     .height(1)
     .style(Style::default().bg(Color::DarkGray));
 
     // Create rows for each transaction
     let rows = app
-        .transactions
-        .expenses
-        .iter()
-        .chain(app.transactions.income.iter())
+        .table_rows()
+        .into_iter()
         .enumerate()
-        .map(|(i, t)| {
+        .map(|(i, (_, t))| {
             Row::new(vec![
+                Cell::from(Text::from(format!(
+                    "{}{}{}",
+                    if t.flagged { "⚠" } else { " " },
+                    if t.deductible { "§" } else { " " },
+                    match t.status {
+                        _ if t.pending => "P",
+                        Status::Uncleared => "",
+                        Status::Cleared => "C",
+                        Status::Reconciled => "R",
+                    }
+                )))
+                .style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
                 Cell::from(Text::from(t.date.to_string())).style(Style::default()),
                 Cell::from(Text::from(format!("{:.2}$", t.amount))).style(Style::default().fg(
                     if t.amount >= 0.0 {
@@ -179,15 +269,47 @@ fn render_transactions(f: &mut Frame, app: &mut App, area: Rect) {
                         Color::Red
                     },
                 )),
-                Cell::from(Text::from(t.category.clone())).style(Style::default()),
-                Cell::from(Text::from(t.description.clone())).style(Style::default()),
+                Cell::from(Text::from(t.payee.clone())).style(Style::default()),
+                Cell::from(Text::from(if t.splits.is_empty() {
+                    t.category.clone()
+                } else {
+                    format!(
+                        "Split: {}",
+                        t.splits
+                            .iter()
+                            .map(|s| s.category.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                }))
+                .style(Style::default()),
+                Cell::from(Text::from(match t.refund_of {
+                    Some(_) => format!("↩ {}", t.description),
+                    None => t.description.clone(),
+                }))
+                .style(Style::default()),
+                Cell::from(Text::from(
+                    t.tags
+                        .iter()
+                        .map(|tag| format!("#{}", tag))
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ))
+                .style(Style::default().fg(Color::Magenta)),
             ])
             .height(1)
-            .style(if i % 2 == 0 {
-                Style::default().bg(Color::DarkGray)
-            } else {
-                Style::default().bg(Color::Black)
-            })
+            .style(
+                if i % 2 == 0 {
+                    Style::default().bg(Color::DarkGray)
+                } else {
+                    Style::default().bg(Color::Black)
+                }
+                .add_modifier(if t.pending {
+                    Modifier::ITALIC
+                } else {
+                    Modifier::empty()
+                }),
+            )
         })
         .collect::<Vec<Row>>();
 
@@ -198,17 +320,48 @@ fn render_transactions(f: &mut Frame, app: &mut App, area: Rect) {
     let transactions_table = Table::new(
         rows.clone(),
         &[
-            Constraint::Percentage(25), // Date (25% width)
-            Constraint::Percentage(25), // Amount (25% width)
-            Constraint::Percentage(25), // Category (25% width)
-            Constraint::Percentage(25), // Description (25% width)
+            Constraint::Length(3),      // Review, tax and reconciliation markers
+            Constraint::Percentage(12), // Date (12% width)
+            Constraint::Percentage(12), // Amount (12% width)
+            Constraint::Percentage(16), // Payee (16% width)
+            Constraint::Percentage(16), // Category (16% width)
+            Constraint::Percentage(26), // Description (26% width)
+            Constraint::Percentage(18), // Tags (18% width)
         ],
     )
     .header(header)
     .block(
         Block::default()
             .borders(Borders::ALL)
-            .title("Transactions")
+            .title(match &app.statement {
+                Some(statement) => {
+                    let difference = statement.difference(&app.transactions);
+                    format!(
+                        "Reconciling to {} | Statement {:.2}  Cleared {:.2}  Difference {:.2} | (space) Clear{}  (r) Leave",
+                        statement.date,
+                        statement.balance,
+                        reconcile::cleared_balance(&app.transactions, statement.date),
+                        difference,
+                        if statement.is_balanced(&app.transactions) {
+                            "  (f) Finish"
+                        } else {
+                            ""
+                        }
+                    )
+                }
+                None => format!(
+                    "Transactions{} | (e) Edit  (u) Refund  (t) Tag filter  (d) Deductible  (r) Reconcile  (Enter) Details{}",
+                    match &app.tag_filter {
+                        Some(tag) => format!(" #{}", tag),
+                        None => String::new(),
+                    },
+                    if flagged > 0 {
+                        "  (x) Dismiss flag"
+                    } else {
+                        ""
+                    }
+                ),
+            })
             .title_style(
                 Style::default()
                     .fg(Color::Cyan)
@@ -233,24 +386,130 @@ fn render_transactions(f: &mut Frame, app: &mut App, area: Rect) {
     render_scrollbar(f, app, area);
 }
 
+// When the selected transaction was entered and changed, with its change log latest first
+fn render_details(f: &mut Frame, app: &App, area: Rect) {
+    let time = |at: DateTime<Utc>| {
+        at.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+            .to_string()
+    };
+    let label = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    match app.selected_transaction() {
+        None => lines.push(Line::from("Select a transaction to see its history")),
+        Some((_, t)) => {
+            lines.push(Line::from(vec![
+                Span::styled("Created ", label),
+                Span::raw(
+                    t.created_at
+                        .map(time)
+                        .unwrap_or_else(|| "unknown".to_string()),
+                ),
+                Span::styled("   Updated ", label),
+                Span::raw(
+                    t.updated_at
+                        .map(time)
+                        .unwrap_or_else(|| "unknown".to_string()),
+                ),
+            ]));
+            if t.changes.is_empty() {
+                lines.push(Line::from("No changes recorded"));
+            }
+            for change in t.changes.iter().rev() {
+                let what = if change.fields.is_empty() {
+                    "Entered".to_string()
+                } else {
+                    change
+                        .fields
+                        .iter()
+                        .map(|c| format!("{}: {} → {}", c.field, c.before, c.after))
+                        .collect::<Vec<_>>()
+                        .join("; ")
+                };
+                lines.push(Line::from(vec![
+                    Span::raw(format!("{}  ", time(change.at))),
+                    Span::styled(
+                        format!("{:<6} ", change.source.to_string()),
+                        Style::default().fg(Color::Magenta),
+                    ),
+                    Span::raw(what),
+                ]));
+            }
+        }
+    }
+
+    let details = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Details | (Enter) Hide ")
+            .title_style(label)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(details, area);
+}
+
+// List of transactions flagged as unusual for their category
+fn render_review(f: &mut Frame, app: &App, area: Rect) {
+    let items = app
+        .flagged_transactions()
+        .into_iter()
+        .map(|t| {
+            ListItem::new(vec![
+                Line::from(vec![
+                    Span::styled("⚠ ", Style::default().fg(Color::Yellow)),
+                    Span::raw(format!("{} ", t.date)),
+                    Span::styled(
+                        format!("{:.2}$", t.amount),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                ]),
+                Line::from(format!("  {} - {}", t.category, t.description)),
+            ])
+        })
+        .collect::<Vec<ListItem>>();
+
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Review ")
+            .title_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .border_style(Style::default().fg(Color::Yellow)),
+    );
+    f.render_widget(list, area);
+}
+
 // working with all cursor visible
 fn render_add_transaction(f: &mut Frame, app: &mut App, area: Rect, _current_tab: Tab) {
     let chunks: std::rc::Rc<[Rect]> = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Percentage(10),
-            Constraint::Percentage(30),
-            Constraint::Percentage(10),
-            Constraint::Percentage(20),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Length(3),
             Constraint::Min(1),
         ])
         .split(area);
 
     // Render all text areas
     f.render_widget(&app.amount_input, chunks[0]);
-    f.render_widget(&app.category_input, chunks[1]);
-    f.render_widget(&app.date_input, chunks[2]);
-    f.render_widget(&app.description_input, chunks[3]);
+    f.render_widget(&app.payee_input, chunks[1]);
+    f.render_widget(&app.category_input, chunks[2]);
+    f.render_widget(&app.date_input, chunks[3]);
+    f.render_widget(&app.description_input, chunks[4]);
+    f.render_widget(&app.tags_input, chunks[5]);
+    f.render_widget(&app.splits_input, chunks[6]);
 
     // Render the active text area with blinking cursor
     let active_textarea = app.get_active_textarea();
@@ -271,23 +530,535 @@ fn render_add_transaction(f: &mut Frame, app: &mut App, area: Rect, _current_tab
         _ => unreachable!(),
     };
 
+    let action = match (app.editing, app.refund_of) {
+        (Some(id), Some(_)) => format!("Editing Refund #{}", id),
+        (Some(id), None) => format!("Editing {} #{}", transaction_type, id),
+        (None, Some(expense)) => format!("Adding Refund of Expense #{}", expense),
+        (None, None) => format!("Adding {}", transaction_type),
+    };
     let instructions = Paragraph::new(format!(
-        "Adding {}.Press Up & Down to switch fields. Press Enter to submit, Esc to Exit",
-        transaction_type
+        "{}. Press Up & Down to switch fields. Press Enter to submit, Esc to Exit",
+        action
     ))
     .style(Style::default().fg(Color::Yellow));
-    f.render_widget(instructions, chunks[4]);
+    f.render_widget(instructions, chunks[7]);
+}
+
+fn render_report(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(area);
+
+    let titles: Vec<Span> = ReportView::ALL
+        .iter()
+        .map(|v| Span::styled(v.to_string(), Style::default().fg(Color::Green)))
+        .collect();
+    let views = Tabs::new(titles)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(" Reports (←/→) "),
+        )
+        .highlight_style(Style::default().bg(Color::LightMagenta))
+        .select(
+            ReportView::ALL
+                .iter()
+                .position(|v| *v == app.report_view)
+                .unwrap_or(0),
+        )
+        .divider("|")
+        .padding(" ", " ");
+    f.render_widget(views, chunks[0]);
+
+    match app.report_view {
+        ReportView::Overview => render_chart(f, app, chunks[1]),
+        ReportView::Comparison => render_comparison(f, app, chunks[1]),
+        ReportView::Tax => render_tax(f, app, chunks[1]),
+        ReportView::Reimbursable => render_reimbursable(f, app, chunks[1]),
+        ReportView::NetWorth => render_net_worth(f, app, chunks[1]),
+        ReportView::Balances => render_balances(f, app, chunks[1]),
+    }
+}
+
+// Style for a spending delta: increases are highlighted, decreases are calm
+fn delta_style(delta: f64) -> Style {
+    if delta > 0.0 {
+        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+    } else if delta < 0.0 {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    }
+}
+
+fn render_comparison(f: &mut Frame, app: &App, area: Rect) {
+    let period = app.report_period;
+    let start = period.start_of(app.report_anchor);
+    let grouping = app.report_grouping;
+    let rows = report::compare(&app.transactions, grouping, period, start);
+    let total = Comparison::total(grouping, &rows);
+
+    let header = Row::new(
+        [
+            match grouping {
+                Grouping::Category => "Category",
+                Grouping::Payee => "Payee",
+                Grouping::Tag => "Tag",
+            },
+            "Current",
+            "Previous",
+            "Change",
+            "Last year",
+            "Change",
+        ]
+        .into_iter()
+        .map(|h| {
+            Cell::from(Text::from(h)).style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+        }),
+    )
+    .height(1)
+    .style(Style::default().bg(Color::DarkGray));
+
+    let table_rows = rows
+        .iter()
+        .chain(total.iter())
+        .enumerate()
+        .map(|(i, r)| {
+            let row = Row::new(vec![
+                Cell::from(r.name.clone()),
+                Cell::from(format!("{:.2}$", r.current)),
+                Cell::from(format!("{:.2}$", r.previous)),
+                Cell::from(report::format_delta(r.previous_delta(), r.previous_pct()))
+                    .style(delta_style(r.previous_delta())),
+                Cell::from(format!("{:.2}$", r.last_year)),
+                Cell::from(report::format_delta(r.last_year_delta(), r.last_year_pct()))
+                    .style(delta_style(r.last_year_delta())),
+            ]);
+            if i == rows.len() {
+                row.style(Style::default().add_modifier(Modifier::BOLD))
+            } else {
+                row
+            }
+        })
+        .collect::<Vec<Row>>();
+
+    let title = format!(
+        " Spending by {} {} vs previous {} and last year | ([ ]) Period  (p) Month/Year  (g) Group ",
+        grouping,
+        period.label(start),
+        period
+    );
+    let table = Table::new(
+        table_rows,
+        &[
+            Constraint::Percentage(20),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+            Constraint::Percentage(19),
+            Constraint::Percentage(14),
+            Constraint::Percentage(19),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .title_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .border_style(Style::default().fg(Color::Cyan))
+            .border_type(BorderType::Rounded),
+    )
+    .column_spacing(1);
+
+    f.render_widget(table, area);
+}
+
+fn render_tax(f: &mut Frame, app: &App, area: Rect) {
+    let settings = app.data.get_tax_settings();
+    let lines = tax::tax_lines(&app.transactions, settings, app.tax_year);
+    let totals = tax::summarize(&lines);
+    let (start, end) = tax::fiscal_year_range(settings, app.tax_year);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(totals.len() as u16 + 3),
+            Constraint::Min(1),
+        ])
+        .split(area);
+
+    let header_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let kind_style = |kind: TaxKind| match kind {
+        TaxKind::Deductible => Style::default().fg(Color::Green),
+        TaxKind::Taxable => Style::default().fg(Color::Yellow),
+    };
+
+    let summary_rows = totals
+        .iter()
+        .map(|t| {
+            Row::new(vec![
+                Cell::from(t.kind.to_string()).style(kind_style(t.kind)),
+                Cell::from(if t.code.is_empty() {
+                    "-".to_string()
+                } else {
+                    t.code.clone()
+                }),
+                Cell::from(t.count.to_string()),
+                Cell::from(format!("{:.2}$", t.total)),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let summary = Table::new(
+        summary_rows,
+        &[
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+        ],
+    )
+    .header(Row::new(vec!["Kind", "Tax code", "Items", "Total"]).style(header_style))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!(
+                " Tax year {} ({} to {}) | ([ ]) Year ",
+                tax::fiscal_year_label(settings, app.tax_year),
+                start,
+                end.pred_opt().unwrap_or(end)
+            ))
+            .title_style(header_style)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(summary, chunks[0]);
+
+    let detail_rows = lines
+        .iter()
+        .map(|l| {
+            Row::new(vec![
+                Cell::from(l.date.to_string()),
+                Cell::from(l.kind.to_string()).style(kind_style(l.kind)),
+                Cell::from(l.code.clone()),
+                Cell::from(l.category.clone()),
+                Cell::from(l.description.clone()),
+                Cell::from(format!("{:.2}$", l.amount)),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let details = Table::new(
+        detail_rows,
+        &[
+            Constraint::Percentage(15),
+            Constraint::Percentage(14),
+            Constraint::Percentage(14),
+            Constraint::Percentage(18),
+            Constraint::Percentage(25),
+            Constraint::Percentage(14),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Date",
+            "Kind",
+            "Tax code",
+            "Category",
+            "Description",
+            "Amount",
+        ])
+        .style(header_style),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Transactions (export with `fintrackr tax report --csv FILE`) "),
+    );
+    f.render_widget(details, chunks[1]);
+}
+
+// Reimbursable expenses that have not been paid back in full yet
+fn render_reimbursable(f: &mut Frame, app: &App, area: Rect) {
+    let items = refund::outstanding(&app.transactions);
+    let total = report::sum(items.iter().map(|item| item.remaining));
+
+    let rows = items
+        .iter()
+        .map(|item| {
+            Row::new(vec![
+                Cell::from(item.expense.date.to_string()),
+                Cell::from(item.expense.payee.clone()),
+                Cell::from(item.expense.description.clone()),
+                Cell::from(format!("{:.2}$", item.expense.amount.abs())),
+                Cell::from(format!("{:.2}$", item.refunded)),
+                Cell::from(format!("{:.2}$", item.remaining)).style(
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            ])
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Percentage(14),
+            Constraint::Percentage(18),
+            Constraint::Percentage(29),
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Date",
+            "Payee",
+            "Description",
+            "Amount",
+            "Refunded",
+            "Outstanding",
+        ])
+        .style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!(
+                " Expenses tagged #{} still to be paid back: {:.2}$ (record refunds with (u) in the Transactions tab) ",
+                refund::REIMBURSABLE_TAG,
+                total
+            ))
+            .title_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(table, area);
+}
+
+// Who owes whom for shared expenses and the payments that settle everything
+fn render_balances(f: &mut Frame, app: &App, area: Rect) {
+    let header_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let balances = shared::balances(&app.transactions, app.data.get_transfers());
+    let payments = shared::settle_up(&balances);
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+
+    let rows = balances
+        .iter()
+        .map(|(person, balance)| {
+            let (state, color) = if *balance > 0.0 {
+                ("is owed", Color::Green)
+            } else if *balance < 0.0 {
+                ("owes", Color::Red)
+            } else {
+                ("is settled", Color::White)
+            };
+            Row::new(vec![
+                Cell::from(person.clone()),
+                Cell::from(state),
+                Cell::from(format!("{:.2}$", balance.abs())).style(Style::default().fg(color)),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Percentage(45),
+            Constraint::Percentage(25),
+            Constraint::Percentage(30),
+        ],
+    )
+    .header(Row::new(vec!["Person", "", "Balance"]).style(header_style))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Balances of shared expenses ")
+            .title_style(header_style)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(table, chunks[0]);
+
+    let items: Vec<ListItem> = if payments.is_empty() {
+        vec![ListItem::new("Everyone is settled up")]
+    } else {
+        payments
+            .iter()
+            .map(|(from, to, amount)| {
+                ListItem::new(Line::from(vec![
+                    Span::styled(from.clone(), Style::default().fg(Color::Red)),
+                    Span::raw(" pays "),
+                    Span::styled(to.clone(), Style::default().fg(Color::Green)),
+                    Span::styled(
+                        format!(" {:.2}$", amount),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                ]))
+            })
+            .collect()
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Settle up: (s) records these payments as transfers ")
+            .title_style(header_style)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(list, chunks[1]);
+}
+
+// Investment positions at the latest prices and the resulting net worth
+fn render_net_worth(f: &mut Frame, app: &App, area: Rect) {
+    let header_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let empty = investments::Prices::new();
+    let prices = app.prices.as_ref().unwrap_or(&empty);
+    let today = Local::now().date_naive();
+    let (portfolio, problem) = match investments::portfolio(app.data.get_trades(), prices, today) {
+        Ok(portfolio) => (portfolio, app.prices.as_ref().err().cloned()),
+        Err(err) => (investments::Portfolio::default(), Some(err)),
+    };
+    let worth = investments::net_worth(
+        &app.transactions,
+        app.data.get_trades(),
+        app.data.get_loans(),
+        &portfolio,
+        today,
+    );
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(area);
+
+    let summary = Paragraph::new(Line::from(vec![
+        Span::raw(format!("Cash {:.2}$  ", worth.cash)),
+        Span::styled(
+            format!("Investments {:.2}$  ", worth.investments),
+            Style::default().fg(Color::Green),
+        ),
+        Span::styled(
+            format!("Debts {:.2}$  ", 0.0 - worth.debts),
+            Style::default().fg(Color::Red),
+        ),
+        Span::styled(
+            format!("Net worth {:.2}$", worth.total()),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Net worth "),
+    );
+    f.render_widget(summary, chunks[0]);
+
+    let gain_style = |gain: f64| {
+        Style::default().fg(if gain >= 0.0 {
+            Color::Green
+        } else {
+            Color::Red
+        })
+    };
+    let rows = portfolio
+        .positions
+        .iter()
+        .map(|p| {
+            Row::new(vec![
+                Cell::from(p.symbol.clone()),
+                Cell::from(format!("{:.4}", p.quantity())),
+                Cell::from(format!("{:.2}$", p.cost())),
+                Cell::from(match p.price {
+                    Some((date, price)) => format!("{:.2}$ ({})", price, date),
+                    None => "no price".to_string(),
+                }),
+                Cell::from(format!("{:.2}$", p.value())),
+                Cell::from(format!("{:+.2}$", p.unrealized())).style(gain_style(p.unrealized())),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Percentage(12),
+            Constraint::Percentage(14),
+            Constraint::Percentage(16),
+            Constraint::Percentage(26),
+            Constraint::Percentage(16),
+            Constraint::Percentage(16),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Symbol",
+            "Quantity",
+            "Cost",
+            "Price",
+            "Value",
+            "Unrealized",
+        ])
+        .style(header_style),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(match problem {
+                Some(problem) => format!(" Holdings | {} ", problem),
+                None => format!(
+                    " Holdings | Realized gains {:+.2}$ | Prices from {} ",
+                    portfolio.realized(),
+                    PRICES_FILE
+                ),
+            })
+            .title_style(header_style)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(table, chunks[1]);
 }
 
 fn render_chart(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(3)])
+        .split(area);
+    render_forecast(f, app, chunks[1]);
+    let area = chunks[0];
+
     // Prepare data for the bar chart
-    let total_expenses: f64 = app
-        .transactions
-        .expenses
-        .iter()
-        .map(|t| t.amount.abs())
-        .sum();
-    let total_income: f64 = app.transactions.income.iter().map(|t| t.amount).sum();
+    let totals = report::totals(&app.transactions, NaiveDate::MIN, NaiveDate::MAX);
+    let total_expenses = totals.expenses;
+    let total_income = totals.income;
 
     let bars = vec![
         Bar::default()
@@ -321,6 +1092,698 @@ fn render_chart(f: &mut Frame, app: &App, area: Rect) {
     f.render_widget(chart, area);
 }
 
+// Actual balance and the balance after all pending transactions have posted
+fn render_forecast(f: &mut Frame, app: &App, area: Rect) {
+    let forecast = report::forecast(&app.transactions);
+    let text = match forecast.until {
+        Some(until) => Line::from(vec![
+            Span::raw(format!("Balance {:.2}$  ", forecast.balance)),
+            Span::styled(
+                format!("Pending +{:.2}$", forecast.pending_income),
+                Style::default().fg(Color::Green),
+            ),
+            Span::raw(" / "),
+            Span::styled(
+                format!("-{:.2}$", forecast.pending_expenses),
+                Style::default().fg(Color::Red),
+            ),
+            Span::styled(
+                format!(
+                    "  Forecast on {}: {:.2}$",
+                    until,
+                    forecast.forecast_balance()
+                ),
+                Style::default().add_modifier(Modifier::BOLD),
+            ),
+        ]),
+        None => Line::from(format!(
+            "Balance {:.2}$  No pending transactions",
+            forecast.balance
+        )),
+    };
+    let paragraph = Paragraph::new(text).block(
+        Block::default()
+            .title(" Forecast ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    f.render_widget(paragraph, area);
+}
+
+// Envelopes of the zero-based budget for the selected month
+fn render_budget(f: &mut Frame, app: &App, area: Rect) {
+    let header_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let budget = app.data.get_budget();
+    if budget.start.is_none() {
+        let paragraph = Paragraph::new(
+            "Zero-based budgeting is off. Turn it on with `fintrackr budget start`, then assign every income to category envelopes here.",
+        )
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title(" Budget ")
+                .title_style(header_style)
+                .border_style(Style::default().fg(Color::Cyan)),
+        );
+        f.render_widget(paragraph, area);
+        return;
+    }
+
+    let envelopes = budget::envelopes(budget, &app.transactions, app.budget_month);
+    let left = budget::to_be_budgeted(budget, &app.transactions, app.budget_month);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(1)])
+        .split(area);
+
+    let summary = Paragraph::new(Line::from(vec![
+        Span::raw(format!("{}  ", app.budget_month.format("%B %Y"))),
+        Span::styled(
+            format!("To be budgeted {:.2}$", left),
+            Style::default()
+                .fg(if left.abs() < 0.005 {
+                    Color::Green
+                } else if left < 0.0 {
+                    Color::Red
+                } else {
+                    Color::Yellow
+                })
+                .add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            match &app.budget_error {
+                Some(err) => format!("  {}", err),
+                None => String::new(),
+            },
+            Style::default().fg(Color::Red),
+        ),
+    ]))
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Budget "),
+    );
+    f.render_widget(summary, chunks[0]);
+
+    let rows = envelopes
+        .iter()
+        .enumerate()
+        .map(|(i, envelope)| {
+            let available = envelope.available();
+            Row::new(vec![
+                Cell::from(envelope.category.clone()),
+                Cell::from(format!("{:.2}$", envelope.carried)),
+                Cell::from(format!("{:.2}$", envelope.assigned)),
+                Cell::from(format!("{:.2}$", envelope.spent)),
+                Cell::from(format!("{:.2}$", available)).style(if envelope.is_overspent() {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::Green)
+                }),
+            ])
+            .style(if i == app.budget_selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black)
+            } else {
+                Style::default()
+            })
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Percentage(28),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Envelope",
+            "Carried",
+            "Assigned",
+            "Spent",
+            "Available",
+        ])
+        .style(header_style),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Envelopes | (↑/↓) Select  ([ ]) Month  (+/-) Assign  (c) Cover overspending ")
+            .title_style(header_style)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(table, chunks[1]);
+}
+
+// Loans with their balances, the remaining schedule of the selected one and the payoff planner
+fn render_debts(f: &mut Frame, app: &App, area: Rect) {
+    let loans = app.data.get_loans();
+    let header_style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(loans.len().max(1) as u16 + 3),
+            Constraint::Min(1),
+        ])
+        .split(area);
+
+    let rows = loans
+        .iter()
+        .enumerate()
+        .map(|(i, loan)| {
+            let remaining = loans::remaining_schedule(loan, &app.transactions);
+            Row::new(vec![
+                Cell::from(loan.name.clone()),
+                Cell::from(loan.kind.clone()),
+                Cell::from(format!("{:.2}$", loans::balance(loan, &app.transactions))),
+                Cell::from(format!("{:.2}%", loan.rate)),
+                Cell::from(format!("{:.2}$", loans::monthly_payment(loan))),
+                Cell::from(
+                    remaining
+                        .first()
+                        .map(|i| i.date.to_string())
+                        .unwrap_or_default(),
+                ),
+                Cell::from(
+                    remaining
+                        .last()
+                        .map(|i| i.date.to_string())
+                        .unwrap_or_else(|| "paid off".to_string()),
+                ),
+                Cell::from(remaining.len().to_string()),
+            ])
+            .style(if i == app.debt_selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black)
+            } else {
+                Style::default()
+            })
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Percentage(20),
+            Constraint::Percentage(10),
+            Constraint::Percentage(14),
+            Constraint::Percentage(8),
+            Constraint::Percentage(12),
+            Constraint::Percentage(13),
+            Constraint::Percentage(13),
+            Constraint::Percentage(10),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Loan", "Kind", "Balance", "Rate", "Payment", "Next due", "Payoff", "Left",
+        ])
+        .style(header_style),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(if loans.is_empty() {
+                " Debts | Add loans with `fintrackr loan add` ".to_string()
+            } else {
+                " Debts | (↑/↓) Select  (p) Record next payment ".to_string()
+            })
+            .title_style(header_style)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(table, chunks[0]);
+
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(chunks[1]);
+
+    let schedule = loans
+        .get(app.debt_selected)
+        .map(|loan| loans::remaining_schedule(loan, &app.transactions))
+        .unwrap_or_default();
+    let schedule_rows = schedule
+        .iter()
+        .map(|i| {
+            Row::new(vec![
+                Cell::from(i.number.to_string()),
+                Cell::from(i.date.to_string()),
+                Cell::from(format!("{:.2}", i.payment)),
+                Cell::from(format!("{:.2}", i.interest)).style(Style::default().fg(Color::Red)),
+                Cell::from(format!("{:.2}", i.principal)).style(Style::default().fg(Color::Green)),
+                Cell::from(format!("{:.2}", i.balance)),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let schedule_table = Table::new(
+        schedule_rows,
+        &[
+            Constraint::Percentage(8),
+            Constraint::Percentage(20),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+            Constraint::Percentage(18),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "#",
+            "Date",
+            "Payment",
+            "Interest",
+            "Principal",
+            "Balance",
+        ])
+        .style(header_style),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(" Remaining schedule "),
+    );
+    f.render_widget(schedule_table, bottom[0]);
+
+    // Both strategies side by side, with the payoff order of the chosen one
+    let mut lines = Vec::new();
+    for strategy in [Strategy::Avalanche, Strategy::Snowball] {
+        let plan = loans::plan(loans, &app.transactions, strategy, app.debt_extra);
+        let style = if strategy == app.debt_strategy {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        lines.push(Line::from(Span::styled(
+            format!(
+                "{}: debt-free {}, interest {:.2}$",
+                strategy,
+                plan.debt_free()
+                    .map(|d| format!("by {}", d.format("%Y-%m")))
+                    .unwrap_or_else(|| "never".to_string()),
+                plan.total_interest
+            ),
+            style,
+        )));
+        if strategy == app.debt_strategy {
+            for (name, date) in &plan.payoff {
+                lines.push(Line::from(format!(
+                    "  {} {}",
+                    date.map(|d| d.format("%Y-%m").to_string())
+                        .unwrap_or_else(|| "never  ".to_string()),
+                    name
+                )));
+            }
+        }
+    }
+    let planner = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!(
+                " Payoff planner, extra {:.2}$/month | (s) Strategy  (+/-) Extra ",
+                app.debt_extra
+            )),
+    );
+    f.render_widget(planner, bottom[1]);
+}
+
+// Colors of the heatmap, from no spending to the biggest spending day of the period
+const HEAT_COLORS: [Color; 5] = [
+    Color::DarkGray,
+    Color::Indexed(52),
+    Color::Indexed(88),
+    Color::Indexed(160),
+    Color::Indexed(196),
+];
+
+// Map a day's spending to one of the heatmap levels relative to the period maximum
+fn heat_level(amount: f64, max: f64) -> usize {
+    if amount <= 0.0 || max <= 0.0 {
+        0
+    } else {
+        ((amount / max * 4.0).ceil() as usize).clamp(1, 4)
+    }
+}
+
+fn render_calendar(f: &mut Frame, app: &App, area: Rect) {
+    let period = app.calendar_period;
+    let start = period.start_of(app.calendar_date);
+    let end = period.shift(start, 1);
+    let daily = report::daily_spending(&app.transactions, start, end);
+    let max = daily.values().copied().fold(0.0, f64::max);
+
+    let mut lines = match period {
+        Period::Month => month_grid(start, end, app.calendar_date, &daily, max),
+        Period::Year => year_grid(start, end, app.calendar_date, &daily, max),
+    };
+    // Legend below the grid
+    let mut legend = vec![Span::raw("Less ")];
+    legend.extend(
+        HEAT_COLORS
+            .iter()
+            .map(|c| Span::styled("■ ", Style::default().fg(*c))),
+    );
+    legend.push(Span::raw("More"));
+    lines.push(Line::from(""));
+    lines.push(Line::from(legend));
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(lines.len() as u16 + 2),
+            Constraint::Min(1),
+        ])
+        .split(area);
+
+    let total: f64 = daily.values().sum();
+    let grid = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!(
+                " Spending {} ({:.2}$) | (←↑↓→) Day  ([ ]) {}  (p) Month/Year  (t) Today ",
+                period.label(start),
+                total,
+                period
+            ))
+            .title_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    f.render_widget(grid, chunks[0]);
+
+    render_day_transactions(f, app, chunks[1]);
+}
+
+// Style of a single calendar cell
+fn heat_style(amount: f64, max: f64, selected: bool) -> Style {
+    let style = Style::default().fg(HEAT_COLORS[heat_level(amount, max)]);
+    if selected {
+        style.add_modifier(Modifier::REVERSED | Modifier::BOLD)
+    } else {
+        style
+    }
+}
+
+// One row per week, days laid out Monday to Sunday
+fn month_grid(
+    start: NaiveDate,
+    end: NaiveDate,
+    selected: NaiveDate,
+    daily: &BTreeMap<NaiveDate, f64>,
+    max: f64,
+) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::from(Span::styled(
+        " Mo  Tu  We  Th  Fr  Sa  Su",
+        Style::default().fg(Color::Yellow),
+    ))];
+    let mut week = vec![Span::raw("    "); start.weekday().num_days_from_monday() as usize];
+    let mut day = start;
+    while day < end {
+        let amount = daily.get(&day).copied().unwrap_or(0.0);
+        let mut style = heat_style(amount, max, day == selected);
+        if amount <= 0.0 && day != selected {
+            style = style.fg(Color::Gray);
+        }
+        week.push(Span::styled(format!(" {:>2} ", day.day()), style));
+        if day.weekday() == Weekday::Sun {
+            lines.push(Line::from(std::mem::take(&mut week)));
+        }
+        day = day + Days::new(1);
+    }
+    if !week.is_empty() {
+        lines.push(Line::from(week));
+    }
+    lines
+}
+
+// Contribution-graph layout: one row per weekday, one column per week
+fn year_grid(
+    start: NaiveDate,
+    end: NaiveDate,
+    selected: NaiveDate,
+    daily: &BTreeMap<NaiveDate, f64>,
+    max: f64,
+) -> Vec<Line<'static>> {
+    let grid_start = start - Days::new(start.weekday().num_days_from_monday() as u64);
+    let weeks = ((end - grid_start).num_days() as u64).div_ceil(7);
+
+    // Month names above the week that contains the first of each month
+    let mut months = vec![' '; 4 + weeks as usize * 2];
+    for week in 0..weeks {
+        let first_of_month = (0..7)
+            .map(|d| grid_start + Days::new(week * 7 + d))
+            .find(|d| d.day() == 1 && *d >= start && *d < end);
+        if let Some(date) = first_of_month {
+            let position = 4 + week as usize * 2;
+            for (i, c) in date.format("%b").to_string().chars().enumerate() {
+                if let Some(slot) = months.get_mut(position + i) {
+                    *slot = c;
+                }
+            }
+        }
+    }
+    let months: String = months.into_iter().collect();
+    let mut lines = vec![Line::from(Span::styled(
+        months,
+        Style::default().fg(Color::Yellow),
+    ))];
+
+    let labels = ["Mon ", "    ", "Wed ", "    ", "Fri ", "    ", "Sun "];
+    for (weekday, label) in labels.iter().enumerate() {
+        let mut row = vec![Span::styled(*label, Style::default().fg(Color::Yellow))];
+        for week in 0..weeks {
+            let day = grid_start + Days::new(week * 7 + weekday as u64);
+            if day < start || day >= end {
+                row.push(Span::raw("  "));
+            } else {
+                let amount = daily.get(&day).copied().unwrap_or(0.0);
+                row.push(Span::styled("■ ", heat_style(amount, max, day == selected)));
+            }
+        }
+        lines.push(Line::from(row));
+    }
+    lines
+}
+
+// List every transaction on the selected calendar day
+fn render_day_transactions(f: &mut Frame, app: &App, area: Rect) {
+    let day = app.calendar_date;
+    let transactions = &app.day_transactions;
+    let spent: f64 = transactions
+        .iter()
+        .filter(|t| t.amount < 0.0 && !t.pending)
+        .map(|t| t.amount.abs())
+        .sum();
+
+    let rows = transactions
+        .iter()
+        .map(|t| {
+            Row::new(vec![
+                Cell::from(format!("{:.2}$", t.amount)).style(Style::default().fg(
+                    if t.amount >= 0.0 {
+                        Color::Green
+                    } else {
+                        Color::Red
+                    },
+                )),
+                Cell::from(t.category.clone()),
+                Cell::from(if t.pending {
+                    format!("{} (pending)", t.description)
+                } else {
+                    t.description.clone()
+                }),
+            ])
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Percentage(20),
+            Constraint::Percentage(30),
+            Constraint::Percentage(50),
+        ],
+    )
+    .header(
+        Row::new(vec!["Amount", "Category", "Description"]).style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(format!(
+                " {} - {} transaction(s), spent {:.2}$ ",
+                day.format("%A %Y-%m-%d"),
+                transactions.len(),
+                spent
+            )),
+    );
+    f.render_widget(table, area);
+}
+
+// Instructions with the savings goals below them
+fn render_home(f: &mut Frame, app: &App, area: Rect) {
+    let goals = app.data.get_goals();
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(6),
+            Constraint::Length(app.bill_reminders().len().max(1) as u16 + 2),
+            Constraint::Length(goals.len().max(1) as u16 * 2 + 2),
+        ])
+        .split(area);
+    instruct(f, app, chunks[0]);
+    render_bills(
+        f,
+        app,
+        chunks[1],
+        &format!(
+            " Bills overdue or due within {} days | (↑/↓) Select  (p) Pay ",
+            REMINDER_DAYS
+        ),
+    );
+    render_goals(f, app, chunks[2]);
+}
+
+// Unpaid bills that are overdue or due within the next days
+fn render_bills(f: &mut Frame, app: &App, area: Rect, title: &str) {
+    let today = Local::now().date_naive();
+    let reminders = app.bill_reminders();
+    let items: Vec<ListItem> = if reminders.is_empty() {
+        vec![ListItem::new(format!(
+            "No bills due in the next {} days. Add bills with `fintrackr bill add`.",
+            REMINDER_DAYS
+        ))
+        .style(Style::default().fg(Color::Gray))]
+    } else {
+        reminders
+            .iter()
+            .enumerate()
+            .map(|(i, due)| {
+                let style = if i == app.bill_selected {
+                    Style::default().bg(Color::Cyan).fg(Color::Black)
+                } else if due.is_overdue(today) {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default().fg(Color::Yellow)
+                };
+                ListItem::new(format!(
+                    "{}  {:<24} {:>10.2}$  {}",
+                    due.date,
+                    due.payee,
+                    due.amount,
+                    due.status(today)
+                ))
+                .style(style)
+            })
+            .collect()
+    };
+    let list = List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(title.to_string())
+            .title_style(
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            ),
+    );
+    f.render_widget(list, area);
+}
+
+// A progress gauge per goal with the monthly contribution needed to stay on schedule
+fn render_goals(f: &mut Frame, app: &App, area: Rect) {
+    let goals = app.data.get_goals();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .title(" Savings goals ")
+        .title_style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        );
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    if goals.is_empty() {
+        f.render_widget(
+            Paragraph::new(
+                "No goals yet. Add one with `fintrackr goal add NAME TARGET --by YYYY-MM`.",
+            )
+            .style(Style::default().fg(Color::Gray)),
+            inner,
+        );
+        return;
+    }
+
+    let today = Local::now().date_naive();
+    let empty = investments::Prices::new();
+    let portfolio = investments::portfolio(
+        app.data.get_trades(),
+        app.prices.as_ref().unwrap_or(&empty),
+        today,
+    )
+    .unwrap_or_default();
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Length(2); goals.len()])
+        .split(inner);
+    for (goal, row) in goals.iter().zip(rows.iter()) {
+        let progress = goals::progress(goal, &app.transactions, &portfolio, today);
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Length(1)])
+            .split(*row);
+        let color = if progress.ahead() > -0.005 {
+            Color::Green
+        } else {
+            Color::Yellow
+        };
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(
+                    format!("{} ", goal.name),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(format!(
+                    "{:.2}$ of {:.2}$ by {} | {:.2}$/month needed | ",
+                    progress.saved, goal.target, goal.due, progress.required_monthly
+                )),
+                Span::styled(progress.status(), Style::default().fg(color)),
+            ])),
+            parts[0],
+        );
+        f.render_widget(
+            Gauge::default()
+                .gauge_style(Style::default().fg(color).bg(Color::DarkGray))
+                .ratio(progress.ratio(goal))
+                .label(format!("{:.0}%", progress.ratio(goal) * 100.0)),
+            parts[1],
+        );
+    }
+}
+
 fn instruct(f: &mut Frame, _app: &App, area: Rect) {
     // Create a vector of list items.
     let items = vec![ListItem::new(
@@ -333,10 +1796,20 @@ fn instruct(f: &mut Frame, _app: &App, area: Rect) {
     ListItem::new(
         "Use the 'Report' tab to see a chart of your income and expenses, giving you a quick view of your financial situation."
     ),
+    ListItem::new(
+        "The 'Calendar' tab shows a heatmap of daily spending; move between days with the arrow keys to list that day's transactions."
+    ),
+    ListItem::new(
+        "The 'Debts' tab shows your loans with their amortization schedules and a snowball/avalanche payoff planner."
+    ),
+    ListItem::new(
+        "The 'Budget' tab assigns your income to category envelopes until nothing is left to budget; unspent money rolls over."
+    ),
     ListItem::new("Navigating between the tabs can be done by pressing left arrow (←) and right arrow keys (→)."),
     ListItem::new(
         "To navigate between the fields inside each form tab, use Tab, Shift+Tab, up arrow key (↑), and down arrow key (↓)."
     ),
+    ListItem::new("Press Ctrl+Z to undo the latest change and Ctrl+Y to redo it, also after restarting the app."),
     ListItem::new("Press Enter to submit the current form or quit the app using Esc key." ),
 ];
     // Create a List from the items.